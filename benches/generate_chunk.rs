@@ -0,0 +1,74 @@
+//! Times how long it takes to generate chunks in the sky, at the surface, and deep underground
+//! 
+//! Sky and underground chunks use the uniform chunk fast path, so they should be much faster than surface chunks.
+//! Each case is also timed with the fast paths turned off, which is the baseline they are compared against.
+//! Shaping is timed on its own with features turned off, and the rest of the time is spent decorating.
+//! 
+//! Run with `cargo bench --bench generate_chunk`
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use minecone::types::ChunkPos;
+use minecone::worldgen::{Worldgen, WorldgenSettings};
+
+const SEED: u64 = 128947;
+/// Number of chunks along each horizontal axis to generate for each case
+const AREA_SIZE: i32 = 8;
+const ITERATIONS: u32 = 3;
+
+fn time_layer<T>(worldgen: &Worldgen, chunk_y: i32, generate: impl Fn(&Worldgen, ChunkPos) -> T) -> Duration {
+    let start = Instant::now();
+
+    for x in 0..AREA_SIZE {
+        for z in 0..AREA_SIZE {
+            black_box(generate(worldgen, ChunkPos::new(x, chunk_y, z)));
+        }
+    }
+
+    start.elapsed() / (AREA_SIZE * AREA_SIZE) as u32
+}
+
+/// Gets the average time to generate a chunk in the layer over all the iterations
+/// 
+/// Each iteration uses a new worldgen, so it can't reuse columns cached by the iteration before
+fn average_time<T>(settings: &WorldgenSettings, chunk_y: i32, generate: impl Fn(&Worldgen, ChunkPos) -> T) -> Duration {
+    // the first run is a warmup
+    time_layer(&Worldgen::new(settings.clone()), chunk_y, &generate);
+
+    let total: Duration = (0..ITERATIONS)
+        .map(|_| time_layer(&Worldgen::new(settings.clone()), chunk_y, &generate))
+        .sum();
+
+    total / ITERATIONS
+}
+
+fn main() {
+    let settings = WorldgenSettings {
+        seed: SEED,
+        ..Default::default()
+    };
+    let shape_settings = WorldgenSettings {
+        features: false,
+        ..settings.clone()
+    };
+
+    let cases = [
+        ("sky", 4),
+        ("surface", 0),
+        ("underground", -8),
+    ];
+
+    for (name, chunk_y) in cases {
+        let fast = average_time(&settings, chunk_y, |worldgen, chunk_pos| worldgen.generate_chunk(chunk_pos));
+        let shape = average_time(&shape_settings, chunk_y, |worldgen, chunk_pos| worldgen.generate_chunk(chunk_pos));
+        let decorate = fast.saturating_sub(shape);
+        let per_block = average_time(&settings, chunk_y, |worldgen, chunk_pos| worldgen.generate_chunk_per_block(chunk_pos));
+
+        println!(
+            "{name:>12}: {fast:>10.3?} per chunk ({shape:>10.3?} shaping, {decorate:>10.3?} decorating), \
+            {per_block:>10.3?} per chunk without fast paths ({:.1}x speedup)",
+            per_block.as_secs_f64() / fast.as_secs_f64(),
+        );
+    }
+}
\ No newline at end of file
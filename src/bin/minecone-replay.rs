@@ -0,0 +1,40 @@
+use std::path::PathBuf;
+use std::process;
+
+use minecone::replay::{InputRecording, run_headless_replay};
+
+const USAGE: &str = "usage: minecone-replay <recording>";
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+
+    let (Some(path), None) = (args.next(), args.next()) else {
+        eprintln!("{USAGE}");
+        process::exit(1);
+    };
+    let path = PathBuf::from(path);
+
+    let recording = match InputRecording::load(&path) {
+        Ok(recording) => recording,
+        Err(error) => {
+            eprintln!("{}: {error}", path.display());
+            process::exit(1);
+        },
+    };
+
+    println!("replaying {} frames with seed {}", recording.frames.len(), recording.worldgen.seed);
+
+    let outcome = run_headless_replay(recording);
+
+    println!("expected world hash: {:016x}", outcome.expected_hash);
+    println!("actual world hash:   {:016x}", outcome.actual_hash);
+    println!("expected camera path hash: {:016x}", outcome.expected_camera_path_hash);
+    println!("actual camera path hash:   {:016x}", outcome.actual_camera_path_hash);
+
+    if outcome.is_match() {
+        println!("replay matches");
+    } else {
+        println!("replay does not match");
+        process::exit(1);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,117 @@
+use std::net::{SocketAddr, Ipv4Addr};
+use std::path::PathBuf;
+use std::time::Duration;
+use std::process;
+
+use bevy::prelude::*;
+use bevy::app::ScheduleRunnerPlugin;
+use bevy::log::LogPlugin;
+use bevy::math::DVec3;
+
+use minecone::net::{NetServerPlugin, DEFAULT_PORT};
+use minecone::server::{MineconeServerPlugin, ServerConfig};
+use minecone::worldgen::WorldgenSettings;
+
+const TICK_DURATION: Duration = Duration::from_millis(50);
+
+const USAGE: &str = "usage: minecone-server [--port <port>] [--load-distance <chunks>] [--ticks <count>] [--worldgen-config <file>] [--seed <seed>] [<x>,<y>,<z> ...]";
+
+struct Args {
+    config: ServerConfig,
+    port: u16,
+}
+
+fn parse_point(point: &str) -> Option<DVec3> {
+    let mut coords = point.split(',').map(|n| n.trim().parse::<f64>());
+
+    let point = DVec3::new(coords.next()?.ok()?, coords.next()?.ok()?, coords.next()?.ok()?);
+
+    if coords.next().is_some() {
+        None
+    } else {
+        Some(point)
+    }
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut config = ServerConfig::default();
+    let mut port = DEFAULT_PORT;
+    let mut load_points = Vec::new();
+    let mut worldgen_config_path = None;
+    let mut seed = None;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--port" => {
+                port = args.next()
+                    .and_then(|n| n.parse::<u16>().ok())
+                    .ok_or("--port needs a port number")?;
+            },
+            "--load-distance" => {
+                let distance = args.next()
+                    .and_then(|n| n.parse::<u32>().ok())
+                    .ok_or("--load-distance needs a number of chunks")?;
+
+                config.load_distance = UVec3::splat(distance);
+            },
+            "--ticks" => {
+                let ticks = args.next()
+                    .and_then(|n| n.parse::<u64>().ok())
+                    .ok_or("--ticks needs a number of ticks")?;
+
+                config.max_ticks = Some(ticks);
+            },
+            "--worldgen-config" => {
+                worldgen_config_path = Some(PathBuf::from(args.next().ok_or("--worldgen-config needs a file")?));
+            },
+            "--seed" => {
+                seed = Some(args.next()
+                    .and_then(|n| n.parse::<u64>().ok())
+                    .ok_or("--seed needs a number")?);
+            },
+            point => {
+                load_points.push(parse_point(point).ok_or_else(|| format!("invalid load point: {point}"))?);
+            },
+        }
+    }
+
+    if !load_points.is_empty() {
+        config.load_points = load_points;
+    }
+
+    // the seed given on the command line overrides the one in the config
+    if let Some(path) = worldgen_config_path {
+        config.worldgen = WorldgenSettings::load(&path).map_err(|error| format!("{}: {error}", path.display()))?;
+    }
+
+    if let Some(seed) = seed {
+        config.worldgen.seed = seed;
+    }
+
+    Ok(Args {
+        config,
+        port,
+    })
+}
+
+fn main() {
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(error) => {
+            eprintln!("{error}\n{USAGE}");
+            process::exit(1);
+        },
+    };
+
+    App::new()
+        .add_plugins((
+            MinimalPlugins.set(ScheduleRunnerPlugin::run_loop(TICK_DURATION)),
+            LogPlugin::default(),
+        ))
+        .add_plugins((
+            MineconeServerPlugin { config: args.config },
+            NetServerPlugin { address: SocketAddr::from((Ipv4Addr::UNSPECIFIED, args.port)) },
+        ))
+        .run();
+}
\ No newline at end of file
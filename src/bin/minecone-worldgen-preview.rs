@@ -0,0 +1,252 @@
+use std::path::{Path, PathBuf};
+use std::process;
+
+use image::{DynamicImage, GrayImage, Luma, Rgb, RgbImage};
+
+use minecone::blocks::BlockType;
+use minecone::worldgen::{BiomeType, ColumnSample, Worldgen, WorldgenSettings};
+
+const USAGE: &str = "usage: minecone-worldgen-preview [--worldgen-config <file>] [--seed <seed>] [--area <x>,<z>,<width>,<depth>] [--out <dir>]";
+
+/// Reads one of the noise values out of a column sample
+type NoiseChannel = fn(&ColumnSample) -> f64;
+
+const DEFAULT_AREA: Area = Area {
+    x: -256,
+    z: -256,
+    width: 512,
+    depth: 512,
+};
+
+/// Columns of the world to preview, each column is 1 pixel
+struct Area {
+    x: i32,
+    z: i32,
+    width: u32,
+    depth: u32,
+}
+
+struct Args {
+    worldgen: WorldgenSettings,
+    area: Area,
+    out_dir: PathBuf,
+}
+
+fn parse_area(area: &str) -> Option<Area> {
+    let mut values = area.split(',').map(|n| n.trim());
+
+    let area = Area {
+        x: values.next()?.parse().ok()?,
+        z: values.next()?.parse().ok()?,
+        width: values.next()?.parse().ok()?,
+        depth: values.next()?.parse().ok()?,
+    };
+
+    if values.next().is_some() || area.width == 0 || area.depth == 0 {
+        None
+    } else {
+        Some(area)
+    }
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut worldgen_config_path = None;
+    let mut seed = None;
+    let mut area = DEFAULT_AREA;
+    let mut out_dir = PathBuf::from("worldgen-preview");
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--worldgen-config" => {
+                worldgen_config_path = Some(PathBuf::from(args.next().ok_or("--worldgen-config needs a file")?));
+            },
+            "--seed" => {
+                seed = Some(args.next()
+                    .and_then(|n| n.parse::<u64>().ok())
+                    .ok_or("--seed needs a number")?);
+            },
+            "--area" => {
+                area = args.next()
+                    .as_deref()
+                    .and_then(parse_area)
+                    .ok_or("--area needs an x, z, width and depth")?;
+            },
+            "--out" => {
+                out_dir = PathBuf::from(args.next().ok_or("--out needs a directory")?);
+            },
+            arg => return Err(format!("unknown argument: {arg}")),
+        }
+    }
+
+    // the seed given on the command line overrides the one in the config
+    let mut worldgen = match worldgen_config_path {
+        Some(path) => WorldgenSettings::load(&path).map_err(|error| format!("{}: {error}", path.display()))?,
+        None => WorldgenSettings::default(),
+    };
+
+    if let Some(seed) = seed {
+        worldgen.seed = seed;
+    }
+
+    Ok(Args {
+        worldgen,
+        area,
+        out_dir,
+    })
+}
+
+fn biome_color(biome: BiomeType) -> Rgb<u8> {
+    match biome {
+        BiomeType::Grasslands => Rgb([86, 160, 60]),
+        BiomeType::Desert => Rgb([222, 200, 120]),
+        BiomeType::Mountains => Rgb([130, 130, 130]),
+        BiomeType::Tundra => Rgb([220, 235, 245]),
+        BiomeType::Swamp => Rgb([70, 90, 50]),
+        BiomeType::Ocean => Rgb([40, 70, 170]),
+    }
+}
+
+fn block_color(block: BlockType) -> Rgb<u8> {
+    match block {
+        BlockType::Air => Rgb([0, 0, 0]),
+        BlockType::Dirt => Rgb([120, 85, 55]),
+        BlockType::Grass => Rgb([90, 150, 60]),
+        BlockType::Stone => Rgb([125, 125, 125]),
+        BlockType::Sand => Rgb([220, 205, 150]),
+        BlockType::Sandstone => Rgb([200, 180, 120]),
+        BlockType::Snow => Rgb([240, 245, 250]),
+        BlockType::CoalOre => Rgb([50, 50, 50]),
+        BlockType::IronOre => Rgb([180, 140, 110]),
+        BlockType::GoldOre => Rgb([230, 200, 50]),
+        BlockType::Log => Rgb([100, 75, 45]),
+        BlockType::Leaves => Rgb([50, 110, 40]),
+        BlockType::Water => Rgb([50, 90, 200]),
+        BlockType::StoneSlab => Rgb([125, 125, 125]),
+    }
+}
+
+/// Maps a noise value from -1 to 1 onto a shade of gray
+fn noise_shade(value: f64) -> Luma<u8> {
+    Luma([((value.clamp(-1.0, 1.0) + 1.0) * 127.5).round() as u8])
+}
+
+fn save_image(image: DynamicImage, path: &Path) -> Result<(), String> {
+    image.save(path).map_err(|error| format!("{}: {error}", path.display()))?;
+    println!("wrote {}", path.display());
+    Ok(())
+}
+
+/// Writes every preview image, each pixel is only based on the column it shows so the images are the same for the same seed
+fn write_previews(args: &Args, samples: &[ColumnSample]) -> Result<(), String> {
+    let Area { width, depth, .. } = args.area;
+    let sample = |x: u32, y: u32| &samples[(y * width + x) as usize];
+
+    std::fs::create_dir_all(&args.out_dir).map_err(|error| format!("{}: {error}", args.out_dir.display()))?;
+
+    let min_height = samples.iter().map(|sample| sample.height).min().unwrap();
+    let max_height = samples.iter().map(|sample| sample.height).max().unwrap();
+    println!("heights range from {min_height} to {max_height}");
+
+    let height_range = (max_height - min_height).max(1) as f64;
+    let heightmap = GrayImage::from_fn(width, depth, |x, y| {
+        Luma([((sample(x, y).height - min_height) as f64 / height_range * 255.0).round() as u8])
+    });
+    save_image(heightmap.into(), &args.out_dir.join("height.png"))?;
+
+    let biomes = RgbImage::from_fn(width, depth, |x, y| biome_color(sample(x, y).biome));
+    save_image(biomes.into(), &args.out_dir.join("biome.png"))?;
+
+    let surface = RgbImage::from_fn(width, depth, |x, y| block_color(sample(x, y).surface_block));
+    save_image(surface.into(), &args.out_dir.join("surface.png"))?;
+
+    let channels: [(&str, NoiseChannel); 4] = [
+        ("temperature", |sample| sample.conditions.temperature),
+        ("humidity", |sample| sample.conditions.humidity),
+        ("special_factor", |sample| sample.conditions.special_factor),
+        ("biome_height", |sample| sample.conditions.biome_height),
+    ];
+
+    for (name, channel) in channels {
+        let noise = GrayImage::from_fn(width, depth, |x, y| noise_shade(channel(sample(x, y))));
+        save_image(noise.into(), &args.out_dir.join(format!("{name}.png")))?;
+    }
+
+    Ok(())
+}
+
+/// Samples every column in the area, image x is world x and image y is world z
+fn sample_area(args: &Args) -> Vec<ColumnSample> {
+    let worldgen = Worldgen::new(args.worldgen.clone());
+    let Area { x, z, width, depth } = args.area;
+
+    worldgen.sample_area(x, z, width, depth)
+}
+
+fn main() {
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(error) => {
+            eprintln!("{error}\n{USAGE}");
+            process::exit(1);
+        },
+    };
+
+    let area = &args.area;
+    println!("previewing {}x{} columns from {},{} with seed {}", area.width, area.depth, area.x, area.z, args.worldgen.seed);
+
+    let samples = sample_area(&args);
+
+    if let Err(error) = write_previews(&args, &samples) {
+        eprintln!("{error}");
+        process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(out_dir: PathBuf) -> Vec<(PathBuf, Vec<u8>)> {
+        let args = Args {
+            worldgen: WorldgenSettings::default(),
+            area: Area {
+                x: -40,
+                z: 10,
+                width: 48,
+                depth: 36,
+            },
+            out_dir,
+        };
+
+        write_previews(&args, &sample_area(&args)).unwrap();
+
+        let mut images = std::fs::read_dir(&args.out_dir).unwrap()
+            .map(|entry| {
+                let path = entry.unwrap().path();
+                let bytes = std::fs::read(&path).unwrap();
+                (PathBuf::from(path.file_name().unwrap()), bytes)
+            })
+            .collect::<Vec<_>>();
+        images.sort();
+
+        std::fs::remove_dir_all(&args.out_dir).unwrap();
+
+        images
+    }
+
+    #[test]
+    fn rendering_twice_gives_identical_images() {
+        let out_dir = std::env::temp_dir().join(format!("minecone-worldgen-preview-test-{}", process::id()));
+
+        let first = render(out_dir.join("first"));
+        let second = render(out_dir.join("second"));
+        std::fs::remove_dir_all(&out_dir).unwrap();
+
+        assert_eq!(first.len(), 7);
+        for ((name, bytes), (second_name, second_bytes)) in first.iter().zip(second.iter()) {
+            assert_eq!(name, second_name);
+            assert!(bytes == second_bytes, "{} was different the second time", name.display());
+        }
+    }
+}
\ No newline at end of file
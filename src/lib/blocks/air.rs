@@ -10,7 +10,12 @@ impl BaseBlock for Air {
 
     fn properties() -> BlockProperties {
         BlockProperties {
-            max_hp: 0
+            max_hp: 0,
+            blast_resistance: 0.0,
         }
     }
+
+    fn collision_shape() -> CollisionShape {
+        CollisionShape::Empty
+    }
 }
\ No newline at end of file
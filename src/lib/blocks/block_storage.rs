@@ -56,6 +56,17 @@ impl BlockStorage {
         }
     }
 
+    /// Returns the number of bytes used by this block storage, including the heap allocated blocks
+    pub fn memory_usage(&self) -> usize {
+        let inner_size = if self.inner.is_some() {
+            std::mem::size_of::<[[[Block; CHUNK_SIZE]; CHUNK_SIZE]; CHUNK_SIZE]>()
+        } else {
+            0
+        };
+
+        std::mem::size_of::<Self>() + inner_size
+    }
+
     /// Returns true if this block storage is only holding air
     pub fn is_empty(&self) -> bool {
         self.inner.is_none()
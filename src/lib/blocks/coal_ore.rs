@@ -0,0 +1,21 @@
+use super::*;
+
+#[derive(Default)]
+pub struct CoalOre;
+
+impl BaseBlock for CoalOre {
+    fn model(texture_builder: &mut TextureBuilder) -> BlockModel {
+        let stone_face = texture_builder.image("textures/stone.png");
+        let ore_face = texture_builder.image("textures/coal_ore.png");
+        let ore_face = texture_builder.overlay(ore_face, stone_face);
+
+        BlockModel::new(BlockFace::Full(ore_face))
+    }
+
+    fn properties() -> BlockProperties {
+        BlockProperties {
+            max_hp: 100,
+            blast_resistance: 6.0,
+        }
+    }
+}
\ No newline at end of file
@@ -13,6 +13,7 @@ impl BaseBlock for Dirt {
     fn properties() -> BlockProperties {
         BlockProperties {
             max_hp: 10,
+            blast_resistance: 0.5,
         }
     }
 }
\ No newline at end of file
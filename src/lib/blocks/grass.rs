@@ -22,6 +22,7 @@ impl BaseBlock for Grass {
     fn properties() -> BlockProperties {
         BlockProperties {
             max_hp: 10,
+            blast_resistance: 0.6,
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,19 @@
+use super::*;
+
+#[derive(Default)]
+pub struct Leaves;
+
+impl BaseBlock for Leaves {
+    fn model(texture_builder: &mut TextureBuilder) -> BlockModel {
+        let leaves_face = texture_builder.image("textures/leaves.png");
+
+        BlockModel::new(BlockFace::Full(leaves_face))
+    }
+
+    fn properties() -> BlockProperties {
+        BlockProperties {
+            max_hp: 3,
+            blast_resistance: 0.2,
+        }
+    }
+}
\ No newline at end of file
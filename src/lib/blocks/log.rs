@@ -0,0 +1,22 @@
+use super::*;
+
+#[derive(Default)]
+pub struct Log;
+
+impl BaseBlock for Log {
+    fn model(texture_builder: &mut TextureBuilder) -> BlockModel {
+        let side_face = texture_builder.image("textures/log_side.png");
+        let top_face = texture_builder.image("textures/log_top.png");
+
+        BlockModel::new(BlockFace::Full(side_face))
+            .set_top(BlockFace::Full(top_face))
+            .set_bottom(BlockFace::Full(top_face))
+    }
+
+    fn properties() -> BlockProperties {
+        BlockProperties {
+            max_hp: 30,
+            blast_resistance: 2.0,
+        }
+    }
+}
\ No newline at end of file
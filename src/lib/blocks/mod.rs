@@ -1,3 +1,4 @@
+use bevy::prelude::Vec3;
 use bytemuck::{bytes_of, bytes_of_mut};
 
 use crate::render::{TextureBuilder, BlockModel, BlockFace};
@@ -7,12 +8,32 @@ pub use block_storage::BlockStorage;
 pub mod utils;
 mod air;
 use air::Air;
+mod coal_ore;
+use coal_ore::CoalOre;
 mod dirt;
 use dirt::Dirt;
+mod gold_ore;
+use gold_ore::GoldOre;
 mod grass;
 use grass::Grass;
+mod iron_ore;
+use iron_ore::IronOre;
+mod leaves;
+use leaves::Leaves;
+mod log;
+use log::Log;
+mod sand;
+use sand::Sand;
+mod sandstone;
+use sandstone::Sandstone;
+mod snow;
+use snow::Snow;
 mod stone;
 use stone::Stone;
+mod stone_slab;
+use stone_slab::StoneSlab;
+mod water;
+use water::Water;
 
 const BLOCK_ID_MASK: u32 = 0xfff;
 const INLINE_BLOCK_HP_MASK: u32 = 0xfff000;
@@ -71,6 +92,10 @@ impl Block {
     pub fn is_air(&self) -> bool {
         self.block_type() == BlockType::Air
     }
+
+    pub fn is_replaceable(&self) -> bool {
+        self.block_type().is_replaceable()
+    }
 }
 
 impl Default for Block {
@@ -83,12 +108,35 @@ impl Default for Block {
 #[derive(Debug, Clone, Copy)]
 pub struct BlockProperties {
     pub max_hp: u16,
+    /// How much the block weakens explosions passing through it
+    pub blast_resistance: f32,
+}
+
+/// The shape entities collide with when touching a block
+#[derive(Debug, Clone, Copy)]
+pub enum CollisionShape {
+    /// Entities can move freely through the block
+    Empty,
+    /// The block is a solid cube
+    Full,
+    /// The block is made of a list of boxes, given as min and max corners,
+    /// with (0, 0, 0) being the minimum corner of the block and (1, 1, 1) being the maximum corner
+    Boxes(&'static [(Vec3, Vec3)]),
 }
 
 // Inline and extended blocks must implement this trait
 trait BaseBlock {
     fn model(texture_builder: &mut TextureBuilder) -> BlockModel;
     fn properties() -> BlockProperties;
+
+    fn collision_shape() -> CollisionShape {
+        CollisionShape::Full
+    }
+
+    /// Blocks with gravity fall when the block under them is removed
+    fn has_gravity() -> bool {
+        false
+    }
 }
 
 /// Blocks which don't need any extra state should implement this trait
@@ -144,6 +192,28 @@ macro_rules! register_blocks {
                 }
             }
 
+            pub fn collision_shape(&self) -> CollisionShape {
+                match self {
+                    $(
+                        Self::$inline_blocks => $inline_blocks::collision_shape(),
+                    )*
+                    $(
+                        Self::$extended_blocks => $extended_blocks::collision_shape(),
+                    )*
+                }
+            }
+
+            pub fn has_gravity(&self) -> bool {
+                match self {
+                    $(
+                        Self::$inline_blocks => $inline_blocks::has_gravity(),
+                    )*
+                    $(
+                        Self::$extended_blocks => $extended_blocks::has_gravity(),
+                    )*
+                }
+            }
+
             pub fn is_inline(&self) -> bool {
                 match self {
                     $(
@@ -164,8 +234,30 @@ register_blocks! {
         Dirt,
         Grass,
         Stone,
+        Sand,
+        Sandstone,
+        Snow,
+        CoalOre,
+        IronOre,
+        GoldOre,
+        Log,
+        Leaves,
+        Water,
+        StoneSlab,
     },
     extended {
         ,
     },
+}
+
+impl BlockType {
+    /// Returns true if this block can be held as an item and placed in the world
+    pub fn is_placeable(&self) -> bool {
+        !self.is_replaceable()
+    }
+
+    /// Returns true if placing a block here just replaces this block, and it can't be broken
+    pub fn is_replaceable(&self) -> bool {
+        matches!(self, BlockType::Air | BlockType::Water)
+    }
 }
\ No newline at end of file
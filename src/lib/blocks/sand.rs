@@ -0,0 +1,23 @@
+use super::*;
+
+#[derive(Default)]
+pub struct Sand;
+
+impl BaseBlock for Sand {
+    fn model(texture_builder: &mut TextureBuilder) -> BlockModel {
+        let sand_face = texture_builder.image("textures/sand.png");
+
+        BlockModel::new(BlockFace::Full(sand_face))
+    }
+
+    fn properties() -> BlockProperties {
+        BlockProperties {
+            max_hp: 8,
+            blast_resistance: 0.5,
+        }
+    }
+
+    fn has_gravity() -> bool {
+        true
+    }
+}
\ No newline at end of file
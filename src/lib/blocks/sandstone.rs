@@ -0,0 +1,19 @@
+use super::*;
+
+#[derive(Default)]
+pub struct Sandstone;
+
+impl BaseBlock for Sandstone {
+    fn model(texture_builder: &mut TextureBuilder) -> BlockModel {
+        let sandstone_face = texture_builder.image("textures/sandstone.png");
+
+        BlockModel::new(BlockFace::Full(sandstone_face))
+    }
+
+    fn properties() -> BlockProperties {
+        BlockProperties {
+            max_hp: 50,
+            blast_resistance: 0.8,
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,19 @@
+use super::*;
+
+#[derive(Default)]
+pub struct Snow;
+
+impl BaseBlock for Snow {
+    fn model(texture_builder: &mut TextureBuilder) -> BlockModel {
+        let snow_face = texture_builder.image("textures/snow.png");
+
+        BlockModel::new(BlockFace::Full(snow_face))
+    }
+
+    fn properties() -> BlockProperties {
+        BlockProperties {
+            max_hp: 5,
+            blast_resistance: 0.1,
+        }
+    }
+}
\ No newline at end of file
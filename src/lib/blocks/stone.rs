@@ -13,6 +13,7 @@ impl BaseBlock for Stone {
     fn properties() -> BlockProperties {
         BlockProperties {
             max_hp: 80,
+            blast_resistance: 6.0,
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,27 @@
+use super::*;
+
+const SLAB_BOXES: &[(Vec3, Vec3)] = &[(Vec3::ZERO, Vec3::new(1.0, 0.5, 1.0))];
+
+/// The bottom half of a stone block, which is low enough to walk up onto
+#[derive(Default)]
+pub struct StoneSlab;
+
+impl BaseBlock for StoneSlab {
+    fn model(texture_builder: &mut TextureBuilder) -> BlockModel {
+        let stone_face = texture_builder.image("textures/stone.png");
+
+        // there are no half height faces yet, so this is drawn as a full block
+        BlockModel::new(BlockFace::Full(stone_face))
+    }
+
+    fn properties() -> BlockProperties {
+        BlockProperties {
+            max_hp: 80,
+            blast_resistance: 6.0,
+        }
+    }
+
+    fn collision_shape() -> CollisionShape {
+        CollisionShape::Boxes(SLAB_BOXES)
+    }
+}
\ No newline at end of file
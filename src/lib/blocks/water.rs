@@ -0,0 +1,23 @@
+use super::*;
+
+#[derive(Default)]
+pub struct Water;
+
+impl BaseBlock for Water {
+    fn model(texture_builder: &mut TextureBuilder) -> BlockModel {
+        let water_face = texture_builder.image("textures/water.png");
+
+        BlockModel::new(BlockFace::Transparent(water_face))
+    }
+
+    fn properties() -> BlockProperties {
+        BlockProperties {
+            max_hp: 0,
+            blast_resistance: 100.0,
+        }
+    }
+
+    fn collision_shape() -> CollisionShape {
+        CollisionShape::Empty
+    }
+}
\ No newline at end of file
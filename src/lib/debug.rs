@@ -1,19 +1,50 @@
 use bevy::prelude::*;
 use bevy::pbr::wireframe::WireframeConfig;
 
+use crate::types::TransformExt;
+use crate::world::World;
+use crate::GameSet;
+
+/// Maximum distance away an explosion can be created with the debug explode key
+const DEBUG_EXPLOSION_REACH: f32 = 50.0;
+const DEBUG_EXPLOSION_POWER: f32 = 8.0;
+
 fn toggle_wireframe(
-    mut wireframe_config: ResMut<WireframeConfig>,
+    wireframe_config: Option<ResMut<WireframeConfig>>,
     input: Res<Input<KeyCode>>,
 ) {
+    // the wireframe plugin is not added when running headless
+    let Some(mut wireframe_config) = wireframe_config else {
+        return;
+    };
+
     if input.just_pressed(KeyCode::P) {
         wireframe_config.global = !wireframe_config.global;
     }
 }
 
+/// Creates an explosion where the camera is looking
+fn debug_explode(
+    cameras: Query<&GlobalTransform, With<Camera3d>>,
+    world: Res<World>,
+    input: Res<Input<KeyCode>>,
+) {
+    if !input.just_pressed(KeyCode::X) {
+        return;
+    }
+
+    for transform in cameras.iter() {
+        if let Some(hit_result) = world.raycast(transform.to_ray(), DEBUG_EXPLOSION_REACH) {
+            world.explode(hit_result.position, DEBUG_EXPLOSION_POWER);
+        }
+    }
+}
+
 pub struct DebugPlugin;
 
 impl Plugin for DebugPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, toggle_wireframe);
+        app.add_systems(Update, toggle_wireframe)
+            .add_systems(Update, debug_explode.in_set(GameSet::Main));
     }
 }
\ No newline at end of file
@@ -0,0 +1,181 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use bevy::prelude::*;
+use bevy::diagnostic::{Diagnostic, DiagnosticId, Diagnostics, RegisterDiagnostic};
+use bevy::time::common_conditions::on_timer;
+
+use crate::world::{World, EcsChunk};
+
+/// Counters for tasks of one kind which are running on the task pool
+/// 
+/// These are updated from the worker threads, and read once per frame by the diagnostics system
+pub struct TaskStats {
+    pending: AtomicU64,
+    running: AtomicU64,
+    /// Total time taken by tasks completed since the last time the average was taken
+    total_nanos: AtomicU64,
+    /// Number of tasks completed since the last time the average was taken
+    completed: AtomicU64,
+}
+
+impl TaskStats {
+    const fn new() -> Self {
+        TaskStats {
+            pending: AtomicU64::new(0),
+            running: AtomicU64::new(0),
+            total_nanos: AtomicU64::new(0),
+            completed: AtomicU64::new(0),
+        }
+    }
+
+    /// Marks that a task has been queued on the task pool
+    pub fn queue(&self) {
+        self.pending.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Runs the work of a previously queued task, recording how long it took
+    pub fn run<T>(&self, f: impl FnOnce() -> T) -> T {
+        self.pending.fetch_sub(1, Ordering::Relaxed);
+        self.running.fetch_add(1, Ordering::Relaxed);
+
+        let start = Instant::now();
+        let out = f();
+        let elapsed = start.elapsed();
+
+        self.total_nanos.fetch_add(elapsed.as_nanos() as u64, Ordering::Relaxed);
+        self.completed.fetch_add(1, Ordering::Relaxed);
+        self.running.fetch_sub(1, Ordering::Relaxed);
+
+        out
+    }
+
+    pub fn pending(&self) -> u64 {
+        self.pending.load(Ordering::Relaxed)
+    }
+
+    pub fn running(&self) -> u64 {
+        self.running.load(Ordering::Relaxed)
+    }
+
+    /// Returns the average time in milliseconds of tasks that completed since the last call,
+    /// or None if no tasks completed
+    fn take_average_ms(&self) -> Option<f64> {
+        let completed = self.completed.swap(0, Ordering::Relaxed);
+        let total_nanos = self.total_nanos.swap(0, Ordering::Relaxed);
+
+        if completed == 0 {
+            None
+        } else {
+            Some(total_nanos as f64 / completed as f64 / 1_000_000.0)
+        }
+    }
+}
+
+/// Stats for chunk generation tasks
+pub static GENERATE_CHUNK_STATS: TaskStats = TaskStats::new();
+/// Stats for chunk meshing tasks
+pub static GENERATE_MESH_STATS: TaskStats = TaskStats::new();
+
+const BYTES_PER_MIB: f64 = 1024.0 * 1024.0;
+
+/// Time between measurements of chunk diagnostics, since measuring them has to lock every loaded chunk
+const CHUNK_DIAGNOSTICS_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Adds diagnostics for the world and the chunk loading and meshing pipeline
+pub struct WorldDiagnosticsPlugin;
+
+impl WorldDiagnosticsPlugin {
+    pub const LOADED_CHUNKS: DiagnosticId = DiagnosticId::from_u128(0x5c1e4a2b_8f3d_4e6a_9b17_2d4c6e8f0a01);
+    pub const NON_EMPTY_CHUNKS: DiagnosticId = DiagnosticId::from_u128(0x5c1e4a2b_8f3d_4e6a_9b17_2d4c6e8f0a02);
+    pub const BLOCK_STORAGE_MEMORY: DiagnosticId = DiagnosticId::from_u128(0x5c1e4a2b_8f3d_4e6a_9b17_2d4c6e8f0a03);
+    pub const GEN_TASKS_PENDING: DiagnosticId = DiagnosticId::from_u128(0x5c1e4a2b_8f3d_4e6a_9b17_2d4c6e8f0a04);
+    pub const GEN_TASKS_RUNNING: DiagnosticId = DiagnosticId::from_u128(0x5c1e4a2b_8f3d_4e6a_9b17_2d4c6e8f0a05);
+    pub const MESH_TASKS_PENDING: DiagnosticId = DiagnosticId::from_u128(0x5c1e4a2b_8f3d_4e6a_9b17_2d4c6e8f0a06);
+    pub const MESH_TASKS_RUNNING: DiagnosticId = DiagnosticId::from_u128(0x5c1e4a2b_8f3d_4e6a_9b17_2d4c6e8f0a07);
+    pub const GENERATE_CHUNK_TIME: DiagnosticId = DiagnosticId::from_u128(0x5c1e4a2b_8f3d_4e6a_9b17_2d4c6e8f0a08);
+    pub const GENERATE_MESH_TIME: DiagnosticId = DiagnosticId::from_u128(0x5c1e4a2b_8f3d_4e6a_9b17_2d4c6e8f0a09);
+    pub const MESH_VERTICES: DiagnosticId = DiagnosticId::from_u128(0x5c1e4a2b_8f3d_4e6a_9b17_2d4c6e8f0a0a);
+    pub const MESH_INDICES: DiagnosticId = DiagnosticId::from_u128(0x5c1e4a2b_8f3d_4e6a_9b17_2d4c6e8f0a0b);
+    pub const DIRTY_QUEUE_LENGTH: DiagnosticId = DiagnosticId::from_u128(0x5c1e4a2b_8f3d_4e6a_9b17_2d4c6e8f0a0c);
+
+    fn chunk_diagnostics(mut diagnostics: Diagnostics, world: Res<World>) {
+        let mut non_empty_chunks = 0;
+        let mut block_storage_memory = 0;
+
+        for chunk in world.chunks.values() {
+            let chunk_data = chunk.data.read();
+
+            if !chunk_data.blocks.is_empty() {
+                non_empty_chunks += 1;
+            }
+
+            block_storage_memory += chunk_data.blocks.memory_usage();
+        }
+
+        diagnostics.add_measurement(Self::LOADED_CHUNKS, || world.chunks.len() as f64);
+        diagnostics.add_measurement(Self::NON_EMPTY_CHUNKS, || non_empty_chunks as f64);
+        diagnostics.add_measurement(Self::BLOCK_STORAGE_MEMORY, || block_storage_memory as f64 / BYTES_PER_MIB);
+        diagnostics.add_measurement(Self::DIRTY_QUEUE_LENGTH, || world.dirty_chunk_count() as f64);
+    }
+
+    fn task_diagnostics(mut diagnostics: Diagnostics) {
+        diagnostics.add_measurement(Self::GEN_TASKS_PENDING, || GENERATE_CHUNK_STATS.pending() as f64);
+        diagnostics.add_measurement(Self::GEN_TASKS_RUNNING, || GENERATE_CHUNK_STATS.running() as f64);
+        diagnostics.add_measurement(Self::MESH_TASKS_PENDING, || GENERATE_MESH_STATS.pending() as f64);
+        diagnostics.add_measurement(Self::MESH_TASKS_RUNNING, || GENERATE_MESH_STATS.running() as f64);
+
+        // only add a measurement when tasks actually finished, so idle frames don't pull the average to 0
+        if let Some(average) = GENERATE_CHUNK_STATS.take_average_ms() {
+            diagnostics.add_measurement(Self::GENERATE_CHUNK_TIME, || average);
+        }
+
+        if let Some(average) = GENERATE_MESH_STATS.take_average_ms() {
+            diagnostics.add_measurement(Self::GENERATE_MESH_TIME, || average);
+        }
+    }
+
+    fn mesh_diagnostics(
+        mut diagnostics: Diagnostics,
+        meshes: Option<Res<Assets<Mesh>>>,
+        chunk_meshes: Query<&Handle<Mesh>, With<EcsChunk>>,
+    ) {
+        // there are no meshes when running headless
+        let Some(meshes) = meshes else {
+            return;
+        };
+
+        let mut vertex_count = 0;
+        let mut index_count = 0;
+
+        for mesh in chunk_meshes.iter().filter_map(|handle| meshes.get(handle)) {
+            vertex_count += mesh.count_vertices();
+            index_count += mesh.indices().map_or(0, |indices| indices.len());
+        }
+
+        diagnostics.add_measurement(Self::MESH_VERTICES, || vertex_count as f64);
+        diagnostics.add_measurement(Self::MESH_INDICES, || index_count as f64);
+    }
+}
+
+impl Plugin for WorldDiagnosticsPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_diagnostic(Diagnostic::new(Self::LOADED_CHUNKS, "loaded_chunks", 1))
+            .register_diagnostic(Diagnostic::new(Self::NON_EMPTY_CHUNKS, "non_empty_chunks", 1))
+            .register_diagnostic(Diagnostic::new(Self::BLOCK_STORAGE_MEMORY, "block_storage_memory", 1).with_suffix("MiB"))
+            .register_diagnostic(Diagnostic::new(Self::GEN_TASKS_PENDING, "gen_tasks_pending", 1))
+            .register_diagnostic(Diagnostic::new(Self::GEN_TASKS_RUNNING, "gen_tasks_running", 1))
+            .register_diagnostic(Diagnostic::new(Self::MESH_TASKS_PENDING, "mesh_tasks_pending", 1))
+            .register_diagnostic(Diagnostic::new(Self::MESH_TASKS_RUNNING, "mesh_tasks_running", 1))
+            .register_diagnostic(Diagnostic::new(Self::GENERATE_CHUNK_TIME, "generate_chunk_time", 20).with_suffix("ms"))
+            .register_diagnostic(Diagnostic::new(Self::GENERATE_MESH_TIME, "generate_mesh_time", 20).with_suffix("ms"))
+            .register_diagnostic(Diagnostic::new(Self::MESH_VERTICES, "chunk_mesh_vertices", 1))
+            .register_diagnostic(Diagnostic::new(Self::MESH_INDICES, "chunk_mesh_indices", 1))
+            .register_diagnostic(Diagnostic::new(Self::DIRTY_QUEUE_LENGTH, "dirty_queue_length", 1))
+            .add_systems(Update, (
+                Self::chunk_diagnostics.run_if(on_timer(CHUNK_DIAGNOSTICS_INTERVAL)),
+                Self::task_diagnostics,
+                Self::mesh_diagnostics,
+            ));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,79 @@
+use bevy::prelude::*;
+
+use crate::types::*;
+use crate::blocks::BlockType;
+use crate::player::{Inventory, player_bounding_box};
+use crate::world::World;
+use super::*;
+
+/// Maximum distance in meters from the player a block can be placed at
+const BLOCK_ITEM_REACH: f32 = 5.0;
+
+/// An item which places a block of the given type in the world
+#[derive(Debug, Component)]
+pub struct BlockItem {
+    pub block_type: BlockType,
+}
+
+impl BlockItem {
+    fn properties() -> ItemProperties {
+        ItemProperties {
+            use_time: 8,
+            use_button: MouseButton::Right,
+        }
+    }
+
+    pub fn spawn_bundle(commands: &mut Commands, block_type: BlockType) -> Entity {
+        assert!(block_type.is_placeable(), "block item created for block which can't be placed");
+
+        commands.spawn((
+            BlockItem {
+                block_type,
+            },
+            WeaponUseTime::from_properties(&Self::properties()),
+            TransformBundle::default(),
+        )).id()
+    }
+
+    pub fn item_type(&self) -> ItemType {
+        ItemType::Block(self.block_type)
+    }
+
+    pub fn add_systems(app: &mut App) {
+        app.add_systems(Update, place_block.in_set(ItemUseSet));
+    }
+}
+
+fn place_block(
+    mut items: Query<(&Parent, &GlobalTransform, &mut WeaponUseTime, &BlockItem)>,
+    mut players: Query<(&mut Inventory, &GlobalTransform)>,
+    world: Res<World>,
+) {
+    for (player, transform, mut use_time, block_item) in items.iter_mut() {
+        if !use_time.try_use() {
+            continue;
+        }
+
+        let Ok((mut inventory, player_transform)) = players.get_mut(player.get()) else {
+            continue;
+        };
+
+        let Some(hit_result) = world.raycast(transform.to_ray(), BLOCK_ITEM_REACH) else {
+            continue;
+        };
+
+        // the place position might be in a chunk that is not loaded yet
+        if !world.get_block(hit_result.place_pos).is_some_and(|block| block.is_replaceable()) {
+            continue;
+        }
+
+        let player_box = player_bounding_box(player_transform.translation());
+        if world.block_bounding_box(hit_result.place_pos).intersects(&player_box) {
+            continue;
+        }
+
+        if inventory.consume_selected(block_item.item_type()) {
+            world.new_block(hit_result.place_pos, block_item.block_type);
+        }
+    }
+}
\ No newline at end of file
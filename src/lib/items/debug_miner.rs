@@ -29,6 +29,7 @@ impl Item for DebugMiner {
     fn properties() -> ItemProperties {
         ItemProperties {
             use_time: 0,
+            use_button: MouseButton::Left,
         }
     }
 
@@ -3,10 +3,13 @@ use bevy::prelude::*;
 mod systems;
 pub use systems::*;
 
+mod block_item;
+use block_item::BlockItem;
 mod debug_miner;
 use debug_miner::DebugMiner;
 
 use crate::GameSet;
+use crate::blocks::BlockType;
 
 #[derive(Debug)]
 pub struct ItemStack {
@@ -17,6 +20,8 @@ pub struct ItemStack {
 #[derive(Debug)]
 struct ItemProperties {
     use_time: usize,
+    /// The mouse button that must be held to use the item
+    use_button: MouseButton,
 }
 
 /// Items should implement this trait to work with the register_items macro
@@ -27,7 +32,7 @@ trait Item: Component + Default {
     fn spawn_bundle(commands: &mut Commands) -> Entity {
         commands.spawn((
             Self::default(),
-            WeaponUseTime::from_use_time(Self::properties().use_time),
+            WeaponUseTime::from_properties(&Self::properties()),
             TransformBundle::default(),
         )).id()
     }
@@ -40,6 +45,8 @@ macro_rules! register_items {
             $(
                 $items,
             )*
+            /// A block which can be placed in the world
+            Block(BlockType),
         }
 
         impl ItemType {
@@ -48,6 +55,7 @@ macro_rules! register_items {
                     $(
                         Self::$items => $items::spawn_bundle(commands),
                     )*
+                    Self::Block(block_type) => BlockItem::spawn_bundle(commands, *block_type),
                 }
             }
         }
@@ -64,6 +72,7 @@ macro_rules! register_items {
             $(
                 $items::add_systems(app);
             )*
+            BlockItem::add_systems(app);
         }
     };
 }
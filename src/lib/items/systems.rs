@@ -2,6 +2,8 @@
 
 use bevy::prelude::*;
 
+use super::ItemProperties;
+
 #[derive(Debug, Component)]
 pub struct WeaponUseTime {
     /// The use time of the tool / weapon
@@ -12,17 +14,24 @@ pub struct WeaponUseTime {
     remaining_cooldown_time: usize,
     /// True if the player is currently attemtping to use the weapon
     pub currently_using: bool,
+    /// The mouse button which is used to use the weapon
+    use_button: MouseButton,
 }
 
 impl WeaponUseTime {
-    pub fn from_use_time(use_time: usize) -> Self {
+    pub(super) fn from_properties(properties: &ItemProperties) -> Self {
         WeaponUseTime {
-            use_time,
-            remaining_cooldown_time: use_time,
+            use_time: properties.use_time,
+            remaining_cooldown_time: properties.use_time,
             currently_using: false,
+            use_button: properties.use_button,
         }
     }
 
+    pub fn use_button(&self) -> MouseButton {
+        self.use_button
+    }
+
     pub fn try_use(&mut self) -> bool {
         if self.remaining_cooldown_time > 0 {
             self.remaining_cooldown_time -= 1;
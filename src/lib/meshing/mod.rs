@@ -6,7 +6,7 @@ use strum::{FromRepr, EnumIter, IntoEnumIterator};
 use crate::blocks::{BlockStorage, BlockType, Block};
 use crate::blocks::utils::Rotation;
 use crate::render::{ATTRIBUTE_UV_BASE, ATTRIBUTE_FACE_COUNT, ATTRIBUTE_SHADING};
-use crate::world::{CHUNK_SIZE, LockedChunkArea};
+use crate::world::{CHUNK_SIZE, LockedChunkArea, DirtyRegion};
 use crate::types::*;
 
 mod chunk_area;
@@ -16,6 +16,8 @@ mod chunk_area;
 pub enum BlockFaceType {
     /// A square side of a block
     Full(TextureUvData),
+    /// A square side of a block which faces behind it can be seen through, like water
+    Transparent(TextureUvData),
     /// The side of a sloped block
     //HalfSlope,
     /// The block has now block face, either if it is air or has a custom model
@@ -40,21 +42,29 @@ pub struct TextureUvData {
 
 impl BlockFaceUv {
     fn is_visible(&self) -> bool {
-        matches!(self.face_type, BlockFaceType::Full(_))
+        self.texture_data().is_some()
+    }
+
+    fn texture_data(&self) -> Option<TextureUvData> {
+        match self.face_type {
+            BlockFaceType::Full(texture_data) | BlockFaceType::Transparent(texture_data) => Some(texture_data),
+            BlockFaceType::Empty => None,
+        }
     }
 
     /// Returns true if this face can be merged with the other face in the greedy meshing algorithm
     fn can_merge_with(&self, other: &BlockFaceUv) -> bool {
         // if either of these do not have texture faces, they are air and cannot be merged
-        let BlockFaceType::Full(this_texture_data) = self.face_type else {
+        let Some(this_texture_data) = self.texture_data() else {
             return false;
         };
 
-        let BlockFaceType::Full(other_texture_data) = other.face_type else {
+        let Some(other_texture_data) = other.texture_data() else {
             return false;
         };
 
         this_texture_data.texture_map_index == other_texture_data.texture_map_index
+            && self.is_occluder() == other.is_occluder()
             && self.rotation == other.rotation
     }
 
@@ -62,6 +72,19 @@ impl BlockFaceUv {
     fn is_occluder(&self) -> bool {
         matches!(self.face_type, BlockFaceType::Full(_))
     }
+
+    /// True if this face can't be seen because of the face of the block in front of it
+    /// 
+    /// Transparent faces only hide faces of the same texture, so there are no faces between 2 blocks of water
+    fn is_hidden_by(&self, other: &BlockFaceUv) -> bool {
+        match (self.face_type, other.face_type) {
+            (_, BlockFaceType::Full(_)) => true,
+            (BlockFaceType::Transparent(this_texture_data), BlockFaceType::Transparent(other_texture_data)) => {
+                this_texture_data.texture_map_index == other_texture_data.texture_map_index
+            },
+            _ => false,
+        }
+    }
 }
 
 /// The model of a block
@@ -101,7 +124,40 @@ pub enum FaceDirection {
 }
 
 impl FaceDirection {
-    fn opposite_face(&self) -> FaceDirection {
+    /// Gets the face which points in the direction of the given axis, towards positive or negative depending on `positive`
+    pub fn from_axis(axis: VecAxis, positive: bool) -> FaceDirection {
+        match (axis, positive) {
+            (VecAxis::X, true) => Self::Right,
+            (VecAxis::X, false) => Self::Left,
+            (VecAxis::Y, true) => Self::Top,
+            (VecAxis::Y, false) => Self::Bottom,
+            (VecAxis::Z, true) => Self::Front,
+            (VecAxis::Z, false) => Self::Back,
+        }
+    }
+
+    /// Unit vector pointing out of the block through this face
+    pub fn normal(&self) -> IVec3 {
+        match self {
+            Self::Front => IVec3::Z,
+            Self::Back => IVec3::NEG_Z,
+            Self::Top => IVec3::Y,
+            Self::Bottom => IVec3::NEG_Y,
+            Self::Left => IVec3::NEG_X,
+            Self::Right => IVec3::X,
+        }
+    }
+
+    /// The axis this face is perpendicular to
+    pub fn axis(&self) -> VecAxis {
+        match self {
+            Self::Front | Self::Back => VecAxis::Z,
+            Self::Top | Self::Bottom => VecAxis::Y,
+            Self::Left | Self::Right => VecAxis::X,
+        }
+    }
+
+    pub fn opposite_face(&self) -> FaceDirection {
         match self {
             Self::Front => Self::Back,
             Self::Back => Self::Front,
@@ -147,6 +203,50 @@ impl MeshBuffers {
     fn is_empty(&self) -> bool {
         self.index_buffer.is_empty()
     }
+
+    fn clear(&mut self) {
+        self.position_buffer.clear();
+        self.uv_base_buffer.clear();
+        self.face_count_buffer.clear();
+        self.shading_buffer.clear();
+        self.index_buffer.clear();
+    }
+
+    fn append(&mut self, other: &MeshBuffers) {
+        let index_base = self.position_buffer.len() as u32;
+
+        self.position_buffer.extend_from_slice(&other.position_buffer);
+        self.uv_base_buffer.extend_from_slice(&other.uv_base_buffer);
+        self.face_count_buffer.extend_from_slice(&other.face_count_buffer);
+        self.shading_buffer.extend_from_slice(&other.shading_buffer);
+        self.index_buffer.extend(other.index_buffer.iter().map(|n| n + index_base));
+    }
+}
+
+/// Mesh buffers for every layer of every face of a chunk from the last time it was meshed
+/// 
+/// When only part of a chunk changes, only the layers which intersect the changed part are remeshed,
+/// and the rest are reused from the cache
+#[derive(Debug, Default)]
+pub struct ChunkMeshCache {
+    /// Empty if the chunk has not been meshed yet, or the cache was invalidated
+    layers: Vec<MeshBuffers>,
+}
+
+impl ChunkMeshCache {
+    const LAYER_COUNT: usize = 6 * CHUNK_SIZE;
+
+    fn is_valid(&self) -> bool {
+        self.layers.len() == Self::LAYER_COUNT
+    }
+
+    fn invalidate(&mut self) {
+        self.layers.clear();
+    }
+
+    fn layer_mut(&mut self, face: FaceDirection, layer: i32) -> &mut MeshBuffers {
+        &mut self.layers[face as usize * CHUNK_SIZE + layer as usize]
+    }
 }
 
 #[derive(Debug)]
@@ -250,7 +350,7 @@ impl FaceMeshData {
                 | FaceDirection::Back => face_count,
         };
 
-        let BlockFaceType::Full(uv_data) = face.face_type else {
+        let Some(uv_data) = face.texture_data() else {
             panic!("face type inserted into mesh has not uv data")
         };
 
@@ -322,22 +422,42 @@ impl<'a> ChunkMeshData<'a> {
 }
 
 /// Generates a mesh for the given chunk, or returns None if the mesh has no faces
+/// 
+/// Only layers which intersect `dirty_region` are remeshed, the rest are taken from `cache`
 // An empty mesh cannot be used here because the custom shader needs all the attributes to exist,
 // and if an attribute exists but it has an empty array, this causes a ton of lag in bevy for some reason
-pub fn generate_mesh(blocks: &ChunkMeshData, models: &[BlockModelUv]) -> Option<Mesh> {
+pub fn generate_mesh(
+    blocks: &ChunkMeshData,
+    models: &[BlockModelUv],
+    cache: &mut ChunkMeshCache,
+    mut dirty_region: DirtyRegion,
+) -> Option<Mesh> {
     if blocks.is_empty() {
+        cache.invalidate();
         return None;
     }
 
-    let mut buffers = MeshBuffers::default();
+    if !cache.is_valid() {
+        cache.layers.resize_with(ChunkMeshCache::LAYER_COUNT, MeshBuffers::default);
+        dirty_region = DirtyRegion::FULL;
+    }
+
     let mut visit_map = VisitedBlockMap::new();
 
     for face in FaceDirection::iter() {
-        for layer in 0..(CHUNK_SIZE as i32) {
-            mesh_layer(blocks, models, &mut buffers, &mut visit_map, face, layer);
+        for layer in dirty_region.layers(face) {
+            let layer_buffers = cache.layer_mut(face, layer);
+            layer_buffers.clear();
+
+            mesh_layer(blocks, models, layer_buffers, &mut visit_map, face, layer);
         }
     }
 
+    let mut buffers = MeshBuffers::default();
+    for layer_buffers in cache.layers.iter() {
+        buffers.append(layer_buffers);
+    }
+
     if buffers.is_empty() {
         return None;
     }
@@ -418,6 +538,13 @@ fn mesh_layer(
         model.get_face(face.opposite_face()).is_occluder()
     };
 
+    let is_hidden = |x, y| {
+        let occluding_pos = occluding_block_pos(x, y);
+        let block = blocks.get(occluding_pos);
+        let model = &models[block.block_id() as usize];
+        get_model(x, y).get_face(face).is_hidden_by(&model.get_face(face.opposite_face()))
+    };
+
     let vertex_occlusion_level = |x, y| {
         let xn_yn = is_occluded(x - 1, y - 1) as u8;
         let xn_yp = is_occluded(x - 1, y) as u8;
@@ -460,7 +587,7 @@ fn mesh_layer(
                 continue;
             }
 
-            if is_occluded(x, y) {
+            if is_hidden(x, y) {
                 y += 1;
                 continue;
             }
@@ -483,7 +610,7 @@ fn mesh_layer(
                     break;
                 }
 
-                if !block_face.can_merge_with(&get_model(x, y_pos).get_face(face)) || is_occluded(x, y_pos) || face_occlusion_data(x, y_pos) != occlusion_data {
+                if !block_face.can_merge_with(&get_model(x, y_pos).get_face(face)) || is_hidden(x, y_pos) || face_occlusion_data(x, y_pos) != occlusion_data {
                     break;
                 }
 
@@ -503,8 +630,8 @@ fn mesh_layer(
                         break 'outer;
                     }
 
-                    if is_occluded(x_pos, y_pos + y) {
-                        // this can be marked as visited, because since it is occluded it will never generate a face
+                    if is_hidden(x_pos, y_pos + y) {
+                        // this can be marked as visited, because since it is hidden it will never generate a face
                         visit_map.visit(x_pos, y_pos + y);
                         break 'outer;
                     }
@@ -2,44 +2,89 @@
 #![feature(box_into_inner)]
 #![feature(let_chains)]
 
+use std::sync::Arc;
+
 use bevy::diagnostic::{FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin};
 use bevy::prelude::*;
 use bevy::pbr::wireframe::WireframePlugin;
 use bevy_inspector_egui::quick::WorldInspectorPlugin;
 
 mod debug;
-mod blocks;
+mod diagnostics;
+pub mod blocks;
 mod items;
 mod meshing;
-mod physics;
+pub mod net;
+pub mod pathfinding;
+pub mod physics;
 mod player;
 mod render;
+pub mod replay;
+pub mod server;
 mod task;
-mod types;
+pub mod types;
 mod ui;
 mod world;
-mod worldgen;
+pub mod worldgen;
 
-pub struct MineconePlugin;
+pub use player::MovementSettings;
 
-impl Plugin for MineconePlugin {
+/// Everything needed to simulate the world, this does not need a window or a gpu
+#[derive(Default)]
+pub struct MineconeCorePlugin {
+    pub worldgen: worldgen::WorldgenSettings,
+}
+
+impl Plugin for MineconeCorePlugin {
     fn build(&self, app: &mut App) {
-        worldgen::Worldgen::init(128947);
+        let worldgen = worldgen::Worldgen::new(self.worldgen.clone());
 
-        app.insert_resource(Msaa::Sample4)
+        app.insert_resource(worldgen::SharedWorldgen(Arc::new(worldgen)))
             .add_plugins((
-                WireframePlugin::default(),
                 LogDiagnosticsPlugin::default(),
                 FrameTimeDiagnosticsPlugin::default(),
-                // NOTE: this plugin causes a lot of lag with larger render distances
-                //WorldInspectorPlugin::new(),
+                diagnostics::WorldDiagnosticsPlugin,
             ))
             .add_plugins((
-                debug::DebugPlugin,
-                items::ItemPlugin,
-                player::PlayerPlugin,
                 physics::PhysicsPlugin,
                 world::WorldPlugin,
+            ));
+    }
+}
+
+/// The player and everything they can do, this also works headless as long as input and transforms are available
+pub struct MineconeGameplayPlugin;
+
+impl Plugin for MineconeGameplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((
+            debug::DebugPlugin,
+            items::ItemPlugin,
+            player::PlayerPlugin,
+        ));
+    }
+}
+
+/// The full game client
+#[derive(Default)]
+pub struct MineconePlugin {
+    pub worldgen: worldgen::WorldgenSettings,
+}
+
+impl Plugin for MineconePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Msaa::Sample4)
+            .add_plugins((
+                MineconeCorePlugin {
+                    worldgen: self.worldgen.clone(),
+                },
+                MineconeGameplayPlugin,
+                WireframePlugin::default(),
+                // NOTE: this plugin causes a lot of lag with larger render distances
+                //WorldInspectorPlugin::new(),
+            ))
+            .add_plugins((
+                world::ChunkMeshPlugin,
                 render::RenderPlugin,
                 ui::UiPlugin,
             ));
@@ -0,0 +1,271 @@
+use std::net::SocketAddr;
+
+use bevy::prelude::*;
+use bevy::app::AppExit;
+use rustc_hash::FxHashMap;
+
+use crate::blocks::{BlockStorage, BlockType};
+use crate::player::{ControlledPlayer, PLAYER_WIDTH, PLAYER_HEIGHT, PLAYER_EYE_HEIGHT};
+use crate::types::{ChunkPos, WorldPos};
+use crate::world::{World, ChunkLoader, ChunkSource, ChunkData, finish_loading_chunk};
+use crate::GameSet;
+use super::protocol::{ClientMessage, ServerMessage};
+use super::{Connection, PROTOCOL_VERSION};
+
+const REMOTE_PLAYER_COLOR: Color = Color::rgb(0.8, 0.3, 0.3);
+
+#[derive(Resource)]
+struct ServerConnection {
+    connection: Connection<ClientMessage, ServerMessage>,
+    hello_sent: bool,
+    player_id: Option<u32>,
+    /// Chunks which have been received but are not loaded by the client yet
+    received_chunks: FxHashMap<ChunkPos, BlockStorage>,
+}
+
+impl ServerConnection {
+    fn send(&self, message: ClientMessage) {
+        // if the connection is closed, this is noticed when messages are next received
+        let _ = self.connection.send(message);
+    }
+}
+
+/// Another player connected to the same server
+#[derive(Component)]
+struct RemotePlayer {
+    player_id: u32,
+    /// Position of the player's eyes
+    position: WorldPos,
+}
+
+#[derive(Resource)]
+struct RemotePlayerAssets {
+    mesh: Handle<Mesh>,
+    material: Handle<StandardMaterial>,
+}
+
+fn setup_client(
+    mut world: ResMut<World>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut commands: Commands,
+) {
+    // local edits are recorded so they can be sent to the server
+    world.enable_change_log();
+
+    commands.insert_resource(RemotePlayerAssets {
+        mesh: meshes.add(shape::Box::new(PLAYER_WIDTH, PLAYER_HEIGHT, PLAYER_WIDTH).into()),
+        material: materials.add(REMOTE_PLAYER_COLOR.into()),
+    });
+}
+
+fn send_hello(
+    mut server: ResMut<ServerConnection>,
+    players: Query<&ChunkLoader, With<ControlledPlayer>>,
+) {
+    if server.hello_sent {
+        return;
+    }
+
+    let Ok(loader) = players.get_single() else {
+        return;
+    };
+
+    server.send(ClientMessage::Hello {
+        protocol_version: PROTOCOL_VERSION,
+        load_distance: loader.load_distance,
+    });
+    server.hello_sent = true;
+}
+
+fn receive_server_messages(
+    mut server: ResMut<ServerConnection>,
+    world: Res<World>,
+    remote_player_assets: Res<RemotePlayerAssets>,
+    mut remote_players: Query<(Entity, &mut RemotePlayer)>,
+    mut exit: EventWriter<AppExit>,
+    mut commands: Commands,
+) {
+    let mut new_positions = FxHashMap::default();
+
+    loop {
+        let message = match server.connection.try_receive() {
+            Ok(Some(message)) => message,
+            Ok(None) => break,
+            Err(_) => {
+                error!("lost connection to server");
+                exit.send(AppExit);
+                return;
+            },
+        };
+
+        match message {
+            ServerMessage::Welcome { player_id } => {
+                info!("joined server as player {player_id}");
+                server.player_id = Some(player_id);
+            },
+            ServerMessage::Disconnect { reason } => {
+                error!("disconnected from server: {reason}");
+                exit.send(AppExit);
+                return;
+            },
+            ServerMessage::ChunkData { chunk_pos, blocks } => {
+                server.received_chunks.insert(chunk_pos, blocks);
+            },
+            ServerMessage::BlockChanges { mut changes } => {
+                // chunks which have not been loaded yet need the change applied too, or it would be lost when they are loaded
+                changes.retain(|(block_pos, block_type)| {
+                    match server.received_chunks.get_mut(&ChunkPos::from(*block_pos)) {
+                        Some(blocks) => {
+                            blocks.new_block(block_pos.as_chunk_local(), *block_type);
+                            false
+                        },
+                        None => true,
+                    }
+                });
+
+                // these came from the server, so they must not be sent back to it
+                world.new_blocks_untracked(changes);
+            },
+            ServerMessage::PlayerPositions { positions } => {
+                new_positions.extend(positions);
+            },
+            ServerMessage::PlayerLeft { player_id } => {
+                new_positions.remove(&player_id);
+
+                for (entity, remote_player) in remote_players.iter() {
+                    if remote_player.player_id == player_id {
+                        commands.entity(entity).despawn();
+                    }
+                }
+            },
+        }
+    }
+
+    for (_, mut remote_player) in remote_players.iter_mut() {
+        if let Some(position) = new_positions.remove(&remote_player.player_id) {
+            remote_player.position = position;
+        }
+    }
+
+    // whatever is left is players which have not been seen before
+    for (player_id, position) in new_positions {
+        commands.spawn((
+            RemotePlayer {
+                player_id,
+                position,
+            },
+            PbrBundle {
+                mesh: remote_player_assets.mesh.clone(),
+                material: remote_player_assets.material.clone(),
+                ..Default::default()
+            },
+        ));
+    }
+}
+
+/// Fills in chunks with the blocks received from the server once the client has loaded them
+fn load_received_chunks(
+    mut server: ResMut<ServerConnection>,
+    world: Res<World>,
+    loaders: Query<&ChunkLoader, With<ControlledPlayer>>,
+) {
+    let Ok(loader) = loaders.get_single() else {
+        return;
+    };
+
+    let region = loader.loaded_region();
+
+    server.received_chunks.retain(|chunk_pos, blocks| {
+        if world.chunks.contains_key(chunk_pos) {
+            let blocks = std::mem::take(blocks);
+            finish_loading_chunk(&world, *chunk_pos, ChunkData::from(blocks));
+            false
+        } else {
+            // the client might not have moved its chunk loader here yet
+            region.contains_chunk(*chunk_pos)
+        }
+    });
+}
+
+/// Sends the edits the player made to the server
+/// 
+/// The edits have already been applied locally, and the server will correct them if it does not allow them
+fn send_block_edits(server: Res<ServerConnection>, world: Res<World>) {
+    // the server decides which blocks fall after a block is removed, so the client does not need these
+    world.take_removed_blocks();
+
+    for (block_pos, block_type) in world.take_block_changes() {
+        let message = if block_type == BlockType::Air {
+            ClientMessage::BreakBlock { block_pos }
+        } else {
+            ClientMessage::PlaceBlock { block_pos, block_type }
+        };
+
+        server.send(message);
+    }
+}
+
+fn send_player_position(
+    server: Res<ServerConnection>,
+    world: Res<World>,
+    players: Query<&Transform, (With<ControlledPlayer>, Changed<Transform>)>,
+) {
+    if server.player_id.is_none() {
+        return;
+    }
+
+    for transform in players.iter() {
+        server.send(ClientMessage::PlayerPosition {
+            position: world.world_pos(transform.translation),
+        });
+    }
+}
+
+fn position_remote_players(
+    world: Res<World>,
+    mut remote_players: Query<(&RemotePlayer, &mut Transform)>,
+) {
+    for (remote_player, mut transform) in remote_players.iter_mut() {
+        let eye_position = world.render_pos(remote_player.position);
+        transform.translation = eye_position - Vec3::new(0.0, PLAYER_EYE_HEIGHT - PLAYER_HEIGHT / 2.0, 0.0);
+    }
+}
+
+/// Connects to a server, which chunks are received from instead of being generated
+pub struct NetClientPlugin {
+    pub address: SocketAddr,
+}
+
+impl Plugin for NetClientPlugin {
+    fn build(&self, app: &mut App) {
+        let connection = match Connection::connect(self.address) {
+            Ok(connection) => connection,
+            Err(error) => {
+                error!("failed to connect to {}: {error}", self.address);
+                app.add_systems(Startup, |mut exit: EventWriter<AppExit>| exit.send(AppExit));
+                return;
+            },
+        };
+
+        app.insert_resource(ChunkSource::Remote)
+            .insert_resource(ServerConnection {
+                connection,
+                hello_sent: false,
+                player_id: None,
+                received_chunks: FxHashMap::default(),
+            })
+            .add_systems(Startup, setup_client)
+            .add_systems(
+                Update,
+                (
+                    send_hello,
+                    receive_server_messages,
+                    load_received_chunks,
+                    send_block_edits,
+                    send_player_position,
+                    position_remote_players,
+                ).chain().in_set(GameSet::Main)
+            );
+    }
+}
\ No newline at end of file
@@ -0,0 +1,108 @@
+use std::io::{self, BufReader, BufWriter, Write};
+use std::marker::PhantomData;
+use std::net::{TcpStream, SocketAddr, Shutdown};
+use std::thread;
+
+use bevy::prelude::*;
+use crossbeam::channel::{self, Sender, Receiver, TryRecvError};
+
+use super::protocol::{Encode, Decode, read_frame, write_frame};
+
+/// Returned when trying to use a connection which the other side has closed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConnectionClosed;
+
+/// A connection which sends messages of type `S` and receives messages of type `R`
+/// 
+/// Reading and writing is done on separate threads so the game never blocks on the network,
+/// and the connection is shut down when this is dropped
+pub struct Connection<S, R> {
+    outgoing: Sender<S>,
+    incoming: Receiver<R>,
+    peer_addr: SocketAddr,
+    marker: PhantomData<fn(S) -> R>,
+}
+
+impl<S: Encode + Send + 'static, R: Decode + Send + 'static> Connection<S, R> {
+    pub fn new(stream: TcpStream) -> io::Result<Self> {
+        // messages are small and latency matters more than throughput
+        stream.set_nodelay(true)?;
+
+        let peer_addr = stream.peer_addr()?;
+        let read_stream = stream.try_clone()?;
+
+        let (incoming_sender, incoming) = channel::unbounded();
+        let (outgoing, outgoing_receiver) = channel::unbounded::<S>();
+
+        thread::spawn(move || {
+            let mut reader = BufReader::new(read_stream);
+
+            loop {
+                match read_frame(&mut reader) {
+                    Ok(message) => if incoming_sender.send(message).is_err() {
+                        break;
+                    },
+                    Err(error) => {
+                        debug!("connection to {peer_addr} closed: {error}");
+                        break;
+                    },
+                }
+            }
+        });
+
+        thread::spawn(move || {
+            let mut writer = BufWriter::new(&stream);
+
+            // this ends once the connection is dropped and the sender is closed
+            'outer: for message in outgoing_receiver.iter() {
+                if write_frame(&mut writer, &message).is_err() {
+                    break;
+                }
+
+                // send everything that has been queued at once
+                for message in outgoing_receiver.try_iter() {
+                    if write_frame(&mut writer, &message).is_err() {
+                        break 'outer;
+                    }
+                }
+
+                if writer.flush().is_err() {
+                    break;
+                }
+            }
+
+            drop(writer);
+            // also wakes up the reader thread
+            let _ = stream.shutdown(Shutdown::Both);
+        });
+
+        Ok(Connection {
+            outgoing,
+            incoming,
+            peer_addr,
+            marker: PhantomData,
+        })
+    }
+
+    pub fn connect(address: SocketAddr) -> io::Result<Self> {
+        Self::new(TcpStream::connect(address)?)
+    }
+
+    pub fn peer_addr(&self) -> SocketAddr {
+        self.peer_addr
+    }
+
+    /// Queues the message to be sent
+    pub fn send(&self, message: S) -> Result<(), ConnectionClosed> {
+        self.outgoing.send(message).map_err(|_| ConnectionClosed)
+    }
+
+    /// Returns the next received message, or `None` if no message has been received yet
+    pub fn try_receive(&self) -> Result<Option<R>, ConnectionClosed> {
+        match self.incoming.try_recv() {
+            Ok(message) => Ok(Some(message)),
+            Err(TryRecvError::Empty) => Ok(None),
+            Err(TryRecvError::Disconnected) => Err(ConnectionClosed),
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,18 @@
+//! Multiplayer over tcp, where the server owns the world and clients send it what they want to change
+//! 
+//! The client sends a hello message with the protocol version, and the server either welcomes it or disconnects it.
+//! After that the server sends the client every chunk around its chunk loader, and any changes to blocks in those chunks.
+
+mod client;
+pub use client::NetClientPlugin;
+mod connection;
+pub use connection::{Connection, ConnectionClosed};
+pub mod protocol;
+mod server;
+pub use server::NetServerPlugin;
+
+/// Must be changed whenever the format of any message changes
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Port the server listens on if none is specified
+pub const DEFAULT_PORT: u16 = 47320;
\ No newline at end of file
@@ -0,0 +1,641 @@
+//! Binary message format used between the client and the server
+//! 
+//! Every message is sent as a frame, which is a little endian u32 length followed by that many bytes of payload.
+//! The first byte of the payload is the message tag, and the rest are the message fields in order.
+//! All integers and floats are little endian.
+
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy::math::DVec3;
+
+use crate::blocks::{BlockStorage, BlockType};
+use crate::world::CHUNK_SIZE;
+use crate::types::{BlockPos, ChunkPos, WorldPos};
+
+/// Largest payload a frame can have, anything bigger is treated as a corrupted stream
+pub const MAX_FRAME_SIZE: usize = 1024 * 1024;
+
+#[derive(Debug)]
+pub enum ProtocolError {
+    Io(io::Error),
+    /// The frame length was bigger than [`MAX_FRAME_SIZE`]
+    FrameTooLarge(usize),
+    /// The payload ended before the message was fully decoded
+    UnexpectedEnd,
+    /// The payload had bytes left over after the message was decoded
+    TrailingBytes(usize),
+    InvalidMessageTag(u8),
+    InvalidBlockId(u16),
+    InvalidString,
+    /// A run of blocks in a chunk did not add up to the size of a chunk
+    InvalidChunkData,
+}
+
+impl fmt::Display for ProtocolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "io error: {error}"),
+            Self::FrameTooLarge(size) => write!(f, "frame of {size} bytes is larger than the maximum of {MAX_FRAME_SIZE} bytes"),
+            Self::UnexpectedEnd => write!(f, "message ended unexpectedly"),
+            Self::TrailingBytes(count) => write!(f, "message had {count} unexpected bytes at the end"),
+            Self::InvalidMessageTag(tag) => write!(f, "invalid message tag {tag}"),
+            Self::InvalidBlockId(id) => write!(f, "invalid block id {id}"),
+            Self::InvalidString => write!(f, "string was not valid utf-8"),
+            Self::InvalidChunkData => write!(f, "chunk data did not contain the right number of blocks"),
+        }
+    }
+}
+
+impl std::error::Error for ProtocolError {}
+
+impl From<io::Error> for ProtocolError {
+    fn from(error: io::Error) -> Self {
+        ProtocolError::Io(error)
+    }
+}
+
+pub trait Encode {
+    fn encode(&self, buf: &mut Vec<u8>);
+}
+
+pub trait Decode: Sized {
+    /// Decodes the value from the start of `buf`, and advances `buf` past the decoded bytes
+    fn decode(buf: &mut &[u8]) -> Result<Self, ProtocolError>;
+}
+
+fn take_bytes<const N: usize>(buf: &mut &[u8]) -> Result<[u8; N], ProtocolError> {
+    if buf.len() < N {
+        return Err(ProtocolError::UnexpectedEnd);
+    }
+
+    let (bytes, rest) = buf.split_at(N);
+    *buf = rest;
+
+    Ok(bytes.try_into().unwrap())
+}
+
+macro_rules! impl_encode_number {
+    ($( $number:ty ),*) => {
+        $(
+            impl Encode for $number {
+                fn encode(&self, buf: &mut Vec<u8>) {
+                    buf.extend_from_slice(&self.to_le_bytes());
+                }
+            }
+
+            impl Decode for $number {
+                fn decode(buf: &mut &[u8]) -> Result<Self, ProtocolError> {
+                    Ok(<$number>::from_le_bytes(take_bytes(buf)?))
+                }
+            }
+        )*
+    };
+}
+
+impl_encode_number!(u8, u16, u32, u64, i32, f32, f64);
+
+impl Encode for bool {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        (*self as u8).encode(buf);
+    }
+}
+
+impl Decode for bool {
+    fn decode(buf: &mut &[u8]) -> Result<Self, ProtocolError> {
+        Ok(u8::decode(buf)? != 0)
+    }
+}
+
+impl Encode for Duration {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        (self.as_nanos() as u64).encode(buf);
+    }
+}
+
+impl Decode for Duration {
+    fn decode(buf: &mut &[u8]) -> Result<Self, ProtocolError> {
+        Ok(Duration::from_nanos(u64::decode(buf)?))
+    }
+}
+
+impl Encode for String {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        (self.len() as u32).encode(buf);
+        buf.extend_from_slice(self.as_bytes());
+    }
+}
+
+impl Decode for String {
+    fn decode(buf: &mut &[u8]) -> Result<Self, ProtocolError> {
+        let len = u32::decode(buf)? as usize;
+        if buf.len() < len {
+            return Err(ProtocolError::UnexpectedEnd);
+        }
+
+        let (bytes, rest) = buf.split_at(len);
+        *buf = rest;
+
+        String::from_utf8(bytes.to_vec()).map_err(|_| ProtocolError::InvalidString)
+    }
+}
+
+impl<T: Encode> Encode for Vec<T> {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        (self.len() as u32).encode(buf);
+        for item in self.iter() {
+            item.encode(buf);
+        }
+    }
+}
+
+impl<T: Decode> Decode for Vec<T> {
+    fn decode(buf: &mut &[u8]) -> Result<Self, ProtocolError> {
+        let len = u32::decode(buf)? as usize;
+
+        // don't trust the length for the allocation, since it could be anything
+        let mut out = Vec::with_capacity(len.min(buf.len()));
+        for _ in 0..len {
+            out.push(T::decode(buf)?);
+        }
+
+        Ok(out)
+    }
+}
+
+impl<A: Encode, B: Encode> Encode for (A, B) {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        self.0.encode(buf);
+        self.1.encode(buf);
+    }
+}
+
+impl<A: Decode, B: Decode> Decode for (A, B) {
+    fn decode(buf: &mut &[u8]) -> Result<Self, ProtocolError> {
+        Ok((A::decode(buf)?, B::decode(buf)?))
+    }
+}
+
+impl Encode for Vec2 {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        self.x.encode(buf);
+        self.y.encode(buf);
+    }
+}
+
+impl Decode for Vec2 {
+    fn decode(buf: &mut &[u8]) -> Result<Self, ProtocolError> {
+        Ok(Vec2::new(f32::decode(buf)?, f32::decode(buf)?))
+    }
+}
+
+impl Encode for IVec3 {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        self.x.encode(buf);
+        self.y.encode(buf);
+        self.z.encode(buf);
+    }
+}
+
+impl Decode for IVec3 {
+    fn decode(buf: &mut &[u8]) -> Result<Self, ProtocolError> {
+        Ok(IVec3::new(i32::decode(buf)?, i32::decode(buf)?, i32::decode(buf)?))
+    }
+}
+
+impl Encode for UVec3 {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        self.x.encode(buf);
+        self.y.encode(buf);
+        self.z.encode(buf);
+    }
+}
+
+impl Decode for UVec3 {
+    fn decode(buf: &mut &[u8]) -> Result<Self, ProtocolError> {
+        Ok(UVec3::new(u32::decode(buf)?, u32::decode(buf)?, u32::decode(buf)?))
+    }
+}
+
+impl Encode for DVec3 {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        self.x.encode(buf);
+        self.y.encode(buf);
+        self.z.encode(buf);
+    }
+}
+
+impl Decode for DVec3 {
+    fn decode(buf: &mut &[u8]) -> Result<Self, ProtocolError> {
+        Ok(DVec3::new(f64::decode(buf)?, f64::decode(buf)?, f64::decode(buf)?))
+    }
+}
+
+impl Encode for ChunkPos {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        self.0.encode(buf);
+    }
+}
+
+impl Decode for ChunkPos {
+    fn decode(buf: &mut &[u8]) -> Result<Self, ProtocolError> {
+        Ok(ChunkPos(IVec3::decode(buf)?))
+    }
+}
+
+impl Encode for BlockPos {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        self.0.encode(buf);
+    }
+}
+
+impl Decode for BlockPos {
+    fn decode(buf: &mut &[u8]) -> Result<Self, ProtocolError> {
+        Ok(BlockPos(IVec3::decode(buf)?))
+    }
+}
+
+impl Encode for WorldPos {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        self.0.encode(buf);
+    }
+}
+
+impl Decode for WorldPos {
+    fn decode(buf: &mut &[u8]) -> Result<Self, ProtocolError> {
+        Ok(WorldPos(DVec3::decode(buf)?))
+    }
+}
+
+impl Encode for BlockType {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        (*self as u16).encode(buf);
+    }
+}
+
+impl Decode for BlockType {
+    fn decode(buf: &mut &[u8]) -> Result<Self, ProtocolError> {
+        let id = u16::decode(buf)?;
+        BlockType::from_repr(id).ok_or(ProtocolError::InvalidBlockId(id))
+    }
+}
+
+fn iter_chunk_local_positions() -> impl Iterator<Item = BlockPos> {
+    let size = CHUNK_SIZE as i32;
+
+    (0..size).flat_map(move |x| {
+        (0..size).flat_map(move |y| {
+            (0..size).map(move |z| BlockPos::new(x, y, z))
+        })
+    })
+}
+
+/// Block storage is run length encoded, since most chunks are made of big areas of the same block
+impl Encode for BlockStorage {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        let mut runs: Vec<(u32, BlockType)> = Vec::new();
+
+        if !self.is_empty() {
+            for block_pos in iter_chunk_local_positions() {
+                let block_type = self.get(block_pos).block_type();
+
+                match runs.last_mut() {
+                    Some((count, run_type)) if *run_type == block_type => *count += 1,
+                    _ => runs.push((1, block_type)),
+                }
+            }
+        }
+
+        runs.encode(buf);
+    }
+}
+
+impl Decode for BlockStorage {
+    fn decode(buf: &mut &[u8]) -> Result<Self, ProtocolError> {
+        let runs = Vec::<(u32, BlockType)>::decode(buf)?;
+
+        let mut blocks = BlockStorage::default();
+        if runs.is_empty() {
+            return Ok(blocks);
+        }
+
+        let block_count = runs.iter().map(|(count, _)| *count as usize).sum::<usize>();
+        if block_count != CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE {
+            return Err(ProtocolError::InvalidChunkData);
+        }
+
+        let block_types = runs.iter()
+            .flat_map(|(count, block_type)| std::iter::repeat(*block_type).take(*count as usize));
+
+        for (block_pos, block_type) in iter_chunk_local_positions().zip(block_types) {
+            if block_type != BlockType::Air {
+                blocks.new_block(block_pos, block_type);
+            }
+        }
+
+        Ok(blocks)
+    }
+}
+
+macro_rules! define_messages {
+    (
+        $( #[$attr:meta] )*
+        pub enum $name:ident {
+            $(
+                $( #[$variant_attr:meta] )*
+                $variant:ident $( { $( $( #[$field_attr:meta] )* $field:ident: $field_type:ty ),* $(,)? } )? = $tag:literal,
+            )*
+        }
+    ) => {
+        $( #[$attr] )*
+        pub enum $name {
+            $(
+                $( #[$variant_attr] )*
+                $variant $( { $( $( #[$field_attr] )* $field: $field_type ),* } )?,
+            )*
+        }
+
+        impl Encode for $name {
+            #[allow(unused_variables)]
+            fn encode(&self, buf: &mut Vec<u8>) {
+                match self {
+                    $(
+                        Self::$variant $( { $( $field ),* } )? => {
+                            buf.push($tag);
+                            $( $( $field.encode(buf); )* )?
+                        },
+                    )*
+                }
+            }
+        }
+
+        impl Decode for $name {
+            fn decode(buf: &mut &[u8]) -> Result<Self, ProtocolError> {
+                match u8::decode(buf)? {
+                    $(
+                        $tag => Ok(Self::$variant $( { $( $field: <$field_type>::decode(buf)? ),* } )?),
+                    )*
+                    tag => Err(ProtocolError::InvalidMessageTag(tag)),
+                }
+            }
+        }
+    };
+}
+
+define_messages! {
+    /// Messages sent from the client to the server
+    #[derive(Debug, Clone)]
+    pub enum ClientMessage {
+        /// Must be the first message sent by the client
+        Hello {
+            protocol_version: u32,
+            /// Load distance of the client's chunk loader, chunks in this distance will be sent to the client
+            load_distance: UVec3,
+        } = 0,
+        /// Where the client's player currently is
+        PlayerPosition {
+            position: WorldPos,
+        } = 1,
+        /// The client wants to mine the block
+        BreakBlock {
+            block_pos: BlockPos,
+        } = 2,
+        /// The client wants to place a block
+        PlaceBlock {
+            block_pos: BlockPos,
+            block_type: BlockType,
+        } = 3,
+    }
+}
+
+define_messages! {
+    /// Messages sent from the server to the client
+    #[derive(Debug, Clone)]
+    pub enum ServerMessage {
+        /// Sent in response to a valid hello message
+        Welcome {
+            player_id: u32,
+        } = 0,
+        /// The server is closing the connection
+        Disconnect {
+            reason: String,
+        } = 1,
+        /// All the blocks in a chunk
+        ChunkData {
+            chunk_pos: ChunkPos,
+            blocks: BlockStorage,
+        } = 2,
+        /// Blocks which changed in chunks the client has been sent
+        BlockChanges {
+            changes: Vec<(BlockPos, BlockType)>,
+        } = 3,
+        /// Positions of every other player
+        PlayerPositions {
+            positions: Vec<(u32, WorldPos)>,
+        } = 4,
+        /// The player with the given id has left
+        PlayerLeft {
+            player_id: u32,
+        } = 5,
+    }
+}
+
+/// Encodes the message and writes it as a frame
+pub fn write_frame<W: Write, M: Encode>(writer: &mut W, message: &M) -> io::Result<()> {
+    let mut payload = Vec::new();
+    message.encode(&mut payload);
+
+    writer.write_all(&(payload.len() as u32).to_le_bytes())?;
+    writer.write_all(&payload)
+}
+
+/// Reads one frame and decodes the message in it
+pub fn read_frame<R: Read, M: Decode>(reader: &mut R) -> Result<M, ProtocolError> {
+    let mut len_bytes = [0; 4];
+    reader.read_exact(&mut len_bytes)?;
+
+    let len = u32::from_le_bytes(len_bytes) as usize;
+    if len > MAX_FRAME_SIZE {
+        return Err(ProtocolError::FrameTooLarge(len));
+    }
+
+    let mut payload = vec![0; len];
+    reader.read_exact(&mut payload)?;
+
+    let mut buf = payload.as_slice();
+    let message = M::decode(&mut buf)?;
+
+    if buf.is_empty() {
+        Ok(message)
+    } else {
+        Err(ProtocolError::TrailingBytes(buf.len()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip<M: Encode + Decode>(message: &M) -> M {
+        let mut frame = Vec::new();
+        write_frame(&mut frame, message).unwrap();
+
+        let mut reader = frame.as_slice();
+        let decoded = read_frame(&mut reader).unwrap();
+        assert!(reader.is_empty(), "frame was not fully read");
+
+        decoded
+    }
+
+    fn encoded<M: Encode>(message: &M) -> Vec<u8> {
+        let mut buf = Vec::new();
+        message.encode(&mut buf);
+        buf
+    }
+
+    /// Blocks of a chunk with a stone floor, a column of dirt, and a single ore
+    fn test_blocks() -> BlockStorage {
+        let mut blocks = BlockStorage::default();
+
+        for x in 0..CHUNK_SIZE as i32 {
+            for z in 0..CHUNK_SIZE as i32 {
+                blocks.new_block(BlockPos::new(x, 0, z), BlockType::Stone);
+            }
+        }
+
+        for y in 1..5 {
+            blocks.new_block(BlockPos::new(3, y, 7), BlockType::Dirt);
+        }
+        blocks.new_block(BlockPos::new(CHUNK_SIZE as i32 - 1, 9, 0), BlockType::IronOre);
+
+        blocks
+    }
+
+    #[test]
+    fn client_messages_round_trip() {
+        let messages = [
+            ClientMessage::Hello { protocol_version: 7, load_distance: UVec3::new(4, 2, 4) },
+            ClientMessage::PlayerPosition { position: WorldPos(DVec3::new(1.5, -20.25, 1e9)) },
+            ClientMessage::BreakBlock { block_pos: BlockPos::new(-5, 12, i32::MIN) },
+            ClientMessage::PlaceBlock { block_pos: BlockPos::new(i32::MAX, 0, -1), block_type: BlockType::Log },
+        ];
+
+        for message in messages {
+            let decoded = round_trip(&message);
+            assert_eq!(encoded(&decoded), encoded(&message), "{message:?} changed after being sent");
+        }
+
+        match round_trip(&ClientMessage::PlaceBlock { block_pos: BlockPos::new(1, 2, 3), block_type: BlockType::Sand }) {
+            ClientMessage::PlaceBlock { block_pos, block_type } => {
+                assert_eq!(block_pos, BlockPos::new(1, 2, 3));
+                assert_eq!(block_type, BlockType::Sand);
+            },
+            message => panic!("decoded the wrong message: {message:?}"),
+        }
+    }
+
+    #[test]
+    fn server_messages_round_trip() {
+        let messages = [
+            ServerMessage::Welcome { player_id: 3 },
+            ServerMessage::Disconnect { reason: String::from("server is closing ✓") },
+            ServerMessage::ChunkData { chunk_pos: ChunkPos::new(-1, 0, 2), blocks: BlockStorage::default() },
+            ServerMessage::ChunkData { chunk_pos: ChunkPos::new(4, -3, 0), blocks: test_blocks() },
+            ServerMessage::BlockChanges { changes: vec![(BlockPos::new(0, 1, 2), BlockType::Air), (BlockPos::new(-3, 4, 5), BlockType::Water)] },
+            ServerMessage::BlockChanges { changes: Vec::new() },
+            ServerMessage::PlayerPositions { positions: vec![(0, WorldPos(DVec3::ZERO)), (9, WorldPos(DVec3::new(-0.5, 3.0, 8.75)))] },
+            ServerMessage::PlayerLeft { player_id: u32::MAX },
+        ];
+
+        for message in messages {
+            let decoded = round_trip(&message);
+            assert_eq!(encoded(&decoded), encoded(&message), "{message:?} changed after being sent");
+        }
+    }
+
+    #[test]
+    fn chunk_data_keeps_every_block() {
+        let blocks = test_blocks();
+
+        let ServerMessage::ChunkData { chunk_pos, blocks: decoded } = round_trip(&ServerMessage::ChunkData { chunk_pos: ChunkPos::new(1, 2, 3), blocks: blocks.clone() }) else {
+            panic!("decoded the wrong message");
+        };
+
+        assert_eq!(chunk_pos, ChunkPos::new(1, 2, 3));
+        for block_pos in iter_chunk_local_positions() {
+            assert_eq!(decoded.get(block_pos).block_type(), blocks.get(block_pos).block_type(), "block at {block_pos:?} is different");
+        }
+    }
+
+    #[test]
+    fn several_frames_are_read_in_order() {
+        let mut stream = Vec::new();
+        for player_id in 0..3 {
+            write_frame(&mut stream, &ServerMessage::Welcome { player_id }).unwrap();
+        }
+
+        let mut reader = stream.as_slice();
+        for player_id in 0..3 {
+            match read_frame(&mut reader).unwrap() {
+                ServerMessage::Welcome { player_id: decoded_id } => assert_eq!(decoded_id, player_id),
+                message => panic!("decoded the wrong message: {message:?}"),
+            }
+        }
+
+        assert!(matches!(read_frame::<_, ServerMessage>(&mut reader), Err(ProtocolError::Io(_))));
+    }
+
+    #[test]
+    fn truncated_frame_is_an_error() {
+        let mut frame = Vec::new();
+        write_frame(&mut frame, &ClientMessage::BreakBlock { block_pos: BlockPos::new(1, 2, 3) }).unwrap();
+        frame.pop();
+
+        assert!(matches!(read_frame::<_, ClientMessage>(&mut frame.as_slice()), Err(ProtocolError::Io(_))));
+
+        // the length says the payload is complete, but the message needs more bytes
+        let mut payload = encoded(&ClientMessage::BreakBlock { block_pos: BlockPos::new(1, 2, 3) });
+        payload.pop();
+        let mut frame = (payload.len() as u32).to_le_bytes().to_vec();
+        frame.extend_from_slice(&payload);
+
+        assert!(matches!(read_frame::<_, ClientMessage>(&mut frame.as_slice()), Err(ProtocolError::UnexpectedEnd)));
+    }
+
+    #[test]
+    fn invalid_frames_are_errors() {
+        let frame_with_payload = |payload: &[u8]| {
+            let mut frame = (payload.len() as u32).to_le_bytes().to_vec();
+            frame.extend_from_slice(payload);
+            frame
+        };
+
+        let frame = frame_with_payload(&[200]);
+        assert!(matches!(read_frame::<_, ServerMessage>(&mut frame.as_slice()), Err(ProtocolError::InvalidMessageTag(200))));
+
+        let mut payload = encoded(&ServerMessage::Welcome { player_id: 1 });
+        payload.push(0);
+        let frame = frame_with_payload(&payload);
+        assert!(matches!(read_frame::<_, ServerMessage>(&mut frame.as_slice()), Err(ProtocolError::TrailingBytes(1))));
+
+        let mut payload = encoded(&ClientMessage::PlaceBlock { block_pos: BlockPos::new(0, 0, 0), block_type: BlockType::Dirt });
+        let len = payload.len();
+        payload[len - 2..].copy_from_slice(&u16::MAX.to_le_bytes());
+        let frame = frame_with_payload(&payload);
+        assert!(matches!(read_frame::<_, ClientMessage>(&mut frame.as_slice()), Err(ProtocolError::InvalidBlockId(u16::MAX))));
+
+        let frame = ((MAX_FRAME_SIZE + 1) as u32).to_le_bytes();
+        assert!(matches!(read_frame::<_, ServerMessage>(&mut frame.as_slice()), Err(ProtocolError::FrameTooLarge(_))));
+    }
+
+    #[test]
+    fn chunk_data_with_wrong_block_count_is_an_error() {
+        let mut payload = vec![2];
+        ChunkPos::new(0, 0, 0).encode(&mut payload);
+        vec![(10u32, BlockType::Stone)].encode(&mut payload);
+
+        assert!(matches!(ServerMessage::decode(&mut payload.as_slice()), Err(ProtocolError::InvalidChunkData)));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,433 @@
+use std::net::{TcpListener, TcpStream, SocketAddr};
+use std::thread;
+
+use bevy::prelude::*;
+use crossbeam::channel::{self, Receiver};
+use rustc_hash::FxHashSet;
+
+use crate::blocks::BlockType;
+use crate::types::{WorldPos, ChunkPos, BlockPos, BLOCK_SIZE};
+use crate::world::{World, ChunkLoader};
+use crate::GameSet;
+use super::protocol::{ClientMessage, ServerMessage};
+use super::{Connection, PROTOCOL_VERSION};
+
+/// Largest load distance a client can ask for
+const MAX_LOAD_DISTANCE: u32 = 16;
+/// Most chunks sent to a single client each tick, so a client joining doesn't stall the server
+const MAX_CHUNKS_PER_TICK: usize = 8;
+/// Furthest away from a player in meters that they are allowed to edit blocks
+const MAX_EDIT_DISTANCE: f64 = 64.0;
+
+#[derive(Resource)]
+struct NetServer {
+    new_connections: Receiver<TcpStream>,
+    next_player_id: u32,
+}
+
+/// A client connected to this server
+#[derive(Component)]
+struct RemoteClient {
+    connection: Connection<ServerMessage, ClientMessage>,
+    player_id: u32,
+    /// Set once the client has sent a valid hello message
+    joined: bool,
+    position: WorldPos,
+    /// Chunks which the client has been sent, and will be sent block changes for
+    sent_chunks: FxHashSet<ChunkPos>,
+}
+
+impl RemoteClient {
+    fn send(&self, message: ServerMessage) {
+        // if the connection is closed, the client will be removed when its messages are next received
+        let _ = self.connection.send(message);
+    }
+
+    fn can_edit(&self, world: &World, block_pos: BlockPos) -> bool {
+        let block_center = WorldPos::from(block_pos).0 + BLOCK_SIZE as f64 / 2.0;
+
+        self.sent_chunks.contains(&ChunkPos::from(block_pos))
+            && block_center.distance(self.position.0) <= MAX_EDIT_DISTANCE
+            && world.get_block(block_pos).is_some()
+    }
+}
+
+/// Marks a client whose connection has closed, it is despawned once its chunks are unloaded
+#[derive(Component)]
+struct Disconnected;
+
+fn enable_change_log(mut world: ResMut<World>) {
+    world.enable_change_log();
+}
+
+fn accept_connections(mut server: ResMut<NetServer>, mut commands: Commands) {
+    while let Ok(stream) = server.new_connections.try_recv() {
+        let connection = match Connection::new(stream) {
+            Ok(connection) => connection,
+            Err(error) => {
+                warn!("failed to set up connection: {error}");
+                continue;
+            },
+        };
+
+        info!("{} connected", connection.peer_addr());
+
+        commands.spawn(RemoteClient {
+            connection,
+            player_id: server.next_player_id,
+            joined: false,
+            position: WorldPos::default(),
+            sent_chunks: FxHashSet::default(),
+        });
+
+        server.next_player_id += 1;
+    }
+}
+
+/// Components of a connected client, the loader and transform are only there once it has joined
+type ClientComponents = (Entity, &'static mut RemoteClient, Option<&'static mut ChunkLoader>, Option<&'static mut Transform>);
+
+fn receive_client_messages(
+    world: Res<World>,
+    mut clients: Query<ClientComponents, Without<Disconnected>>,
+    mut commands: Commands,
+) {
+    let mut left_players = Vec::new();
+
+    for (entity, mut client, mut loader, mut transform) in clients.iter_mut() {
+        let mut disconnect = false;
+
+        loop {
+            let message = match client.connection.try_receive() {
+                Ok(Some(message)) => message,
+                Ok(None) => break,
+                Err(_) => {
+                    disconnect = true;
+                    break;
+                },
+            };
+
+            match message {
+                ClientMessage::Hello { protocol_version, load_distance } => {
+                    if client.joined {
+                        continue;
+                    }
+
+                    if protocol_version != PROTOCOL_VERSION {
+                        client.send(ServerMessage::Disconnect {
+                            reason: format!("server is on protocol version {PROTOCOL_VERSION}, but client is on version {protocol_version}"),
+                        });
+                        disconnect = true;
+                        break;
+                    }
+
+                    client.joined = true;
+                    client.send(ServerMessage::Welcome {
+                        player_id: client.player_id,
+                    });
+
+                    commands.entity(entity).insert((
+                        ChunkLoader::new(ChunkPos::ZERO, load_distance.min(UVec3::splat(MAX_LOAD_DISTANCE))),
+                        TransformBundle::default(),
+                    ));
+
+                    info!("{} joined as player {}", client.connection.peer_addr(), client.player_id);
+                },
+                ClientMessage::PlayerPosition { position } => {
+                    if !client.joined {
+                        continue;
+                    }
+
+                    client.position = position;
+
+                    // moving the transform will also move the chunk loader
+                    if let Some(ref mut transform) = transform {
+                        transform.translation = world.render_pos(position);
+                    }
+                },
+                ClientMessage::BreakBlock { block_pos } => {
+                    if !client.joined {
+                        continue;
+                    }
+
+                    let is_valid = client.can_edit(&world, block_pos)
+                        && world.get_block(block_pos).is_some_and(|block| !block.is_replaceable());
+
+                    if !is_valid || world.new_block(block_pos, BlockType::Air).is_none() {
+                        reject_edit(&client, &world, block_pos);
+                    }
+                },
+                ClientMessage::PlaceBlock { block_pos, block_type } => {
+                    if !client.joined {
+                        continue;
+                    }
+
+                    let is_valid = block_type.is_placeable()
+                        && client.can_edit(&world, block_pos)
+                        && world.get_block(block_pos).is_some_and(|block| block.is_replaceable());
+
+                    if !is_valid || world.new_block(block_pos, block_type).is_none() {
+                        reject_edit(&client, &world, block_pos);
+                    }
+                },
+            }
+        }
+
+        if disconnect {
+            info!("{} disconnected", client.connection.peer_addr());
+
+            if client.joined {
+                left_players.push(client.player_id);
+            }
+
+            // let the chunk loader unload its chunks before the client is despawned
+            if let Some(ref mut loader) = loader {
+                loader.load_distance = UVec3::ZERO;
+            }
+
+            commands.entity(entity).insert(Disconnected);
+        }
+    }
+
+    for player_id in left_players {
+        for (_, client, _, _) in clients.iter() {
+            client.send(ServerMessage::PlayerLeft { player_id });
+        }
+    }
+}
+
+/// Tells the client what the block actually is, so it can undo the edit it predicted
+fn reject_edit(client: &RemoteClient, world: &World, block_pos: BlockPos) {
+    if let Some(block) = world.get_block(block_pos) && client.sent_chunks.contains(&ChunkPos::from(block_pos)) {
+        client.send(ServerMessage::BlockChanges {
+            changes: vec![(block_pos, block.block_type())],
+        });
+    }
+}
+
+fn despawn_disconnected_clients(
+    clients: Query<(Entity, Option<&ChunkLoader>), With<Disconnected>>,
+    mut commands: Commands,
+) {
+    for (entity, loader) in clients.iter() {
+        if loader.map_or(true, ChunkLoader::is_unloaded) {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+/// Sends each client the closest loaded chunks around it which it has not been sent yet
+fn send_chunks(
+    world: Res<World>,
+    mut clients: Query<(&mut RemoteClient, &ChunkLoader), Without<Disconnected>>,
+) {
+    for (mut client, loader) in clients.iter_mut() {
+        let region = loader.loaded_region();
+
+        // the client unloads chunks outside its loader as well, so they must be sent again if the client comes back
+        client.sent_chunks.retain(|chunk_pos| region.contains_chunk(*chunk_pos));
+
+        let mut unsent_chunks = region.iter_chunks()
+            .filter(|chunk_pos| !client.sent_chunks.contains(chunk_pos))
+            .filter(|chunk_pos| world.chunks.get(chunk_pos).is_some_and(|chunk| chunk.is_loaded()))
+            .collect::<Vec<_>>();
+
+        unsent_chunks.sort_by_key(|chunk_pos| (chunk_pos.0 - loader.position.0).length_squared());
+
+        for chunk_pos in unsent_chunks.into_iter().take(MAX_CHUNKS_PER_TICK) {
+            let blocks = world.chunks[&chunk_pos].data.read().blocks.clone();
+
+            client.send(ServerMessage::ChunkData {
+                chunk_pos,
+                blocks,
+            });
+
+            client.sent_chunks.insert(chunk_pos);
+        }
+    }
+}
+
+fn send_block_changes(world: Res<World>, clients: Query<&RemoteClient, Without<Disconnected>>) {
+    let changes = world.take_block_changes();
+    if changes.is_empty() {
+        return;
+    }
+
+    for client in clients.iter() {
+        let client_changes = changes.iter()
+            .filter(|(block_pos, _)| client.sent_chunks.contains(&ChunkPos::from(*block_pos)))
+            .copied()
+            .collect::<Vec<_>>();
+
+        if !client_changes.is_empty() {
+            client.send(ServerMessage::BlockChanges {
+                changes: client_changes,
+            });
+        }
+    }
+}
+
+fn send_player_positions(clients: Query<&RemoteClient, Without<Disconnected>>) {
+    let positions = clients.iter()
+        .filter(|client| client.joined)
+        .map(|client| (client.player_id, client.position))
+        .collect::<Vec<_>>();
+
+    for client in clients.iter().filter(|client| client.joined) {
+        let other_positions = positions.iter()
+            .filter(|(player_id, _)| *player_id != client.player_id)
+            .copied()
+            .collect::<Vec<_>>();
+
+        if !other_positions.is_empty() {
+            client.send(ServerMessage::PlayerPositions {
+                positions: other_positions,
+            });
+        }
+    }
+}
+
+/// Accepts clients and keeps them in sync with the world
+pub struct NetServerPlugin {
+    pub address: SocketAddr,
+}
+
+impl Plugin for NetServerPlugin {
+    fn build(&self, app: &mut App) {
+        let listener = TcpListener::bind(self.address)
+            .unwrap_or_else(|error| panic!("failed to listen on {}: {error}", self.address));
+
+        info!("listening on {}", self.address);
+
+        let (connection_sender, new_connections) = channel::unbounded();
+
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => if connection_sender.send(stream).is_err() {
+                        break;
+                    },
+                    Err(error) => warn!("failed to accept connection: {error}"),
+                }
+            }
+        });
+
+        app.insert_resource(NetServer {
+                new_connections,
+                next_player_id: 0,
+            })
+            .add_systems(Startup, enable_change_log)
+            .add_systems(
+                Update,
+                (
+                    accept_connections,
+                    receive_client_messages,
+                    despawn_disconnected_clients,
+                    send_chunks,
+                    send_block_changes,
+                    send_player_positions,
+                ).chain().in_set(GameSet::Main)
+            );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{Ipv4Addr, SocketAddrV4};
+    use std::time::{Duration, Instant};
+
+    use bevy::math::DVec3;
+
+    use crate::net::ConnectionClosed;
+    use super::*;
+
+    /// How long to wait for the server to answer before failing the test
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    /// Runs a server on a free loopback port, without a window or any chunks
+    fn test_server() -> (App, SocketAddr) {
+        // the port is only free until it is bound again, but nothing else should take it in between
+        let address = TcpListener::bind(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 0))
+            .and_then(|listener| listener.local_addr())
+            .unwrap();
+
+        let mut app = App::new();
+        app.insert_resource(World::default())
+            .add_plugins(NetServerPlugin { address });
+
+        (app, address)
+    }
+
+    /// Updates the server until the client receives a message
+    fn receive(app: &mut App, client: &Connection<ClientMessage, ServerMessage>) -> Result<ServerMessage, ConnectionClosed> {
+        let start = Instant::now();
+
+        loop {
+            app.update();
+
+            if let Some(message) = client.try_receive()? {
+                return Ok(message);
+            }
+
+            assert!(start.elapsed() < TIMEOUT, "server did not send a message");
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    fn remote_client(app: &mut App) -> &RemoteClient {
+        app.world.query::<&RemoteClient>().single(&app.world)
+    }
+
+    #[test]
+    fn client_with_matching_version_is_welcomed() {
+        let (mut app, address) = test_server();
+        let client = Connection::<ClientMessage, ServerMessage>::connect(address).unwrap();
+
+        client.send(ClientMessage::Hello { protocol_version: PROTOCOL_VERSION, load_distance: UVec3::splat(2) }).unwrap();
+
+        match receive(&mut app, &client).unwrap() {
+            ServerMessage::Welcome { player_id } => assert_eq!(player_id, 0),
+            message => panic!("expected a welcome message but got {message:?}"),
+        }
+
+        assert!(remote_client(&mut app).joined);
+    }
+
+    #[test]
+    fn client_with_different_version_is_disconnected() {
+        let (mut app, address) = test_server();
+        let client = Connection::<ClientMessage, ServerMessage>::connect(address).unwrap();
+
+        client.send(ClientMessage::Hello { protocol_version: PROTOCOL_VERSION + 1, load_distance: UVec3::splat(2) }).unwrap();
+
+        match receive(&mut app, &client).unwrap() {
+            ServerMessage::Disconnect { reason } => assert!(reason.contains("protocol version"), "unexpected reason: {reason}"),
+            message => panic!("expected a disconnect message but got {message:?}"),
+        }
+
+        // the server closes the connection after telling the client why
+        assert_eq!(receive(&mut app, &client).unwrap_err(), ConnectionClosed);
+    }
+
+    #[test]
+    fn position_before_hello_is_ignored() {
+        let (mut app, address) = test_server();
+        let client = Connection::<ClientMessage, ServerMessage>::connect(address).unwrap();
+
+        let position = WorldPos(DVec3::new(10.0, 20.0, 30.0));
+        client.send(ClientMessage::PlayerPosition { position }).unwrap();
+        client.send(ClientMessage::Hello { protocol_version: PROTOCOL_VERSION, load_distance: UVec3::splat(2) }).unwrap();
+
+        assert!(matches!(receive(&mut app, &client).unwrap(), ServerMessage::Welcome { .. }));
+        assert_eq!(remote_client(&mut app).position, WorldPos::default());
+
+        client.send(ClientMessage::PlayerPosition { position }).unwrap();
+
+        let start = Instant::now();
+        while remote_client(&mut app).position != position {
+            assert!(start.elapsed() < TIMEOUT, "server never received the position");
+            app.update();
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,358 @@
+//! A* pathfinding for mobs walking over the blocks in the world
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use bevy::prelude::*;
+use rustc_hash::FxHashMap;
+
+use crate::blocks::{Block, BlockType, CollisionShape};
+use crate::task::{Task, TaskPool};
+use crate::types::*;
+use crate::world::{World, ChunkRegion, OwnedChunkArea, LockedChunkArea};
+
+/// Number of chunks around the start and goal which are included in the search area
+const SEARCH_MARGIN: ChunkPos = ChunkPos::new(1, 1, 1);
+/// Searches needing more chunks than this are not started, so one search can't lock a huge part of the world
+const MAX_SEARCH_CHUNKS: usize = 512;
+
+/// Describes how a mob is able to move, all distances are in blocks
+#[derive(Debug, Clone, Copy)]
+pub struct PathfindingParams {
+    /// Number of blocks of free space the mob needs above the block it stands on
+    pub height: i32,
+    /// Tallest ledge the mob can walk up without jumping
+    pub step_height: i32,
+    /// Tallest ledge the mob can jump up
+    pub jump_height: i32,
+    /// Furthest the mob is willing to fall
+    pub max_drop: i32,
+    /// Extra cost added for each block jumped, so the mob prefers to walk around obstacles when it is not much longer
+    pub jump_cost: u32,
+    /// Maximum number of positions to check before giving up
+    pub max_nodes: usize,
+    /// Blocks which the mob should never stand on or move through
+    pub is_hazard: fn(BlockType) -> bool,
+}
+
+impl Default for PathfindingParams {
+    fn default() -> Self {
+        PathfindingParams {
+            height: 4,
+            step_height: 1,
+            jump_height: 2,
+            max_drop: 6,
+            jump_cost: 2,
+            max_nodes: 20000,
+            is_hazard: |_| false,
+        }
+    }
+}
+
+/// A path of block positions the mob's feet will be in, from the start to the goal
+pub type Path = Vec<BlockPos>;
+
+/// Finds paths through the blocks in a locked chunk area
+pub struct Pathfinder<'a> {
+    area: &'a LockedChunkArea<'a>,
+    params: PathfindingParams,
+}
+
+impl<'a> Pathfinder<'a> {
+    pub fn new(area: &'a LockedChunkArea<'a>, params: PathfindingParams) -> Self {
+        Pathfinder {
+            area,
+            params,
+        }
+    }
+
+    fn get_block(&self, block_pos: BlockPos) -> Option<Block> {
+        self.area.get_block(block_pos)
+    }
+
+    fn is_hazard(&self, block: Block) -> bool {
+        (self.params.is_hazard)(block.block_type())
+    }
+
+    /// Returns true if the block can be stood on, blocks outside of the area can't be stood on
+    fn is_solid(&self, block_pos: BlockPos) -> bool {
+        self.get_block(block_pos).is_some_and(|block| {
+            !matches!(block.block_type().collision_shape(), CollisionShape::Empty)
+                && !self.is_hazard(block)
+        })
+    }
+
+    /// Returns true if the mob can move through the block, blocks outside of the area are not passable
+    fn is_passable(&self, block_pos: BlockPos) -> bool {
+        self.get_block(block_pos).is_some_and(|block| {
+            matches!(block.block_type().collision_shape(), CollisionShape::Empty)
+                && !self.is_hazard(block)
+        })
+    }
+
+    /// Returns true if the mob's whole body fits with its feet at the given position
+    fn body_fits(&self, feet_pos: BlockPos) -> bool {
+        (0..self.params.height).all(|y| self.is_passable(feet_pos + BlockPos::new(0, y, 0)))
+    }
+
+    /// Returns true if the mob can stand with its feet at the given position
+    pub fn is_walkable(&self, feet_pos: BlockPos) -> bool {
+        self.is_solid(feet_pos - BlockPos::new(0, 1, 0)) && self.body_fits(feet_pos)
+    }
+
+    /// Calls `f` with every position the mob can move to from `position` and the cost of moving there
+    fn for_each_neighbor<F: FnMut(BlockPos, u32)>(&self, position: BlockPos, mut f: F) {
+        let directions = [
+            BlockPos::new(1, 0, 0),
+            BlockPos::new(-1, 0, 0),
+            BlockPos::new(0, 0, 1),
+            BlockPos::new(0, 0, -1),
+        ];
+
+        for direction in directions {
+            let column = position + direction;
+
+            if self.is_walkable(column) {
+                f(column, 1);
+            } else if !self.body_fits(column) {
+                // something is in the way, so try to step or jump up onto it
+                for dy in 1..=self.params.jump_height {
+                    // the mob needs space above its head in the column it is jumping from
+                    if !self.is_passable(position + BlockPos::new(0, self.params.height + dy - 1, 0)) {
+                        break;
+                    }
+
+                    let target = column + BlockPos::new(0, dy, 0);
+                    if self.is_walkable(target) {
+                        let cost = if dy > self.params.step_height {
+                            1 + dy as u32 + self.params.jump_cost
+                        } else {
+                            1 + dy as u32
+                        };
+
+                        f(target, cost);
+                        break;
+                    }
+                }
+            } else {
+                // there is space to move but nothing to stand on, so try dropping down
+                for dy in 1..=self.params.max_drop {
+                    let target = column - BlockPos::new(0, dy, 0);
+                    if !self.is_passable(target) {
+                        break;
+                    }
+
+                    if self.is_walkable(target) {
+                        f(target, 1 + dy as u32);
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    fn heuristic(position: BlockPos, goal: BlockPos) -> u32 {
+        let diff = (goal.0 - position.0).abs();
+        (diff.x + diff.y + diff.z) as u32
+    }
+
+    /// Finds the cheapest path from `start` to `goal`, or `None` if there is no path or the search gave up
+    pub fn find_path(&self, start: BlockPos, goal: BlockPos) -> Option<Path> {
+        if !self.is_walkable(start) || !self.is_walkable(goal) {
+            return None;
+        }
+
+        // maps each visited position to the position it was reached from and the cost to get there
+        let mut came_from: FxHashMap<BlockPos, (BlockPos, u32)> = FxHashMap::default();
+        let mut open_set = BinaryHeap::new();
+
+        came_from.insert(start, (start, 0));
+        open_set.push(Reverse((Self::heuristic(start, goal), 0, start.to_array())));
+
+        let mut visited_count = 0;
+
+        while let Some(Reverse((_, cost, position))) = open_set.pop() {
+            let position = BlockPos(IVec3::from_array(position));
+
+            if position == goal {
+                return Some(Self::reconstruct_path(&came_from, start, goal));
+            }
+
+            // this entry is outdated, a cheaper way to get here was already found
+            if came_from.get(&position).is_some_and(|(_, best_cost)| *best_cost < cost) {
+                continue;
+            }
+
+            visited_count += 1;
+            if visited_count > self.params.max_nodes {
+                return None;
+            }
+
+            self.for_each_neighbor(position, |neighbor, move_cost| {
+                let neighbor_cost = cost + move_cost;
+
+                let is_better = came_from.get(&neighbor)
+                    .map_or(true, |(_, old_cost)| neighbor_cost < *old_cost);
+
+                if is_better {
+                    came_from.insert(neighbor, (position, neighbor_cost));
+
+                    let estimate = neighbor_cost + Self::heuristic(neighbor, goal);
+                    open_set.push(Reverse((estimate, neighbor_cost, neighbor.to_array())));
+                }
+            });
+        }
+
+        None
+    }
+
+    fn reconstruct_path(came_from: &FxHashMap<BlockPos, (BlockPos, u32)>, start: BlockPos, goal: BlockPos) -> Path {
+        let mut path = vec![goal];
+        let mut current = goal;
+
+        while current != start {
+            current = came_from[&current].0;
+            path.push(current);
+        }
+
+        path.reverse();
+        path
+    }
+}
+
+/// Starts searching for a path from `start` to `goal` on the task pool
+/// 
+/// The search runs on a snapshot of the chunks around the start and goal, the same way chunks are locked for meshing.
+/// Returns `None` if any of those chunks are not loaded, or if the start and goal are too far apart
+pub fn find_path(world: &World, start: BlockPos, goal: BlockPos, params: PathfindingParams) -> Option<Task<Option<Path>>> {
+    let corners = ChunkRegion::from_corners(ChunkPos::from(start), ChunkPos::from(goal));
+    let region = ChunkRegion {
+        min_chunk: corners.min_chunk - SEARCH_MARGIN,
+        size: corners.size + 2 * SEARCH_MARGIN.0.as_uvec3(),
+    };
+
+    if region.chunk_count() > MAX_SEARCH_CHUNKS {
+        return None;
+    }
+
+    let owned_chunk_area = OwnedChunkArea::new(world, region)?;
+
+    Some(TaskPool::get().spawn(move || {
+        let chunk_area = owned_chunk_area.read();
+
+        Pathfinder::new(&chunk_area, params).find_path(start, goal)
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::blocks::BlockStorage;
+    use crate::world::CHUNK_SIZE;
+    use super::*;
+
+    const FLOOR_HEIGHT: i32 = 4;
+    /// Taller than the default jump height and body height, so mobs can't get over it
+    const WALL_HEIGHT: i32 = 6;
+
+    /// Builds a world made of 1 chunk with a stone floor, and calls `build` to add more blocks to it
+    fn test_world<F: FnOnce(&mut BlockStorage)>(build: F) -> World {
+        let mut blocks = BlockStorage::default();
+
+        for x in 0..CHUNK_SIZE as i32 {
+            for z in 0..CHUNK_SIZE as i32 {
+                for y in 0..FLOOR_HEIGHT {
+                    blocks.new_block(BlockPos::new(x, y, z), BlockType::Stone);
+                }
+            }
+        }
+
+        build(&mut blocks);
+
+        World::with_chunks([(ChunkPos::new(0, 0, 0), blocks)])
+    }
+
+    fn add_wall(blocks: &mut BlockStorage, x: i32, z: i32) {
+        for y in FLOOR_HEIGHT..FLOOR_HEIGHT + WALL_HEIGHT {
+            blocks.new_block(BlockPos::new(x, y, z), BlockType::Stone);
+        }
+    }
+
+    fn search(world: &World, start: BlockPos, goal: BlockPos) -> Option<Path> {
+        let chunk_pos = ChunkPos::new(0, 0, 0);
+        let area = LockedChunkArea::new(world, ChunkRegion::from_corners(chunk_pos, chunk_pos)).unwrap();
+
+        Pathfinder::new(&area, PathfindingParams::default()).find_path(start, goal)
+    }
+
+    #[test]
+    fn path_goes_around_wall() {
+        // wall across the middle of the chunk with a gap at the high z end
+        let world = test_world(|blocks| {
+            for z in 0..26 {
+                add_wall(blocks, 12, z);
+            }
+        });
+
+        let start = BlockPos::new(6, FLOOR_HEIGHT, 6);
+        let goal = BlockPos::new(18, FLOOR_HEIGHT, 6);
+
+        let path = search(&world, start, goal).expect("no path around the wall");
+
+        assert_eq!(path.first(), Some(&start));
+        assert_eq!(path.last(), Some(&goal));
+
+        for step in path.windows(2) {
+            let diff = (step[1].0 - step[0].0).abs();
+            assert_eq!(diff.x + diff.z, 1, "path moves more than 1 block at once");
+            assert_eq!(diff.y, 0, "path leaves the floor");
+        }
+
+        assert!(path.iter().all(|position| position.x != 12 || position.z >= 26));
+        // the path through the gap is much longer than a straight line
+        assert!(path.len() > 40);
+    }
+
+    #[test]
+    fn path_steps_up_onto_ledge() {
+        let world = test_world(|blocks| {
+            for x in 10..CHUNK_SIZE as i32 {
+                for z in 0..CHUNK_SIZE as i32 {
+                    blocks.new_block(BlockPos::new(x, FLOOR_HEIGHT, z), BlockType::Stone);
+                }
+            }
+        });
+
+        let start = BlockPos::new(6, FLOOR_HEIGHT, 6);
+        let goal = BlockPos::new(14, FLOOR_HEIGHT + 1, 6);
+
+        let path = search(&world, start, goal).expect("no path onto the ledge");
+        assert_eq!(path.len(), 9);
+    }
+
+    #[test]
+    fn enclosed_goal_is_unreachable() {
+        let world = test_world(|blocks| {
+            for i in 18..=22 {
+                add_wall(blocks, i, 18);
+                add_wall(blocks, i, 22);
+                add_wall(blocks, 18, i);
+                add_wall(blocks, 22, i);
+            }
+        });
+
+        let goal = BlockPos::new(20, FLOOR_HEIGHT, 20);
+
+        assert!(search(&world, BlockPos::new(5, FLOOR_HEIGHT, 5), goal).is_none());
+        // the goal itself can be stood on, so the search really had to fail
+        let chunk_pos = ChunkPos::new(0, 0, 0);
+        let area = LockedChunkArea::new(&world, ChunkRegion::from_corners(chunk_pos, chunk_pos)).unwrap();
+        assert!(Pathfinder::new(&area, PathfindingParams::default()).is_walkable(goal));
+    }
+
+    #[test]
+    fn goal_in_air_is_unreachable() {
+        let world = test_world(|_| ());
+
+        assert!(search(&world, BlockPos::new(5, FLOOR_HEIGHT, 5), BlockPos::new(10, FLOOR_HEIGHT + 3, 10)).is_none());
+    }
+}
\ No newline at end of file
@@ -0,0 +1,351 @@
+use bevy::prelude::*;
+
+use crate::blocks::{Block, CollisionShape};
+use crate::types::*;
+use crate::world::{World, ChunkLockCache};
+
+/// Gap left between a moving box and whatever it collides with,
+/// so floating point error does not let the box sink into the block
+const COLLISION_EPSILON: f32 = 0.0001;
+
+/// An axis aligned box in render space
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundingBox {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl BoundingBox {
+    pub fn new(min: Vec3, max: Vec3) -> Self {
+        BoundingBox {
+            min,
+            max,
+        }
+    }
+
+    pub fn from_center(center: Vec3, half_extents: Vec3) -> Self {
+        BoundingBox {
+            min: center - half_extents,
+            max: center + half_extents,
+        }
+    }
+
+    /// The box taking up the entire space of a block with the given minimum corner
+    pub fn from_block_corner(block_min: Vec3) -> Self {
+        BoundingBox {
+            min: block_min,
+            max: block_min + Vec3::splat(BLOCK_SIZE),
+        }
+    }
+
+    pub fn size(&self) -> Vec3 {
+        self.max - self.min
+    }
+
+    pub fn center(&self) -> Vec3 {
+        (self.min + self.max) * 0.5
+    }
+
+    pub fn translate(&self, offset: Vec3) -> Self {
+        BoundingBox {
+            min: self.min + offset,
+            max: self.max + offset,
+        }
+    }
+
+    /// Returns the box covering all the space this box passes through when moved by `movement`
+    pub fn swept(&self, movement: Vec3) -> Self {
+        BoundingBox {
+            min: self.min.min(self.min + movement),
+            max: self.max.max(self.max + movement),
+        }
+    }
+
+    /// Returns true if the boxes overlap, boxes which are only touching do not count as intersecting
+    pub fn intersects(&self, other: &BoundingBox) -> bool {
+        self.min.cmplt(other.max).all() && self.max.cmpgt(other.min).all()
+    }
+
+    /// Returns true if the boxes overlap on the given axis
+    fn overlaps_on_axis(&self, other: &BoundingBox, axis: VecAxis) -> bool {
+        self.min[axis] < other.max[axis] && self.max[axis] > other.min[axis]
+    }
+
+    /// Returns the minimum and maximum positions (both inclusive) of blocks that this box overlaps,
+    /// relative to the block at the render space origin
+    fn render_block_range(&self) -> (IVec3, IVec3) {
+        let min = (self.min / BLOCK_SIZE).floor().as_ivec3();
+        let max = (self.max / BLOCK_SIZE).ceil().as_ivec3() - IVec3::ONE;
+
+        (min, max)
+    }
+}
+
+/// Calls `f` with each box that makes up the collision shape of the block with its minimum corner at `block_min`
+pub fn block_collision_boxes<F: FnMut(BoundingBox)>(block: Block, block_min: Vec3, mut f: F) {
+    match block.block_type().collision_shape() {
+        CollisionShape::Empty => (),
+        CollisionShape::Full => f(BoundingBox::from_block_corner(block_min)),
+        CollisionShape::Boxes(boxes) => {
+            for (min, max) in boxes {
+                f(BoundingBox {
+                    min: block_min + *min * BLOCK_SIZE,
+                    max: block_min + *max * BLOCK_SIZE,
+                });
+            }
+        },
+    }
+}
+
+/// The result of sweeping a box through the world
+#[derive(Debug, Default)]
+pub struct SweepResult {
+    /// How far the box can move along the velocity before colliding
+    pub movement: Vec3,
+    /// The normals of every surface the box collided with
+    pub normals: Vec<Vec3>,
+    /// The blocks which stopped the movement of the box
+    pub touched_blocks: Vec<BlockPos>,
+}
+
+impl SweepResult {
+    /// Returns true if the box hit anything
+    pub fn collided(&self) -> bool {
+        !self.normals.is_empty()
+    }
+
+    /// Returns true if the box collided with a surface facing in the direction of `normal`
+    pub fn hit_normal(&self, normal: Vec3) -> bool {
+        self.normals.contains(&normal)
+    }
+}
+
+/// Collects the collision boxes of all blocks overlapping `area`
+/// 
+/// Blocks in chunks which are not loaded or still being generated are treated as full blocks,
+/// so nothing can move into unloaded parts of the world
+fn collect_colliders(chunk_lock: &mut ChunkLockCache, origin: BlockPos, area: BoundingBox, out: &mut Vec<(BlockPos, BoundingBox)>) {
+    let (min, max) = area.render_block_range();
+
+    for x in min.x..=max.x {
+        for y in min.y..=max.y {
+            for z in min.z..=max.z {
+                let render_block_pos = BlockPos::new(x, y, z);
+                let block_pos = render_block_pos + origin;
+                let block_min = Vec3::from(render_block_pos);
+
+                match chunk_lock.get_loaded_block(block_pos) {
+                    Some(block) => block_collision_boxes(block, block_min, |collider| out.push((block_pos, collider))),
+                    None => out.push((block_pos, BoundingBox::from_block_corner(block_min))),
+                }
+            }
+        }
+    }
+}
+
+impl World {
+    /// The bounding box in render space taking up the entire space of the given block
+    pub fn block_bounding_box(&self, block_pos: BlockPos) -> BoundingBox {
+        BoundingBox::from_block_corner(self.block_render_pos(block_pos))
+    }
+
+    /// Moves the box along `velocity` one axis at a time, stopping on each axis when it would collide with a block
+    /// 
+    /// The y axis is resolved first, so a box resting on the ground can still slide along it
+    pub fn sweep_box(&self, bounds: BoundingBox, velocity: Vec3) -> SweepResult {
+        let mut chunk_lock = ChunkLockCache::new(self);
+        let mut result = SweepResult::default();
+
+        let mut current = bounds;
+        let mut colliders = Vec::new();
+
+        for axis in [VecAxis::Y, VecAxis::X, VecAxis::Z] {
+            let distance = velocity[axis];
+            if distance == 0.0 {
+                continue;
+            }
+
+            let mut movement = Vec3::ZERO;
+            movement[axis] = distance;
+
+            colliders.clear();
+            collect_colliders(&mut chunk_lock, BlockPos::from(self.origin()), current.swept(movement), &mut colliders);
+
+            let mut allowed = distance;
+            let mut hit_blocks = Vec::new();
+
+            for (block_pos, collider) in colliders.iter() {
+                let blocks_path = VecAxis::ALL.iter()
+                    .filter(|other_axis| **other_axis != axis)
+                    .all(|other_axis| current.overlaps_on_axis(collider, *other_axis));
+
+                if !blocks_path {
+                    continue;
+                }
+
+                // distance between the faces of the box and the collider, which is negative if they already overlap
+                let gap = if distance > 0.0 {
+                    collider.min[axis] - current.max[axis]
+                } else {
+                    current.min[axis] - collider.max[axis]
+                };
+
+                // the box is already partially inside of this collider, so let it move out
+                if gap < -COLLISION_EPSILON {
+                    continue;
+                }
+
+                let limit = (gap - COLLISION_EPSILON).max(0.0) * distance.signum();
+
+                if limit.abs() < allowed.abs() {
+                    allowed = limit;
+                    hit_blocks.clear();
+                    hit_blocks.push(*block_pos);
+                } else if limit == allowed && allowed != distance {
+                    hit_blocks.push(*block_pos);
+                }
+            }
+
+            if allowed != distance {
+                let mut normal = Vec3::ZERO;
+                normal[axis] = -distance.signum();
+
+                result.normals.push(normal);
+                result.touched_blocks.extend(hit_blocks);
+            }
+
+            movement[axis] = allowed;
+            result.movement[axis] = allowed;
+            current = current.translate(movement);
+        }
+
+        result
+    }
+
+    /// Returns true if the box overlaps any block's collision shape
+    pub fn box_collides(&self, bounds: BoundingBox) -> bool {
+        let mut chunk_lock = ChunkLockCache::new(self);
+        let mut colliders = Vec::new();
+        collect_colliders(&mut chunk_lock, BlockPos::from(self.origin()), bounds, &mut colliders);
+
+        colliders.iter().any(|(_, collider)| collider.intersects(&bounds))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::blocks::{BlockStorage, BlockType};
+    use crate::world::{Chunk, ChunkData, CHUNK_SIZE};
+    use super::*;
+
+    /// Height of the top of the floor in blocks
+    const FLOOR_HEIGHT: i32 = 4;
+    /// X position of the wall in blocks
+    const WALL_X: i32 = 20;
+
+    /// Builds a world with only the chunk at the origin loaded, which has a stone floor and a wall along the z axis
+    fn test_world() -> World {
+        let mut blocks = BlockStorage::default();
+
+        for x in 0..CHUNK_SIZE as i32 {
+            for z in 0..CHUNK_SIZE as i32 {
+                for y in 0..FLOOR_HEIGHT {
+                    blocks.new_block(BlockPos::new(x, y, z), BlockType::Stone);
+                }
+            }
+        }
+
+        for y in FLOOR_HEIGHT..CHUNK_SIZE as i32 {
+            for z in 0..CHUNK_SIZE as i32 {
+                blocks.new_block(BlockPos::new(WALL_X, y, z), BlockType::Stone);
+            }
+        }
+
+        World::with_chunks([(ChunkPos::new(0, 0, 0), blocks)])
+    }
+
+    #[test]
+    fn falling_box_lands_on_floor() {
+        let world = test_world();
+        let bounds = world.block_bounding_box(BlockPos::new(10, 10, 10));
+
+        let result = world.sweep_box(bounds, Vec3::new(0.0, -20.0, 0.0));
+        let landed = bounds.translate(result.movement);
+
+        assert!(result.hit_normal(Vec3::Y));
+        assert!((landed.min.y - FLOOR_HEIGHT as f32 * BLOCK_SIZE).abs() < 0.001);
+        assert!(result.touched_blocks.contains(&BlockPos::new(10, FLOOR_HEIGHT - 1, 10)));
+        assert!(!world.box_collides(landed));
+    }
+
+    #[test]
+    fn box_slides_until_it_hits_wall() {
+        let world = test_world();
+        let bounds = world.block_bounding_box(BlockPos::new(10, FLOOR_HEIGHT, 10));
+
+        // moving diagonally along the floor only stops the x movement
+        let result = world.sweep_box(bounds, Vec3::new(20.0, 0.0, 2.0));
+        let moved = bounds.translate(result.movement);
+
+        assert!(result.hit_normal(Vec3::NEG_X));
+        assert!(!result.hit_normal(Vec3::Y));
+        assert!((moved.max.x - WALL_X as f32 * BLOCK_SIZE).abs() < 0.001);
+        assert_eq!(result.movement.z, 2.0);
+        assert!(result.touched_blocks.iter().all(|block_pos| block_pos.x == WALL_X));
+    }
+
+    #[test]
+    fn box_moves_freely_through_air() {
+        let world = test_world();
+        let bounds = world.block_bounding_box(BlockPos::new(10, 10, 10));
+        let velocity = Vec3::new(1.0, 2.0, -1.5);
+
+        let result = world.sweep_box(bounds, velocity);
+
+        assert!(!result.collided());
+        assert_eq!(result.movement, velocity);
+    }
+
+    #[test]
+    fn unloaded_chunks_are_solid() {
+        let world = test_world();
+        let bounds = world.block_bounding_box(BlockPos::new(2, 10, 10));
+
+        let result = world.sweep_box(bounds, Vec3::new(-5.0, 0.0, 0.0));
+
+        assert!(result.hit_normal(Vec3::X));
+        assert!(bounds.translate(result.movement).min.x.abs() < 0.001);
+    }
+
+    #[test]
+    fn chunks_still_generating_are_solid() {
+        let mut world = test_world();
+        let chunk_pos = ChunkPos::new(0, 1, 0);
+        world.chunks.insert(chunk_pos, Arc::new(Chunk::new(chunk_pos, Entity::PLACEHOLDER, ChunkData::default())));
+
+        let bounds = world.block_bounding_box(BlockPos::new(10, CHUNK_SIZE as i32 - 2, 10));
+        let result = world.sweep_box(bounds, Vec3::new(0.0, 5.0, 0.0));
+
+        // the chunk above is empty until it is generated, but it should still stop the box
+        assert!(result.hit_normal(Vec3::NEG_Y));
+        assert!((bounds.translate(result.movement).max.y - CHUNK_SIZE as f32 * BLOCK_SIZE).abs() < 0.001);
+    }
+
+    #[test]
+    fn box_collides_only_when_overlapping() {
+        let world = test_world();
+
+        // inside the floor
+        assert!(world.box_collides(world.block_bounding_box(BlockPos::new(10, 1, 10))));
+        // resting exactly on top of the floor only touches it
+        assert!(!world.box_collides(world.block_bounding_box(BlockPos::new(10, FLOOR_HEIGHT, 10))));
+        // partly sunk into the floor
+        let sunk = world.block_bounding_box(BlockPos::new(10, FLOOR_HEIGHT, 10)).translate(Vec3::new(0.0, -0.1, 0.0));
+        assert!(world.box_collides(sunk));
+        // in the air next to the wall
+        assert!(!world.box_collides(world.block_bounding_box(BlockPos::new(WALL_X - 1, 10, 10))));
+        assert!(world.box_collides(world.block_bounding_box(BlockPos::new(WALL_X, 10, 10))));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,222 @@
+//! Small entities which are moved by the rigid body simulation
+
+use bevy::prelude::*;
+
+use crate::blocks::BlockType;
+use crate::items::{ItemStack, ItemType};
+use crate::types::*;
+use crate::world::World;
+use super::RigidBody;
+
+/// Half the size of a dropped item's bounding box in meters
+const DROPPED_ITEM_HALF_EXTENT: f32 = 0.125;
+
+/// An item lying in the world that can be picked up
+#[derive(Debug, Component)]
+pub struct DroppedItem(pub ItemStack);
+
+/// A block which is falling, and will turn back into a block once it lands
+#[derive(Debug, Clone, Copy, Component)]
+pub struct FallingBlock(pub BlockType);
+
+/// Spawns a dropped item centered at the given position in render space
+pub fn spawn_dropped_item(commands: &mut Commands, position: Vec3, stack: ItemStack, velocity: Vec3) -> Entity {
+    let body = RigidBody::new(Vec3::splat(DROPPED_ITEM_HALF_EXTENT))
+        .with_velocity(velocity)
+        .with_restitution(0.3);
+
+    commands.spawn((
+        DroppedItem(stack),
+        body,
+        TransformBundle::from_transform(Transform::from_translation(position)),
+    )).id()
+}
+
+/// Spawns a falling block in the space of the block at the given position
+/// 
+/// This does not remove the block already at that position
+pub fn spawn_falling_block(commands: &mut Commands, world: &World, block_pos: BlockPos, block_type: BlockType) -> Entity {
+    let half_extent = BLOCK_SIZE * 0.5;
+    let center = world.block_render_pos(block_pos) + Vec3::splat(half_extent);
+
+    // slightly smaller than a block so it can fall down a one block wide hole
+    let body = RigidBody::new(Vec3::splat(half_extent * 0.98));
+
+    commands.spawn((
+        FallingBlock(block_type),
+        body,
+        TransformBundle::from_transform(Transform::from_translation(center)),
+    )).id()
+}
+
+/// Turns blocks with gravity that were resting on a removed block into falling blocks
+/// 
+/// A falling block removes its own block, so a stack of blocks falls one block per tick
+pub(super) fn drop_unsupported_blocks(world: Res<World>, mut commands: Commands) {
+    for removed_pos in world.take_removed_blocks() {
+        // the space might have been filled again since the block was removed
+        if !world.get_block(removed_pos).is_some_and(|block| block.is_replaceable()) {
+            continue;
+        }
+
+        let above_pos = removed_pos + BlockPos::new(0, 1, 0);
+        let Some(above) = world.get_block(above_pos) else {
+            continue;
+        };
+
+        if above.block_type().has_gravity() {
+            world.new_block(above_pos, BlockType::Air);
+            spawn_falling_block(&mut commands, &world, above_pos, above.block_type());
+        }
+    }
+}
+
+/// Turns falling blocks which have hit the ground back into blocks,
+/// or into dropped items if the space they landed in is already taken
+/// 
+/// They land straight away instead of waiting to come to rest, so a falling stack can land on its own lower blocks
+pub(super) fn land_falling_blocks(
+    world: Res<World>,
+    falling_blocks: Query<(Entity, &FallingBlock, &RigidBody, &Transform)>,
+    mut commands: Commands,
+) {
+    for (entity, falling_block, body, transform) in falling_blocks.iter() {
+        if !body.on_ground() && !body.is_resting() {
+            continue;
+        }
+
+        commands.entity(entity).despawn();
+
+        let block_pos = world.block_pos_at(transform.translation);
+        if world.get_block(block_pos).is_some_and(|block| block.is_replaceable()) {
+            world.new_block(block_pos, falling_block.0);
+        } else {
+            let stack = ItemStack {
+                item: ItemType::Block(falling_block.0),
+                stack_size: 1,
+            };
+
+            spawn_dropped_item(&mut commands, transform.translation, stack, Vec3::ZERO);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::blocks::BlockStorage;
+    use crate::world::CHUNK_SIZE;
+    use super::super::{rigid_body::simulate_rigid_bodies, PHYSICS_TIMESTEP};
+    use super::*;
+
+    const FLOOR_HEIGHT: i32 = 4;
+
+    /// A world with only the chunk at the origin loaded, which has a stone floor and a dirt block holding up 2 sand blocks
+    fn test_world() -> World {
+        let mut blocks = BlockStorage::default();
+
+        for x in 0..CHUNK_SIZE as i32 {
+            for z in 0..CHUNK_SIZE as i32 {
+                for y in 0..FLOOR_HEIGHT {
+                    blocks.new_block(BlockPos::new(x, y, z), BlockType::Stone);
+                }
+            }
+        }
+
+        blocks.new_block(BlockPos::new(10, FLOOR_HEIGHT, 10), BlockType::Dirt);
+        blocks.new_block(BlockPos::new(10, FLOOR_HEIGHT + 1, 10), BlockType::Sand);
+        blocks.new_block(BlockPos::new(10, FLOOR_HEIGHT + 2, 10), BlockType::Sand);
+
+        World::with_chunks([(ChunkPos::new(0, 0, 0), blocks)])
+    }
+
+    /// Runs the physics systems once per update, without a window or any timing
+    fn test_app(world: World) -> App {
+        let mut app = App::new();
+        app.insert_resource(world)
+            .insert_resource(FixedTime::new(PHYSICS_TIMESTEP))
+            .add_systems(Update, (drop_unsupported_blocks, simulate_rigid_bodies, land_falling_blocks).chain());
+
+        app
+    }
+
+    fn block_type_at(app: &App, block_pos: BlockPos) -> BlockType {
+        app.world.resource::<World>().get_block(block_pos).unwrap().block_type()
+    }
+
+    #[test]
+    fn sand_falls_when_support_is_removed() {
+        let world = test_world();
+        world.new_block(BlockPos::new(10, FLOOR_HEIGHT, 10), BlockType::Air);
+
+        let mut app = test_app(world);
+        for _ in 0..300 {
+            app.update();
+        }
+
+        assert_eq!(block_type_at(&app, BlockPos::new(10, FLOOR_HEIGHT, 10)), BlockType::Sand);
+        assert_eq!(block_type_at(&app, BlockPos::new(10, FLOOR_HEIGHT + 1, 10)), BlockType::Sand);
+        assert_eq!(block_type_at(&app, BlockPos::new(10, FLOOR_HEIGHT + 2, 10)), BlockType::Air);
+
+        let falling_blocks = app.world.query::<&FallingBlock>().iter(&app.world).count();
+        assert_eq!(falling_blocks, 0);
+    }
+
+    #[test]
+    fn sand_replaces_water_it_lands_in() {
+        let world = test_world();
+        world.new_block(BlockPos::new(10, FLOOR_HEIGHT, 10), BlockType::Water);
+
+        let mut app = test_app(world);
+        for _ in 0..300 {
+            app.update();
+        }
+
+        // the sand sinks through the water to the floor, and lands in the water instead of dropping as an item
+        assert_eq!(block_type_at(&app, BlockPos::new(10, FLOOR_HEIGHT, 10)), BlockType::Sand);
+        assert_eq!(block_type_at(&app, BlockPos::new(10, FLOOR_HEIGHT + 1, 10)), BlockType::Sand);
+        assert_eq!(app.world.query::<&DroppedItem>().iter(&app.world).count(), 0);
+    }
+
+    #[test]
+    fn supported_sand_does_not_fall() {
+        let world = test_world();
+        // removing a block next to the sand does not leave it unsupported
+        world.new_block(BlockPos::new(11, FLOOR_HEIGHT - 1, 10), BlockType::Air);
+
+        let mut app = test_app(world);
+        app.update();
+        app.update();
+
+        assert_eq!(block_type_at(&app, BlockPos::new(10, FLOOR_HEIGHT + 1, 10)), BlockType::Sand);
+        assert_eq!(app.world.query::<&FallingBlock>().iter(&app.world).count(), 0);
+    }
+
+    #[test]
+    fn thrown_body_comes_to_rest_the_same_way_every_time() {
+        let world = test_world();
+        let delta = PHYSICS_TIMESTEP.as_secs_f32();
+
+        let simulate = || {
+            let mut body = RigidBody::new(Vec3::splat(DROPPED_ITEM_HALF_EXTENT))
+                .with_velocity(Vec3::new(3.0, 4.0, -2.0))
+                .with_restitution(0.3);
+            let mut position = world.block_render_pos(BlockPos::new(16, 8, 16));
+
+            let mut ticks = 0;
+            while !body.is_resting() {
+                body.step(&mut position, &world, delta);
+                ticks += 1;
+                assert!(ticks < 1000, "body never came to rest");
+            }
+
+            (position, ticks)
+        };
+
+        let (position, ticks) = simulate();
+        assert_eq!(simulate(), (position, ticks));
+
+        // resting on top of the floor
+        let floor_top = FLOOR_HEIGHT as f32 * BLOCK_SIZE;
+        assert!((position.y - DROPPED_ITEM_HALF_EXTENT - floor_top).abs() < 0.001);
+    }
+}
\ No newline at end of file
@@ -1,9 +1,41 @@
+use std::time::Duration;
+
 use bevy::prelude::*;
 
+use crate::world::ChunkSource;
+
+mod collision;
+pub use collision::*;
+mod entities;
+pub use entities::{spawn_dropped_item, spawn_falling_block, DroppedItem, FallingBlock};
+mod rigid_body;
+pub use rigid_body::RigidBody;
+
+/// Acceleration of gravity in meters per second squared
+pub const GRAVITY: f32 = 25.0;
+/// Fastest speed anything can fall at in meters per second
+pub const TERMINAL_VELOCITY: f32 = 50.0;
+
+/// Time between each tick of the rigid body simulation
+pub const PHYSICS_TIMESTEP: Duration = Duration::from_micros(16_667);
+
 pub struct PhysicsPlugin;
 
 impl Plugin for PhysicsPlugin {
     fn build(&self, app: &mut App) {
-        
+        app.insert_resource(FixedTime::new(PHYSICS_TIMESTEP))
+            .add_systems(
+                FixedUpdate,
+                (
+                    entities::drop_unsupported_blocks.run_if(edits_blocks),
+                    rigid_body::simulate_rigid_bodies,
+                    entities::land_falling_blocks.run_if(edits_blocks),
+                ).chain()
+            );
     }
+}
+
+/// Falling blocks are only simulated where the blocks come from, when connected to a server it sends the blocks they change
+fn edits_blocks(chunk_source: Res<ChunkSource>) -> bool {
+    *chunk_source != ChunkSource::Remote
 }
\ No newline at end of file
@@ -0,0 +1,157 @@
+use bevy::prelude::*;
+
+use crate::world::World;
+use super::{BoundingBox, GRAVITY, TERMINAL_VELOCITY};
+
+/// Bounces slower than this in meters per second are absorbed instead of bouncing
+const MIN_BOUNCE_SPEED: f32 = 0.5;
+/// Bodies moving slower than this in meters per second while on the ground are considered to be coming to rest
+const REST_SPEED: f32 = 0.05;
+/// Number of physics ticks a body must be slow and on the ground for before it starts resting
+const REST_TICKS: u32 = 10;
+/// How far below a resting body is checked to see if it is still supported
+const SUPPORT_CHECK_DEPTH: f32 = 0.01;
+
+/// A small box shaped body which is moved by the physics simulation and collides with the world
+/// 
+/// The body's position is the translation of its transform, which is the center of the box
+#[derive(Debug, Clone, Component)]
+pub struct RigidBody {
+    pub velocity: Vec3,
+    pub half_extents: Vec3,
+    /// Multiplied by gravity to get the acceleration this body falls at
+    pub gravity_scale: f32,
+    /// Fraction of speed kept when bouncing off a surface, 0 is no bounce and 1 is a perfect bounce
+    pub restitution: f32,
+    /// Fraction of horizontal speed lost per second while sliding on the ground
+    pub friction: f32,
+    on_ground: bool,
+    /// Resting bodies are not simulated until the ground under them disappears
+    resting: bool,
+    slow_ticks: u32,
+}
+
+impl RigidBody {
+    pub fn new(half_extents: Vec3) -> Self {
+        RigidBody {
+            velocity: Vec3::ZERO,
+            half_extents,
+            gravity_scale: 1.0,
+            restitution: 0.0,
+            friction: 5.0,
+            on_ground: false,
+            resting: false,
+            slow_ticks: 0,
+        }
+    }
+
+    pub fn with_velocity(mut self, velocity: Vec3) -> Self {
+        self.velocity = velocity;
+        self
+    }
+
+    pub fn with_restitution(mut self, restitution: f32) -> Self {
+        self.restitution = restitution;
+        self
+    }
+
+    pub fn with_friction(mut self, friction: f32) -> Self {
+        self.friction = friction;
+        self
+    }
+
+    pub fn with_gravity_scale(mut self, gravity_scale: f32) -> Self {
+        self.gravity_scale = gravity_scale;
+        self
+    }
+
+    pub fn on_ground(&self) -> bool {
+        self.on_ground
+    }
+
+    pub fn is_resting(&self) -> bool {
+        self.resting
+    }
+
+    /// Wakes up the body if it was resting, and sets its velocity
+    pub fn set_velocity(&mut self, velocity: Vec3) {
+        self.velocity = velocity;
+        self.resting = false;
+        self.slow_ticks = 0;
+    }
+
+    pub fn bounding_box(&self, position: Vec3) -> BoundingBox {
+        BoundingBox::from_center(position, self.half_extents)
+    }
+
+    /// Advances the body by `delta` seconds, moving `position` and colliding with blocks in the world
+    /// 
+    /// This only depends on its inputs, so the same starting state and world will always give the same result
+    pub fn step(&mut self, position: &mut Vec3, world: &World, delta: f32) {
+        let bounds = self.bounding_box(*position);
+
+        if self.resting {
+            let support = bounds.translate(Vec3::new(0.0, -SUPPORT_CHECK_DEPTH, 0.0));
+            if world.box_collides(support) {
+                return;
+            }
+
+            // the ground under the body was removed, so start falling again
+            self.resting = false;
+            self.slow_ticks = 0;
+        }
+
+        self.velocity.y = (self.velocity.y - GRAVITY * self.gravity_scale * delta).max(-TERMINAL_VELOCITY);
+
+        let result = world.sweep_box(bounds, self.velocity * delta);
+        *position += result.movement;
+
+        for normal in result.normals.iter() {
+            // remove the velocity going into the surface, then bounce some of it back out
+            let into_surface = self.velocity.dot(*normal);
+            if into_surface < 0.0 {
+                let bounce_speed = -into_surface * self.restitution;
+                let bounce_speed = if bounce_speed < MIN_BOUNCE_SPEED {
+                    0.0
+                } else {
+                    bounce_speed
+                };
+
+                self.velocity += *normal * (bounce_speed - into_surface);
+            }
+        }
+
+        self.on_ground = result.hit_normal(Vec3::Y);
+
+        if self.on_ground {
+            let friction_factor = (1.0 - self.friction * delta).max(0.0);
+            self.velocity.x *= friction_factor;
+            self.velocity.z *= friction_factor;
+
+            if self.velocity.length() < REST_SPEED {
+                self.slow_ticks += 1;
+            } else {
+                self.slow_ticks = 0;
+            }
+
+            if self.slow_ticks >= REST_TICKS {
+                self.resting = true;
+                self.velocity = Vec3::ZERO;
+            }
+        } else {
+            self.slow_ticks = 0;
+        }
+    }
+}
+
+pub(super) fn simulate_rigid_bodies(
+    world: Res<World>,
+    fixed_time: Res<FixedTime>,
+    mut bodies: Query<(&mut RigidBody, &mut Transform)>,
+) {
+    let delta = fixed_time.period.as_secs_f32();
+
+    for (mut body, mut transform) in bodies.iter_mut() {
+        body.step(&mut transform.translation, &world, delta);
+    }
+}
\ No newline at end of file
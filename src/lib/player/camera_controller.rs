@@ -4,14 +4,67 @@ use bevy::math::Vec4Swizzles;
 use bevy::window::CursorGrabMode;
 
 use crate::GameSet;
-use crate::world::ChunkLoader;
-use crate::types::ChunkPos;
+use crate::physics::{BoundingBox, SweepResult, GRAVITY, TERMINAL_VELOCITY};
+use crate::types::BLOCK_SIZE;
+use crate::world::World;
+use super::player_bounding_box;
 
-const SPEED: f32 = 0.25;
-const FAST_SPEED: f32 = 5.0;
 const ROTATION_SPEED: f32 = 2.0;
 const MOUSE_ROTATION_SPEED: f32 = 0.001;
 
+/// Upwards velocity the player gets when jumping, enough to jump a bit over 2 blocks
+const JUMP_SPEED: f32 = 7.5;
+/// Tallest ledge the player will automatically walk up onto, which is half a block
+const STEP_HEIGHT: f32 = BLOCK_SIZE / 2.0 + 0.01;
+/// How far below the player is checked for ground when deciding if a crouching player would walk off a ledge
+const CROUCH_EDGE_CHECK_DEPTH: f32 = 0.05;
+
+/// Speeds used by the controller, these can be changed while the game is running
+/// 
+/// All speeds are in meters per second, and accelerations are in meters per second squared
+#[derive(Debug, Clone, Resource, Reflect)]
+#[reflect(Resource)]
+pub struct MovementSettings {
+    pub fly_speed: f32,
+    pub fly_sprint_speed: f32,
+    pub walk_speed: f32,
+    pub sprint_speed: f32,
+    pub crouch_speed: f32,
+    /// How quickly the player speeds up to the speed they are trying to move at
+    pub acceleration: f32,
+    /// Acceleration used instead of `acceleration` when walking and not on the ground
+    pub air_acceleration: f32,
+    /// Rate of exponential decay of the velocity when the player is not trying to move,
+    /// each second the velocity is multiplied by `e^-damping`
+    pub damping: f32,
+}
+
+impl Default for MovementSettings {
+    fn default() -> Self {
+        MovementSettings {
+            // these match the old per frame fly speeds at 60 fps
+            fly_speed: 15.0,
+            fly_sprint_speed: 300.0,
+            walk_speed: 4.3,
+            sprint_speed: 5.6,
+            crouch_speed: 1.3,
+            acceleration: 40.0,
+            air_acceleration: 10.0,
+            damping: 10.0,
+        }
+    }
+}
+
+/// Moves `velocity` towards `target_velocity` without changing faster than `acceleration`,
+/// or slows `velocity` down using `damping` when there is no target velocity
+fn approach_velocity(velocity: Vec3, target_velocity: Vec3, acceleration: f32, damping: f32, delta: f32) -> Vec3 {
+    if target_velocity == Vec3::ZERO {
+        velocity * (-damping * delta).exp()
+    } else {
+        velocity + (target_velocity - velocity).clamp_length_max(acceleration * delta)
+    }
+}
+
 #[derive(Component, Default)]
 pub struct Controller {
     forward_pressed: bool,
@@ -23,6 +76,13 @@ pub struct Controller {
     horizantal_rotation: f32,
     verticle_rotation: f32,
     sprint_pressed: bool,
+    crouch_pressed: bool,
+    /// When true the player flies freely through blocks, otherwise they walk and collide with the world
+    pub flying: bool,
+    /// Velocity in meters per second
+    velocity: Vec3,
+    /// True if the player was standing on something last frame
+    on_ground: bool,
 }
 
 fn handle_keyboard_input(
@@ -42,6 +102,14 @@ fn handle_keyboard_input(
 
         controller.sprint_pressed = keys.pressed(KeyCode::ShiftLeft)
             || keys.pressed(KeyCode::ShiftRight);
+
+        controller.crouch_pressed = keys.pressed(KeyCode::ControlLeft)
+            || keys.pressed(KeyCode::ControlRight);
+
+        if keys.just_pressed(KeyCode::F) {
+            controller.flying = !controller.flying;
+            controller.velocity = Vec3::ZERO;
+        }
     }
 }
 
@@ -63,77 +131,243 @@ fn handle_mouse_input(
     }
 }
 
-fn move_camera(
-    time: Res<Time>,
-    mut query: Query<(&mut Transform, &Controller)>,
-) {
-    let delta = time.delta_seconds();
-    let up = Vec3::Y;
+/// Moves the bounding box by `movement`, stepping up onto any ledge shorter than [`STEP_HEIGHT`] that blocks horizontal movement
+fn sweep_with_step(world: &World, bounds: BoundingBox, movement: Vec3) -> SweepResult {
+    let result = world.sweep_box(bounds, movement);
 
-    for (mut camera_transform, controller) in query.iter_mut() {
-        // all these or from camera pov
-        let camera_forward = camera_transform.forward();
-        let camera_right = camera_transform.right();
-        let camera_up = camera_transform.up();
+    let horizontal_blocked = result.movement.x != movement.x || result.movement.z != movement.z;
+    if !horizontal_blocked {
+        return result;
+    }
 
-        let camera_forward_norm = camera_forward.normalize();
-        let camera_right_norm = camera_right.normalize();
-        let camera_up_norm = camera_up.normalize();
+    // try moving up, then across, then back down to see if the player can get further by stepping up
+    let up = world.sweep_box(bounds, Vec3::new(0.0, STEP_HEIGHT, 0.0));
+    let raised_bounds = bounds.translate(up.movement);
 
-        let distance_moved = if controller.sprint_pressed {
-            FAST_SPEED
-        } else {
-            SPEED
-        };
+    let across = world.sweep_box(raised_bounds, Vec3::new(movement.x, 0.0, movement.z));
+    let moved_bounds = raised_bounds.translate(across.movement);
 
-        // TODO: change so total speed is not increesed when going diagonal
-        if controller.forward_pressed {
-            camera_transform.translation += camera_forward_norm * distance_moved;
-        }
-        if controller.backward_pressed {
-            camera_transform.translation -= camera_forward_norm * distance_moved;
-        }
+    let down = world.sweep_box(moved_bounds, Vec3::new(0.0, movement.y.min(0.0) - up.movement.y, 0.0));
 
-        if controller.left_pressed {
-            camera_transform.translation -= camera_right_norm * distance_moved;
-        }
-        if controller.right_pressed {
-            camera_transform.translation += camera_right_norm * distance_moved;
-        }
+    let step_distance = across.movement.x * across.movement.x + across.movement.z * across.movement.z;
+    let direct_distance = result.movement.x * result.movement.x + result.movement.z * result.movement.z;
 
-        if controller.up_pressed {
-            camera_transform.translation += camera_up_norm * distance_moved;
-        }
-        if controller.down_pressed {
-            camera_transform.translation -= camera_up_norm * distance_moved;
+    // only step up if the player actually ends up standing on something
+    if step_distance > direct_distance && down.hit_normal(Vec3::Y) {
+        let mut normals = across.normals;
+        normals.extend(down.normals);
+
+        let mut touched_blocks = across.touched_blocks;
+        touched_blocks.extend(down.touched_blocks);
+
+        SweepResult {
+            movement: up.movement + across.movement + down.movement,
+            normals,
+            touched_blocks,
         }
+    } else {
+        result
+    }
+}
 
-        let mut forward4 = Vec4::new(
-            camera_forward.x,
-            camera_forward.y,
-            camera_forward.z,
-            0.0,
-        );
+/// Stops a crouching player from walking off the edge of a block
+fn clamp_crouch_movement(world: &World, bounds: BoundingBox, mut movement: Vec3) -> Vec3 {
+    let has_ground = |offset: Vec3| {
+        let check_bounds = bounds.translate(offset - Vec3::new(0.0, CROUCH_EDGE_CHECK_DEPTH, 0.0));
+        world.box_collides(check_bounds)
+    };
 
-        let verticle_rotation = Mat4::from_axis_angle(camera_right_norm, -controller.verticle_rotation);
-        let forward_temp = verticle_rotation * forward4;
+    if !has_ground(Vec3::new(movement.x, 0.0, 0.0)) {
+        movement.x = 0.0;
+    }
 
-        // stop camera from rotating all the way around top or bottom
-        if forward_temp.xyz().normalize().dot(up).abs() < 0.995 {
-            forward4 = forward_temp;
-        }
+    if !has_ground(Vec3::new(0.0, 0.0, movement.z)) {
+        movement.z = 0.0;
+    }
+
+    if !has_ground(Vec3::new(movement.x, 0.0, movement.z)) {
+        movement.x = 0.0;
+        movement.z = 0.0;
+    }
+
+    movement
+}
+
+fn walk(world: &World, settings: &MovementSettings, delta: f32, camera_transform: &mut Transform, controller: &mut Controller) {
+    // walking direction ignores where the camera is looking vertically
+    let forward = Vec3::new(camera_transform.forward().x, 0.0, camera_transform.forward().z).normalize_or_zero();
+    let right = Vec3::new(camera_transform.right().x, 0.0, camera_transform.right().z).normalize_or_zero();
+
+    let mut wish_direction = Vec3::ZERO;
+    if controller.forward_pressed {
+        wish_direction += forward;
+    }
+    if controller.backward_pressed {
+        wish_direction -= forward;
+    }
+    if controller.left_pressed {
+        wish_direction -= right;
+    }
+    if controller.right_pressed {
+        wish_direction += right;
+    }
 
-        let horizantal_rotation = Mat4::from_axis_angle(up, -controller.horizantal_rotation);
-        forward4 = horizantal_rotation * forward4;
+    let speed = if controller.crouch_pressed {
+        settings.crouch_speed
+    } else if controller.sprint_pressed {
+        settings.sprint_speed
+    } else {
+        settings.walk_speed
+    };
 
-        let forward = forward4.xyz();
+    let acceleration = if controller.on_ground {
+        settings.acceleration
+    } else {
+        settings.air_acceleration
+    };
 
-        *camera_transform = camera_transform.looking_at(camera_transform.translation + forward, up);
+    let horizontal_velocity = approach_velocity(
+        Vec3::new(controller.velocity.x, 0.0, controller.velocity.z),
+        wish_direction.normalize_or_zero() * speed,
+        acceleration,
+        settings.damping,
+        delta,
+    );
+    controller.velocity.x = horizontal_velocity.x;
+    controller.velocity.z = horizontal_velocity.z;
+
+    if controller.on_ground && controller.up_pressed {
+        controller.velocity.y = JUMP_SPEED;
+    }
+
+    controller.velocity.y = (controller.velocity.y - GRAVITY * delta).max(-TERMINAL_VELOCITY);
+
+    let bounds = player_bounding_box(camera_transform.translation);
+    let mut movement = controller.velocity * delta;
+
+    if controller.on_ground && controller.crouch_pressed {
+        movement = clamp_crouch_movement(world, bounds, movement);
+    }
+
+    let result = if controller.on_ground {
+        sweep_with_step(world, bounds, movement)
+    } else {
+        world.sweep_box(bounds, movement)
+    };
+
+    camera_transform.translation += result.movement;
+
+    controller.on_ground = result.hit_normal(Vec3::Y);
+    if controller.on_ground || result.hit_normal(Vec3::NEG_Y) {
+        controller.velocity.y = 0.0;
+    }
+
+    // stop moving into walls so velocity does not build up while pressed against them
+    if result.hit_normal(Vec3::X) || result.hit_normal(Vec3::NEG_X) {
+        controller.velocity.x = 0.0;
+    }
+    if result.hit_normal(Vec3::Z) || result.hit_normal(Vec3::NEG_Z) {
+        controller.velocity.z = 0.0;
+    }
+}
+
+fn fly(settings: &MovementSettings, delta: f32, camera_transform: &mut Transform, controller: &mut Controller) {
+    // all these or from camera pov
+    let camera_forward_norm = camera_transform.forward().normalize();
+    let camera_right_norm = camera_transform.right().normalize();
+    let camera_up_norm = camera_transform.up().normalize();
+
+    let mut wish_direction = Vec3::ZERO;
+    if controller.forward_pressed {
+        wish_direction += camera_forward_norm;
+    }
+    if controller.backward_pressed {
+        wish_direction -= camera_forward_norm;
+    }
+
+    if controller.left_pressed {
+        wish_direction -= camera_right_norm;
+    }
+    if controller.right_pressed {
+        wish_direction += camera_right_norm;
+    }
+
+    if controller.up_pressed {
+        wish_direction += camera_up_norm;
+    }
+    if controller.down_pressed {
+        wish_direction -= camera_up_norm;
+    }
+
+    let speed = if controller.sprint_pressed {
+        settings.fly_sprint_speed
+    } else {
+        settings.fly_speed
+    };
+
+    // normalize so moving diagonally is not faster than moving along one direction
+    let target_velocity = wish_direction.normalize_or_zero() * speed;
+
+    // scale acceleration with speed so sprinting does not take forever to get up to speed
+    let acceleration = settings.acceleration * speed / settings.walk_speed;
+
+    controller.velocity = approach_velocity(controller.velocity, target_velocity, acceleration, settings.damping, delta);
+    camera_transform.translation += controller.velocity * delta;
+}
+
+fn rotate_camera(camera_transform: &mut Transform, controller: &Controller) {
+    let up = Vec3::Y;
+
+    let camera_forward = camera_transform.forward();
+    let camera_right_norm = camera_transform.right().normalize();
+
+    let mut forward4 = Vec4::new(
+        camera_forward.x,
+        camera_forward.y,
+        camera_forward.z,
+        0.0,
+    );
+
+    let verticle_rotation = Mat4::from_axis_angle(camera_right_norm, -controller.verticle_rotation);
+    let forward_temp = verticle_rotation * forward4;
+
+    // stop camera from rotating all the way around top or bottom
+    if forward_temp.xyz().normalize().dot(up).abs() < 0.995 {
+        forward4 = forward_temp;
+    }
+
+    let horizantal_rotation = Mat4::from_axis_angle(up, -controller.horizantal_rotation);
+    forward4 = horizantal_rotation * forward4;
+
+    let forward = forward4.xyz();
+
+    *camera_transform = camera_transform.looking_at(camera_transform.translation + forward, up);
+}
+
+fn move_camera(
+    time: Res<Time>,
+    world: Res<World>,
+    settings: Res<MovementSettings>,
+    mut query: Query<(&mut Transform, &mut Controller)>,
+) {
+    let delta = time.delta_seconds();
+
+    for (mut camera_transform, mut controller) in query.iter_mut() {
+        if controller.flying {
+            fly(&settings, delta, &mut camera_transform, &mut controller);
+        } else {
+            walk(&world, &settings, delta, &mut camera_transform, &mut controller);
+        }
+
+        rotate_camera(&mut camera_transform, &controller);
     }
 }
 
 fn grab_mouse(mut query: Query<&mut Window>) {
-    let mut window = query.single_mut();
+    // there is no window when running headless
+    let Ok(mut window) = query.get_single_mut() else {
+        return;
+    };
 
     window.cursor.visible = false;
     window.cursor.grab_mode = CursorGrabMode::Locked;
@@ -143,7 +377,9 @@ pub struct ControllerPlugin;
 
 impl Plugin for ControllerPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, grab_mouse)
+        app.init_resource::<MovementSettings>()
+            .register_type::<MovementSettings>()
+            .add_systems(Startup, grab_mouse)
             .add_systems(
                 Update,
                 (
@@ -152,4 +388,73 @@ impl Plugin for ControllerPlugin {
                 ).chain().in_set(GameSet::Main)
             );
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::blocks::{BlockStorage, BlockType};
+    use crate::types::{BlockPos, ChunkPos};
+    use crate::world::CHUNK_SIZE;
+    use super::super::PLAYER_EYE_HEIGHT;
+    use super::*;
+
+    /// Height of the top of the floor in blocks
+    const FLOOR_HEIGHT: i32 = 4;
+    /// X position of the step in blocks
+    const STEP_X: i32 = 12;
+    /// Distance between the player and the step before they move
+    const PLAYER_GAP: f32 = 0.1;
+    /// Movement in one frame of walking towards the step, with a bit of gravity pulling the player down
+    const MOVEMENT: Vec3 = Vec3::new(0.3, -0.01, 0.0);
+
+    /// Builds a world with a stone floor and a row of `step` blocks on top of it along the z axis
+    fn world_with_step(step: BlockType) -> World {
+        let mut blocks = BlockStorage::default();
+
+        for x in 0..CHUNK_SIZE as i32 {
+            for z in 0..CHUNK_SIZE as i32 {
+                for y in 0..FLOOR_HEIGHT {
+                    blocks.new_block(BlockPos::new(x, y, z), BlockType::Stone);
+                }
+            }
+        }
+
+        for z in 0..CHUNK_SIZE as i32 {
+            blocks.new_block(BlockPos::new(STEP_X, FLOOR_HEIGHT, z), step);
+        }
+
+        World::with_chunks([(ChunkPos::new(0, 0, 0), blocks)])
+    }
+
+    /// Bounding box of a player standing on the floor a bit before the step
+    fn player_before_step() -> BoundingBox {
+        let feet = Vec3::new(STEP_X as f32 * BLOCK_SIZE - PLAYER_GAP - 0.3, FLOOR_HEIGHT as f32 * BLOCK_SIZE, 8.0);
+        player_bounding_box(feet + Vec3::new(0.0, PLAYER_EYE_HEIGHT, 0.0))
+    }
+
+    #[test]
+    fn player_steps_onto_slab() {
+        let world = world_with_step(BlockType::StoneSlab);
+        let bounds = player_before_step();
+
+        let result = sweep_with_step(&world, bounds, MOVEMENT);
+        let moved = bounds.translate(result.movement);
+
+        assert!((result.movement.x - MOVEMENT.x).abs() < 0.001, "player only moved {} towards the slab", result.movement.x);
+        assert!((moved.min.y - (FLOOR_HEIGHT as f32 + 0.5) * BLOCK_SIZE).abs() < 0.001, "player is at {} instead of on the slab", moved.min.y);
+        assert!(result.hit_normal(Vec3::Y));
+        assert!(!world.box_collides(moved));
+    }
+
+    #[test]
+    fn player_does_not_step_onto_full_block() {
+        let world = world_with_step(BlockType::Stone);
+        let bounds = player_before_step();
+
+        let result = sweep_with_step(&world, bounds, MOVEMENT);
+        let moved = bounds.translate(result.movement);
+
+        assert!(result.movement.x <= PLAYER_GAP + 0.001, "player moved {} into the block", result.movement.x);
+        assert!((moved.min.y - FLOOR_HEIGHT as f32 * BLOCK_SIZE).abs() < 0.001, "player is at {} instead of on the floor", moved.min.y);
+    }
 }
\ No newline at end of file
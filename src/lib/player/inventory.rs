@@ -1,15 +1,105 @@
 use bevy::prelude::*;
 
-use crate::{items::{WeaponUseTime, ItemUseSet, ItemStack}, GameSet};
+use crate::{items::{WeaponUseTime, ItemUseSet, ItemStack, ItemType}, GameSet};
 
 use super::ControlledPlayer;
 
+/// Number of item slots in each row of the inventory
+const INVENTORY_WIDTH: usize = 10;
+
+/// Keys used to select each slot of the hotbar
+const HOTBAR_KEYS: [KeyCode; INVENTORY_WIDTH] = [
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+    KeyCode::Key0,
+];
+
 #[derive(Debug, Default, Component)]
 pub struct Inventory {
     /// All the items in the inventory, with row 0 being the hotbar
-    items: [[Option<ItemStack>; 10]; 5],
+    items: [[Option<ItemStack>; INVENTORY_WIDTH]; 5],
     selected_hotbar_index: usize,
     pub(super) selected_item: Option<Entity>,
+    /// The item type `selected_item` was spawned for
+    selected_item_type: Option<ItemType>,
+}
+
+impl Inventory {
+    /// Puts the item stack in the given hotbar slot, replacing what was there before
+    pub fn set_hotbar_slot(&mut self, index: usize, stack: ItemStack) {
+        self.items[0][index] = Some(stack);
+    }
+
+    pub fn selected_stack(&self) -> Option<&ItemStack> {
+        self.items[0][self.selected_hotbar_index].as_ref()
+    }
+
+    /// Removes one item from the selected stack if it holds the given item type
+    /// 
+    /// Returns true if an item was consumed
+    pub fn consume_selected(&mut self, item: ItemType) -> bool {
+        let slot = &mut self.items[0][self.selected_hotbar_index];
+
+        let Some(stack) = slot else {
+            return false;
+        };
+
+        if stack.item != item || stack.stack_size == 0 {
+            return false;
+        }
+
+        stack.stack_size -= 1;
+        if stack.stack_size == 0 {
+            *slot = None;
+        }
+
+        true
+    }
+}
+
+fn select_hotbar_slot(
+    mut players: Query<&mut Inventory, With<ControlledPlayer>>,
+    keys: Res<Input<KeyCode>>,
+) {
+    for mut inventory in players.iter_mut() {
+        for (i, key) in HOTBAR_KEYS.iter().enumerate() {
+            if keys.just_pressed(*key) {
+                inventory.selected_hotbar_index = i;
+            }
+        }
+    }
+}
+
+/// Respawns the held item entity when the selected stack changes or runs out
+fn update_selected_item(
+    mut players: Query<(Entity, &mut Inventory)>,
+    mut commands: Commands,
+) {
+    for (player, mut inventory) in players.iter_mut() {
+        let selected_type = inventory.selected_stack().map(|stack| stack.item);
+        if selected_type == inventory.selected_item_type {
+            continue;
+        }
+
+        if let Some(old_item) = inventory.selected_item.take() {
+            commands.entity(old_item).despawn_recursive();
+        }
+
+        if let Some(item_type) = selected_type {
+            let item = item_type.spawn_bundle(&mut commands);
+            commands.entity(player).add_child(item);
+            inventory.selected_item = Some(item);
+        }
+
+        inventory.selected_item_type = selected_type;
+    }
 }
 
 fn mark_item_for_use(
@@ -20,7 +110,7 @@ fn mark_item_for_use(
     for inventory in players.iter() {
         if let Some(selected_item) = inventory.selected_item {
             if let Ok(mut item) = items.get_mut(selected_item) {
-                item.currently_using = buttons.pressed(MouseButton::Left);
+                item.currently_using = buttons.pressed(item.use_button());
             }
         }
     }
@@ -31,7 +121,13 @@ pub struct InventoryPlugin;
 impl Plugin for InventoryPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(Update, 
-            mark_item_for_use
+            (
+                select_hotbar_slot,
+                update_selected_item,
+                apply_deferred,
+                mark_item_for_use,
+            )
+                .chain()
                 .in_set(GameSet::Main)
                 .before(ItemUseSet)
         );
@@ -1,32 +1,67 @@
 use bevy::prelude::*;
 
-use crate::items::ItemType;
-use crate::{world::ChunkLoader, types::ChunkPos};
+use crate::blocks::BlockType;
+use crate::items::{ItemType, ItemStack};
+use crate::physics::BoundingBox;
+use crate::{world::{ChunkLoader, FloatingOriginAnchor}, types::ChunkPos};
 
 mod camera_controller;
+pub use camera_controller::MovementSettings;
 mod inventory;
+pub use inventory::Inventory;
 
 const RENDER_DISTANCE: UVec3 = UVec3::new(10, 5, 10);
 
+/// Position of the player's camera when they first spawn, which is high enough to be above the terrain
+const SPAWN_POSITION: Vec3 = Vec3::new(0.0, 10.0, 0.0);
+
+/// Width of the player's bounding box along the x and z axis in meters
+pub const PLAYER_WIDTH: f32 = 0.6;
+/// Height of the player's bounding box in meters
+pub const PLAYER_HEIGHT: f32 = 1.8;
+/// Height of the camera above the bottom of the player's bounding box
+pub const PLAYER_EYE_HEIGHT: f32 = 1.6;
+
+/// Returns the player's bounding box given the position of the camera
+pub fn player_bounding_box(eye_position: Vec3) -> BoundingBox {
+    let half_width = PLAYER_WIDTH / 2.0;
+
+    let min = eye_position - Vec3::new(half_width, PLAYER_EYE_HEIGHT, half_width);
+    let max = min + Vec3::new(PLAYER_WIDTH, PLAYER_HEIGHT, PLAYER_WIDTH);
+
+    BoundingBox::new(min, max)
+}
+
 /// Marks the player that is currently being controlled
 #[derive(Component)]
-struct ControlledPlayer;
+pub struct ControlledPlayer;
 
 fn setup_player(mut commands: Commands) {
-    let debug_miner = ItemType::DebugMiner.spawn_bundle(&mut commands);
+    let mut inventory = Inventory::default();
+    inventory.set_hotbar_slot(0, ItemStack {
+        item: ItemType::DebugMiner,
+        stack_size: 1,
+    });
 
-    let mut inventory = inventory::Inventory::default();
-    inventory.selected_item = Some(debug_miner);
+    let block_types = [BlockType::Dirt, BlockType::Grass, BlockType::Stone, BlockType::Sand, BlockType::Sandstone, BlockType::Snow, BlockType::Log, BlockType::Leaves];
+    for (i, block_type) in block_types.into_iter().enumerate() {
+        inventory.set_hotbar_slot(i + 1, ItemStack {
+            item: ItemType::Block(block_type),
+            stack_size: 64,
+        });
+    }
 
-    let player = commands.spawn((
+    commands.spawn((
         ControlledPlayer,
-        Camera3dBundle::default(),
+        Camera3dBundle {
+            transform: Transform::from_translation(SPAWN_POSITION),
+            ..Default::default()
+        },
         camera_controller::Controller::default(),
         ChunkLoader::new(ChunkPos::new(0, 0, 0), RENDER_DISTANCE),
+        FloatingOriginAnchor,
         inventory,
-    )).id();
-
-    commands.entity(player).push_children(&[debug_miner]);
+    ));
 }
 
 pub struct PlayerPlugin;
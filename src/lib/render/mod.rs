@@ -10,8 +10,6 @@ pub use material::*;
 mod texture_map;
 pub use texture_map::*;
 
-use self::material::BlockMaterial;
-
 const SKY_COLOR: Color = Color::Rgba {
     red: 0.4,
     green: 0.6,
@@ -39,6 +37,7 @@ impl Plugin for RenderPlugin {
             })
             .add_plugins(MaterialPlugin::<BlockMaterial>::default())
             .add_state::<TextureLoadState>()
+            // the headless server has no textures to wait for, so only the client waits for them
             .configure_set(Update, GameSet::Main.run_if(in_state(TextureLoadState::Done)))
             .add_systems(OnEnter(TextureLoadState::Loading), load_textures)
             .add_systems(Update, poll_load_status.run_if(in_state(TextureLoadState::Loading)))
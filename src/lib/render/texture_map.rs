@@ -103,6 +103,8 @@ impl TextureBuilder {
 #[derive(Debug, Clone, Copy)]
 pub enum BlockFace {
     Full(TextureKey),
+    /// A full face which doesn't hide the faces of blocks behind it
+    Transparent(TextureKey),
     Empty,
 }
 
@@ -257,14 +259,17 @@ pub fn generate_texture_map(
 
         for (i, face) in model.faces.iter().enumerate() {
             match face {
-                BlockFace::Full(texture_key) => {
-                    let uv_data = uv_data_map.entry(*texture_key)
+                BlockFace::Full(texture_key) | BlockFace::Transparent(texture_key) => {
+                    let uv_data = *uv_data_map.entry(*texture_key)
                         .or_insert_with(|| {
                             let image = image_data_map.get(texture_key).unwrap();
                             insert_texture(image)
                         });
     
-                    uv_faces[i].face_type = BlockFaceType::Full(*uv_data);
+                    uv_faces[i].face_type = match face {
+                        BlockFace::Transparent(_) => BlockFaceType::Transparent(uv_data),
+                        _ => BlockFaceType::Full(uv_data),
+                    };
                 },
                 // default uv face is already set to empty
                 BlockFace::Empty => ()
@@ -0,0 +1,700 @@
+//! Recording every input the player makes, and replaying them later to reproduce exactly what happened
+//! 
+//! For replays to be deterministic, the recording keeps which chunks finished generating on each frame,
+//! and the replay finishes loading those chunks on the same frames, waiting for them if they aren't generated yet.
+//! Each replayed frame also uses the same frame time it was recorded with.
+
+use std::fmt;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy::app::AppExit;
+use bevy::hierarchy::HierarchyPlugin;
+use bevy::input::{InputPlugin, InputSystem};
+use bevy::input::mouse::MouseMotion;
+use bevy::reflect::{DynamicEnum, Enum};
+use bevy::time::TimeUpdateStrategy;
+use bevy::transform::TransformPlugin;
+use rustc_hash::FxHasher;
+
+use crate::net::protocol::{Encode, Decode, ProtocolError};
+use crate::player::ControlledPlayer;
+use crate::types::ChunkPos;
+use crate::world::{World, ChunkLoadTiming};
+use crate::worldgen::{SharedWorldgen, WorldgenSettings};
+use crate::{MineconeCorePlugin, MineconeGameplayPlugin};
+
+/// Must be changed whenever the format of recordings changes
+pub const RECORDING_VERSION: u32 = 4;
+
+/// Key codes are stored by name, so recordings still work if bevy reorders the variants
+impl Encode for KeyCode {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        self.variant_name().to_string().encode(buf);
+    }
+}
+
+impl Decode for KeyCode {
+    fn decode(buf: &mut &[u8]) -> Result<Self, ProtocolError> {
+        let name = String::decode(buf)?;
+        KeyCode::from_reflect(&DynamicEnum::new(name, ())).ok_or(ProtocolError::InvalidString)
+    }
+}
+
+impl Encode for MouseButton {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        match self {
+            MouseButton::Left => 0u8.encode(buf),
+            MouseButton::Right => 1u8.encode(buf),
+            MouseButton::Middle => 2u8.encode(buf),
+            MouseButton::Other(button) => {
+                3u8.encode(buf);
+                button.encode(buf);
+            },
+        }
+    }
+}
+
+impl Decode for MouseButton {
+    fn decode(buf: &mut &[u8]) -> Result<Self, ProtocolError> {
+        match u8::decode(buf)? {
+            0 => Ok(MouseButton::Left),
+            1 => Ok(MouseButton::Right),
+            2 => Ok(MouseButton::Middle),
+            3 => Ok(MouseButton::Other(u16::decode(buf)?)),
+            tag => Err(ProtocolError::InvalidMessageTag(tag)),
+        }
+    }
+}
+
+impl Encode for WorldgenSettings {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        self.seed.encode(buf);
+        self.caves.encode(buf);
+        self.ores.encode(buf);
+        self.features.encode(buf);
+    }
+}
+
+impl Decode for WorldgenSettings {
+    fn decode(buf: &mut &[u8]) -> Result<Self, ProtocolError> {
+        Ok(WorldgenSettings {
+            seed: u64::decode(buf)?,
+            caves: bool::decode(buf)?,
+            ores: bool::decode(buf)?,
+            features: bool::decode(buf)?,
+        })
+    }
+}
+
+/// All the input that happened in one frame
+#[derive(Debug, Clone, Default)]
+pub struct RecordedFrame {
+    /// Time since the last frame
+    pub delta: Duration,
+    pub keys_pressed: Vec<KeyCode>,
+    pub keys_released: Vec<KeyCode>,
+    pub buttons_pressed: Vec<MouseButton>,
+    pub buttons_released: Vec<MouseButton>,
+    pub mouse_motion: Vec<Vec2>,
+    /// Chunks which finished generating this frame
+    pub loaded_chunks: Vec<ChunkPos>,
+}
+
+impl Encode for RecordedFrame {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        self.delta.encode(buf);
+        self.keys_pressed.encode(buf);
+        self.keys_released.encode(buf);
+        self.buttons_pressed.encode(buf);
+        self.buttons_released.encode(buf);
+        self.mouse_motion.encode(buf);
+        self.loaded_chunks.encode(buf);
+    }
+}
+
+impl Decode for RecordedFrame {
+    fn decode(buf: &mut &[u8]) -> Result<Self, ProtocolError> {
+        Ok(RecordedFrame {
+            delta: Duration::decode(buf)?,
+            keys_pressed: Vec::decode(buf)?,
+            keys_released: Vec::decode(buf)?,
+            buttons_pressed: Vec::decode(buf)?,
+            buttons_released: Vec::decode(buf)?,
+            mouse_motion: Vec::decode(buf)?,
+            loaded_chunks: Vec::decode(buf)?,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub enum LoadRecordingError {
+    Io(io::Error),
+    Decode(ProtocolError),
+    UnsupportedVersion(u32),
+}
+
+impl fmt::Display for LoadRecordingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "failed to read recording: {error}"),
+            Self::Decode(error) => write!(f, "invalid recording: {error}"),
+            Self::UnsupportedVersion(version) => write!(f, "recording is version {version}, but only version {RECORDING_VERSION} is supported"),
+        }
+    }
+}
+
+impl std::error::Error for LoadRecordingError {}
+
+#[derive(Debug, Clone, Default)]
+pub struct InputRecording {
+    /// Settings of the worldgen the recording was made with, the world must be generated the same way to replay it
+    pub worldgen: WorldgenSettings,
+    pub frames: Vec<RecordedFrame>,
+    /// Hash of the world after the last frame, used to check that a replay did the same thing
+    pub final_world_hash: u64,
+    /// Hash of where the camera was and where it was looking every frame
+    pub camera_path_hash: u64,
+}
+
+impl InputRecording {
+    pub fn load(path: &Path) -> Result<Self, LoadRecordingError> {
+        let data = fs::read(path).map_err(LoadRecordingError::Io)?;
+        let mut buf = data.as_slice();
+
+        let version = u32::decode(&mut buf).map_err(LoadRecordingError::Decode)?;
+        if version != RECORDING_VERSION {
+            return Err(LoadRecordingError::UnsupportedVersion(version));
+        }
+
+        let recording = InputRecording {
+            worldgen: WorldgenSettings::decode(&mut buf).map_err(LoadRecordingError::Decode)?,
+            frames: Vec::decode(&mut buf).map_err(LoadRecordingError::Decode)?,
+            final_world_hash: u64::decode(&mut buf).map_err(LoadRecordingError::Decode)?,
+            camera_path_hash: u64::decode(&mut buf).map_err(LoadRecordingError::Decode)?,
+        };
+
+        if buf.is_empty() {
+            Ok(recording)
+        } else {
+            Err(LoadRecordingError::Decode(ProtocolError::TrailingBytes(buf.len())))
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut buf = Vec::new();
+        RECORDING_VERSION.encode(&mut buf);
+        self.worldgen.encode(&mut buf);
+        self.frames.encode(&mut buf);
+        self.final_world_hash.encode(&mut buf);
+        self.camera_path_hash.encode(&mut buf);
+
+        fs::write(path, buf)
+    }
+}
+
+/// Hashes the camera's position and rotation every frame, so recordings and replays can be checked to move the camera the same way
+#[derive(Resource, Default)]
+struct CameraPathHasher(FxHasher);
+
+impl CameraPathHasher {
+    fn hash(&self) -> u64 {
+        self.0.finish()
+    }
+}
+
+fn hash_camera_path(
+    mut hasher: ResMut<CameraPathHasher>,
+    world: Res<World>,
+    cameras: Query<&Transform, With<ControlledPlayer>>,
+) {
+    for transform in cameras.iter() {
+        // the world position is used since the render position changes whenever the floating origin moves
+        world.world_pos(transform.translation).0.to_array().map(f64::to_bits).hash(&mut hasher.0);
+        transform.rotation.to_array().map(f32::to_bits).hash(&mut hasher.0);
+    }
+}
+
+#[derive(Resource)]
+struct InputRecorder {
+    path: PathBuf,
+    recording: InputRecording,
+}
+
+fn record_worldgen_settings(mut recorder: ResMut<InputRecorder>, worldgen: Res<SharedWorldgen>) {
+    recorder.recording.worldgen = worldgen.settings().clone();
+}
+
+fn record_inputs(
+    mut recorder: ResMut<InputRecorder>,
+    time: Res<Time>,
+    keys: Res<Input<KeyCode>>,
+    buttons: Res<Input<MouseButton>>,
+    mut mouse_motion: EventReader<MouseMotion>,
+) {
+    recorder.recording.frames.push(RecordedFrame {
+        delta: time.delta(),
+        keys_pressed: keys.get_just_pressed().copied().collect(),
+        keys_released: keys.get_just_released().copied().collect(),
+        buttons_pressed: buttons.get_just_pressed().copied().collect(),
+        buttons_released: buttons.get_just_released().copied().collect(),
+        mouse_motion: mouse_motion.iter().map(|motion| motion.delta).collect(),
+        loaded_chunks: Vec::new(),
+    });
+}
+
+/// Moves the chunks which finished loading this frame into the recorded frame
+fn record_loaded_chunks(mut recorder: ResMut<InputRecorder>, mut timing: ResMut<ChunkLoadTiming>) {
+    let ChunkLoadTiming::Recorded(loaded_chunks) = &mut *timing else {
+        return;
+    };
+
+    if let Some(frame) = recorder.recording.frames.last_mut() {
+        frame.loaded_chunks.append(loaded_chunks);
+    }
+}
+
+/// Saves the recording when the game exits
+fn save_recording(
+    mut recorder: ResMut<InputRecorder>,
+    world: Res<World>,
+    camera_path: Res<CameraPathHasher>,
+    mut exit_events: EventReader<AppExit>,
+) {
+    if exit_events.iter().last().is_none() {
+        return;
+    }
+
+    recorder.recording.final_world_hash = world.block_hash();
+    recorder.recording.camera_path_hash = camera_path.hash();
+
+    match recorder.recording.save(&recorder.path) {
+        Ok(()) => info!(
+            "saved recording of {} frames to {}",
+            recorder.recording.frames.len(),
+            recorder.path.display(),
+        ),
+        Err(error) => error!("failed to save recording to {}: {error}", recorder.path.display()),
+    }
+}
+
+/// Records all input until the game exits, and then saves it to `path`
+pub struct InputRecorderPlugin {
+    pub path: PathBuf,
+}
+
+impl Plugin for InputRecorderPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ChunkLoadTiming::Recorded(Vec::new()))
+            .insert_resource(InputRecorder {
+                path: self.path.clone(),
+                recording: InputRecording::default(),
+            })
+            .init_resource::<CameraPathHasher>()
+            .add_systems(Startup, record_worldgen_settings)
+            .add_systems(PreUpdate, record_inputs.after(InputSystem))
+            .add_systems(Last, (record_loaded_chunks, hash_camera_path, save_recording).chain());
+    }
+}
+
+/// The result of a finished replay
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReplayOutcome {
+    /// World hash at the end of the recording
+    pub expected_hash: u64,
+    /// World hash at the end of the replay
+    pub actual_hash: u64,
+    /// Hash of the camera path in the recording
+    pub expected_camera_path_hash: u64,
+    /// Hash of the camera path in the replay
+    pub actual_camera_path_hash: u64,
+}
+
+impl ReplayOutcome {
+    /// Returns true if the replay ended up with the same world as the recording, and moved the camera the same way
+    pub fn is_match(&self) -> bool {
+        self.expected_hash == self.actual_hash
+            && self.expected_camera_path_hash == self.actual_camera_path_hash
+    }
+}
+
+#[derive(Resource)]
+pub struct InputReplay {
+    recording: InputRecording,
+    next_frame: usize,
+    /// Replayed key state, which overwrites the real input every frame
+    keys: Input<KeyCode>,
+    buttons: Input<MouseButton>,
+    outcome: Option<ReplayOutcome>,
+}
+
+impl InputReplay {
+    /// Returns the outcome once every frame has been replayed
+    pub fn outcome(&self) -> Option<ReplayOutcome> {
+        self.outcome
+    }
+
+    fn is_finished(&self) -> bool {
+        self.next_frame >= self.recording.frames.len()
+    }
+}
+
+fn check_replay_worldgen_settings(replay: Res<InputReplay>, worldgen: Res<SharedWorldgen>) {
+    if replay.recording.worldgen != *worldgen.settings() {
+        warn!(
+            "recording was made with worldgen settings {:?}, but the world uses {:?}, so the replay will not match",
+            replay.recording.worldgen,
+            worldgen.settings(),
+        );
+    }
+}
+
+fn replay_inputs(
+    mut replay: ResMut<InputReplay>,
+    mut keys: ResMut<Input<KeyCode>>,
+    mut buttons: ResMut<Input<MouseButton>>,
+    mut mouse_motion: ResMut<Events<MouseMotion>>,
+    mut time_update_strategy: ResMut<TimeUpdateStrategy>,
+    mut timing: ResMut<ChunkLoadTiming>,
+) {
+    if replay.is_finished() {
+        return;
+    }
+
+    let replay = &mut *replay;
+    let frame = &replay.recording.frames[replay.next_frame];
+
+    replay.keys.clear();
+    for key in frame.keys_released.iter() {
+        replay.keys.release(*key);
+    }
+    for key in frame.keys_pressed.iter() {
+        replay.keys.press(*key);
+    }
+
+    replay.buttons.clear();
+    for button in frame.buttons_released.iter() {
+        replay.buttons.release(*button);
+    }
+    for button in frame.buttons_pressed.iter() {
+        replay.buttons.press(*button);
+    }
+
+    *keys = replay.keys.clone();
+    *buttons = replay.buttons.clone();
+
+    // get rid of any real mouse movement
+    mouse_motion.clear();
+    for delta in frame.mouse_motion.iter() {
+        mouse_motion.send(MouseMotion {
+            delta: *delta,
+        });
+    }
+
+    *timing = ChunkLoadTiming::Replayed(frame.loaded_chunks.clone());
+
+    replay.next_frame += 1;
+
+    // time is updated before this runs, so the next frame's delta has to be set now
+    *time_update_strategy = match replay.recording.frames.get(replay.next_frame) {
+        Some(next_frame) => TimeUpdateStrategy::ManualDuration(next_frame.delta),
+        None => TimeUpdateStrategy::Automatic,
+    };
+}
+
+fn replay_in_progress(replay: Res<InputReplay>) -> bool {
+    replay.outcome.is_none()
+}
+
+fn finish_replay(
+    mut replay: ResMut<InputReplay>,
+    world: Res<World>,
+    camera_path: Res<CameraPathHasher>,
+    mut keys: ResMut<Input<KeyCode>>,
+    mut buttons: ResMut<Input<MouseButton>>,
+    mut timing: ResMut<ChunkLoadTiming>,
+) {
+    if !replay.is_finished() || replay.outcome.is_some() {
+        return;
+    }
+
+    let outcome = ReplayOutcome {
+        expected_hash: replay.recording.final_world_hash,
+        actual_hash: world.block_hash(),
+        expected_camera_path_hash: replay.recording.camera_path_hash,
+        actual_camera_path_hash: camera_path.hash(),
+    };
+
+    if outcome.is_match() {
+        info!("replay finished, world and camera path match the recording");
+    } else if outcome.expected_hash != outcome.actual_hash {
+        error!(
+            "replay finished, but world hash {:016x} does not match recorded hash {:016x}",
+            outcome.actual_hash,
+            outcome.expected_hash,
+        );
+    } else {
+        error!(
+            "replay finished, but camera path hash {:016x} does not match recorded hash {:016x}",
+            outcome.actual_camera_path_hash,
+            outcome.expected_camera_path_hash,
+        );
+    }
+
+    replay.outcome = Some(outcome);
+
+    // give control back to the real input, and let chunks load normally again
+    keys.release_all();
+    buttons.release_all();
+    *timing = ChunkLoadTiming::Immediate;
+}
+
+/// Feeds the recorded input to the game instead of the real input
+pub struct InputReplayPlugin {
+    pub recording: InputRecording,
+}
+
+impl Plugin for InputReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ChunkLoadTiming::Replayed(Vec::new()))
+            .insert_resource(InputReplay {
+                recording: self.recording.clone(),
+                next_frame: 0,
+                keys: Input::default(),
+                buttons: Input::default(),
+                outcome: None,
+            })
+            .init_resource::<CameraPathHasher>()
+            .add_systems(Startup, check_replay_worldgen_settings)
+            .add_systems(PreUpdate, replay_inputs.after(InputSystem))
+            .add_systems(Last, (hash_camera_path.run_if(replay_in_progress), finish_replay).chain());
+    }
+}
+
+/// Replays the recording without a window, and returns whether the world ended up the same
+/// 
+/// The world is generated with the worldgen settings from the recording
+pub fn run_headless_replay(recording: InputRecording) -> ReplayOutcome {
+    let mut app = headless_app(recording.worldgen.clone());
+    app.add_plugins(InputReplayPlugin { recording });
+
+    run_headless_replay_app(app)
+}
+
+/// The game without a window, for recording or replaying input
+fn headless_app(worldgen: WorldgenSettings) -> App {
+    let mut app = App::new();
+
+    app.add_plugins((
+            MinimalPlugins,
+            TransformPlugin,
+            HierarchyPlugin,
+            InputPlugin,
+        ))
+        .add_plugins((
+            MineconeCorePlugin { worldgen },
+            MineconeGameplayPlugin,
+        ));
+
+    app
+}
+
+fn run_headless_replay_app(mut app: App) -> ReplayOutcome {
+    // the app is updated manually instead of with a runner, so it can be stopped as soon as the replay is done
+    app.finish();
+    app.cleanup();
+
+    loop {
+        app.update();
+
+        if let Some(outcome) = app.world.resource::<InputReplay>().outcome() {
+            return outcome;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use bevy::input::ButtonState;
+    use bevy::input::keyboard::KeyboardInput;
+    use bevy::input::mouse::MouseButtonInput;
+
+    use crate::world::ChunkLoader;
+    use super::*;
+
+    const FRAME_TIME: Duration = Duration::from_millis(16);
+    const FRAME_COUNT: usize = 120;
+    /// How long to wait for the chunks around the player to be generated before recording
+    const LOAD_TIMEOUT: Duration = Duration::from_secs(60);
+
+    /// Only loads the chunks right around the player, so the test doesn't have to wait for the full render distance to generate
+    fn shrink_chunk_loaders(mut loaders: Query<&mut ChunkLoader>) {
+        for mut loader in loaders.iter_mut() {
+            loader.load_distance = UVec3::ONE;
+        }
+    }
+
+    fn test_app(worldgen: WorldgenSettings) -> App {
+        let mut app = headless_app(worldgen);
+        app.add_systems(PostStartup, shrink_chunk_loaders);
+
+        app
+    }
+
+    fn chunks_loaded(app: &App) -> bool {
+        let chunks = &app.world.resource::<World>().chunks;
+        !chunks.is_empty() && chunks.values().all(|chunk| chunk.is_loaded())
+    }
+
+    fn press_key(app: &mut App, key_code: KeyCode, state: ButtonState) {
+        app.world.send_event(KeyboardInput {
+            scan_code: 0,
+            key_code: Some(key_code),
+            state,
+            window: Entity::PLACEHOLDER,
+        });
+    }
+
+    fn press_button(app: &mut App, button: MouseButton, state: ButtonState) {
+        app.world.send_event(MouseButtonInput {
+            button,
+            state,
+            window: Entity::PLACEHOLDER,
+        });
+    }
+
+    /// Walks forward while looking around, then mines a block and places one, the same way a player would
+    fn record(path: &Path) -> InputRecording {
+        let mut app = test_app(WorldgenSettings::default());
+        app.add_plugins(InputRecorderPlugin { path: path.to_path_buf() })
+            .insert_resource(TimeUpdateStrategy::ManualDuration(FRAME_TIME));
+
+        app.finish();
+        app.cleanup();
+
+        // chunks are generated in the background, so wait for the ones around the player before doing anything
+        let start = Instant::now();
+        while !chunks_loaded(&app) {
+            app.update();
+
+            assert!(start.elapsed() < LOAD_TIMEOUT, "chunks around the player did not load");
+            std::thread::sleep(Duration::from_millis(1));
+        }
+
+        for frame in 0..FRAME_COUNT {
+            match frame {
+                10 => press_key(&mut app, KeyCode::W, ButtonState::Pressed),
+                50 => press_key(&mut app, KeyCode::W, ButtonState::Released),
+                60 => press_button(&mut app, MouseButton::Left, ButtonState::Pressed),
+                61 => press_button(&mut app, MouseButton::Left, ButtonState::Released),
+                70 => press_key(&mut app, KeyCode::Key3, ButtonState::Pressed),
+                71 => press_key(&mut app, KeyCode::Key3, ButtonState::Released),
+                80 => press_button(&mut app, MouseButton::Right, ButtonState::Pressed),
+                81 => press_button(&mut app, MouseButton::Right, ButtonState::Released),
+                _ => (),
+            }
+
+            // look around while walking, and then down at the ground
+            if (20..45).contains(&frame) {
+                app.world.send_event(MouseMotion { delta: Vec2::new(8.0, 0.0) });
+            } else if (45..58).contains(&frame) {
+                app.world.send_event(MouseMotion { delta: Vec2::new(0.0, 15.0) });
+            }
+
+            app.update();
+        }
+
+        // the recording is saved when the game exits
+        app.world.send_event(AppExit);
+        app.update();
+
+        InputRecording::load(path).unwrap()
+    }
+
+    fn replay(recording: InputRecording) -> ReplayOutcome {
+        let mut app = test_app(recording.worldgen.clone());
+        app.add_plugins(InputReplayPlugin { recording });
+
+        run_headless_replay_app(app)
+    }
+
+    #[test]
+    fn replay_reproduces_recorded_run() {
+        let path = std::env::temp_dir().join(format!("minecone-replay-test-{}", std::process::id()));
+        let recording = record(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert!(recording.frames.len() > FRAME_COUNT);
+        assert!(recording.frames.iter().any(|frame| !frame.mouse_motion.is_empty()));
+        assert!(recording.frames.iter().any(|frame| !frame.loaded_chunks.is_empty()));
+
+        let outcome = replay(recording.clone());
+        assert_eq!(outcome.actual_hash, outcome.expected_hash, "replay ended with a different world");
+        assert_eq!(outcome.actual_camera_path_hash, outcome.expected_camera_path_hash, "replay moved the camera differently");
+        assert!(outcome.is_match());
+
+        // turning the camera differently changes the camera path, even if the world ends up the same
+        let mut changed_recording = recording;
+        for frame in changed_recording.frames.iter_mut() {
+            for delta in frame.mouse_motion.iter_mut() {
+                delta.x = -delta.x;
+            }
+        }
+
+        let outcome = replay(changed_recording);
+        assert_ne!(outcome.actual_camera_path_hash, outcome.expected_camera_path_hash);
+        assert!(!outcome.is_match());
+    }
+
+    #[test]
+    fn recording_round_trips_through_file() {
+        let recording = InputRecording {
+            worldgen: WorldgenSettings {
+                seed: 1234,
+                caves: false,
+                ores: true,
+                features: false,
+            },
+            frames: vec![
+                RecordedFrame {
+                    delta: Duration::from_micros(16_667),
+                    keys_pressed: vec![KeyCode::W, KeyCode::ShiftLeft],
+                    buttons_pressed: vec![MouseButton::Left, MouseButton::Other(7)],
+                    mouse_motion: vec![Vec2::new(1.5, -2.0)],
+                    loaded_chunks: vec![ChunkPos::new(0, -1, 0), ChunkPos::new(-3, 2, 1)],
+                    ..Default::default()
+                },
+                RecordedFrame {
+                    keys_released: vec![KeyCode::W],
+                    buttons_released: vec![MouseButton::Left],
+                    ..Default::default()
+                },
+            ],
+            final_world_hash: 0x0123_4567_89ab_cdef,
+            camera_path_hash: 42,
+        };
+
+        let path = std::env::temp_dir().join(format!("minecone-recording-test-{}", std::process::id()));
+        recording.save(&path).unwrap();
+        let loaded = InputRecording::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.worldgen, recording.worldgen);
+        assert_eq!(loaded.final_world_hash, recording.final_world_hash);
+        assert_eq!(loaded.camera_path_hash, recording.camera_path_hash);
+        assert_eq!(loaded.frames.len(), 2);
+        assert_eq!(loaded.frames[0].delta, recording.frames[0].delta);
+        assert_eq!(loaded.frames[0].keys_pressed, recording.frames[0].keys_pressed);
+        assert_eq!(loaded.frames[0].buttons_pressed, recording.frames[0].buttons_pressed);
+        assert_eq!(loaded.frames[0].mouse_motion, recording.frames[0].mouse_motion);
+        assert_eq!(loaded.frames[0].loaded_chunks, recording.frames[0].loaded_chunks);
+        assert_eq!(loaded.frames[1].keys_released, recording.frames[1].keys_released);
+        assert_eq!(loaded.frames[1].buttons_released, recording.frames[1].buttons_released);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,122 @@
+//! Headless dedicated server, which simulates the world without a window or gpu
+
+use std::io::BufRead;
+use std::thread;
+
+use bevy::prelude::*;
+use bevy::app::AppExit;
+use bevy::math::DVec3;
+use crossbeam::channel::{self, Receiver};
+
+use crate::types::{WorldPos, ChunkPos};
+use crate::world::{ChunkLoader, World};
+use crate::worldgen::WorldgenSettings;
+use crate::MineconeCorePlugin;
+
+/// How many chunks are loaded around each load point by default
+pub const DEFAULT_LOAD_DISTANCE: UVec3 = UVec3::new(4, 4, 4);
+
+#[derive(Debug, Clone, Resource)]
+pub struct ServerConfig {
+    /// Positions in meters which chunks will always be loaded around
+    pub load_points: Vec<DVec3>,
+    /// Distance in chunks that is loaded around each load point
+    pub load_distance: UVec3,
+    /// If set, the server stops after running this many ticks
+    pub max_ticks: Option<u64>,
+    pub worldgen: WorldgenSettings,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            load_points: vec![DVec3::ZERO],
+            load_distance: DEFAULT_LOAD_DISTANCE,
+            max_ticks: None,
+            worldgen: WorldgenSettings::default(),
+        }
+    }
+}
+
+/// Lines typed into the server console
+#[derive(Resource)]
+struct ConsoleInput(Receiver<String>);
+
+/// Spawns a chunk loader at every load point
+fn spawn_load_points(
+    config: Res<ServerConfig>,
+    world: Res<World>,
+    mut commands: Commands,
+) {
+    for point in config.load_points.iter() {
+        let position = WorldPos(*point);
+
+        commands.spawn((
+            ChunkLoader::new(ChunkPos::from(position), config.load_distance),
+            TransformBundle::from_transform(Transform::from_translation(world.render_pos(position))),
+        ));
+
+        info!("loading chunks around {point}");
+    }
+}
+
+/// Reads lines from stdin on a separate thread so the console never blocks the tick loop
+fn spawn_console_reader(mut commands: Commands) {
+    let (sender, receiver) = channel::unbounded();
+
+    thread::spawn(move || {
+        for line in std::io::stdin().lock().lines() {
+            let Ok(line) = line else {
+                break;
+            };
+
+            if sender.send(line).is_err() {
+                break;
+            }
+        }
+    });
+
+    commands.insert_resource(ConsoleInput(receiver));
+}
+
+fn handle_console_input(console: Res<ConsoleInput>, mut exit: EventWriter<AppExit>) {
+    for line in console.0.try_iter() {
+        match line.trim() {
+            "stop" => {
+                info!("stopping server");
+                exit.send(AppExit);
+            },
+            "" => (),
+            command => warn!("unknown command: {command}"),
+        }
+    }
+}
+
+fn stop_after_max_ticks(
+    config: Res<ServerConfig>,
+    mut tick: Local<u64>,
+    mut exit: EventWriter<AppExit>,
+) {
+    *tick += 1;
+
+    if config.max_ticks.is_some_and(|max_ticks| *tick == max_ticks) {
+        info!("stopping server after {} ticks", *tick);
+        exit.send(AppExit);
+    }
+}
+
+/// Runs the world simulation headless, this should be used with `MinimalPlugins`
+pub struct MineconeServerPlugin {
+    pub config: ServerConfig,
+}
+
+impl Plugin for MineconeServerPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(self.config.clone())
+            .add_plugins(MineconeCorePlugin {
+                worldgen: self.config.worldgen.clone(),
+            })
+            .add_systems(Startup, (spawn_load_points, spawn_console_reader))
+            .add_systems(Update, (handle_console_input, stop_after_max_ticks));
+    }
+}
\ No newline at end of file
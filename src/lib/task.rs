@@ -10,7 +10,7 @@ use std::ptr;
 use crossbeam::deque::{Injector, Steal};
 
 static TASK_POOL: LazyLock<TaskPool> = LazyLock::new(|| {
-    let cpu_count = std::cmp::max(1, num_cpus::get() - 2);
+    let cpu_count = std::cmp::max(1, num_cpus::get().saturating_sub(2));
     TaskPool::new(cpu_count)
 });
 
@@ -127,4 +127,17 @@ impl<T: Send + 'static> Task<T> {
             Err(_) => None,
         }
     }
+
+    /// Blocks until the task is finished and returns its result
+    /// 
+    /// This never returns if the result has already been taken with `poll`
+    pub fn wait(&self) -> T {
+        loop {
+            if let Some(result) = self.poll() {
+                return result;
+            }
+
+            thread::sleep(SLEEP_DURATION);
+        }
+    }
 }
\ No newline at end of file
@@ -1,6 +1,7 @@
 use std::ops::{Index, IndexMut};
 
 use bevy::prelude::*;
+use bevy::math::DVec3;
 use derive_more::{Add, AddAssign, Sub, SubAssign, Mul, MulAssign, Div, DivAssign};
 
 use crate::world::{CHUNK_SIZE, ChunkRegion};
@@ -9,13 +10,17 @@ use crate::world::{CHUNK_SIZE, ChunkRegion};
 pub const BLOCK_SIZE: f32 = 0.5;
 
 #[repr(u8)]
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum VecAxis {
     X = 0,
     Y = 1,
     Z = 2,
 }
 
+impl VecAxis {
+    pub const ALL: [VecAxis; 3] = [VecAxis::X, VecAxis::Y, VecAxis::Z];
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Deref, DerefMut, Add, AddAssign, Sub, SubAssign, Mul, MulAssign, Div, DivAssign)]
 pub struct ChunkPos(pub IVec3);
 
@@ -189,6 +194,31 @@ impl From<BlockPos> for Vec3 {
     }
 }
 
+impl From<WorldPos> for BlockPos {
+    fn from(position: WorldPos) -> Self {
+        BlockPos((position.0 / BLOCK_SIZE as f64).floor().as_ivec3())
+    }
+}
+
+/// A position in the world in meters
+/// 
+/// This is stored as f64 so it stays precise far away from the world origin,
+/// unlike positions in render space which are f32 and relative to the floating origin
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deref, DerefMut, Add, AddAssign, Sub, SubAssign)]
+pub struct WorldPos(pub DVec3);
+
+impl From<BlockPos> for WorldPos {
+    fn from(block_pos: BlockPos) -> Self {
+        WorldPos(block_pos.0.as_dvec3() * BLOCK_SIZE as f64)
+    }
+}
+
+impl From<WorldPos> for ChunkPos {
+    fn from(position: WorldPos) -> Self {
+        BlockPos::from(position).into()
+    }
+}
+
 pub trait VecExt {
     type NumType;
 
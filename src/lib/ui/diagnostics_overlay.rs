@@ -0,0 +1,79 @@
+use std::fmt::Write;
+
+use bevy::prelude::*;
+use bevy::diagnostic::DiagnosticsStore;
+
+const OVERLAY_FONT_SIZE: f32 = 16.0;
+const OVERLAY_MARGIN: Val = Val::Px(5.0);
+
+/// Text which shows the value of every registered diagnostic
+#[derive(Debug, Component)]
+pub struct DiagnosticsOverlay;
+
+fn init_diagnostics_overlay(mut commands: Commands) {
+    commands.spawn((
+        TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: OVERLAY_MARGIN,
+                left: OVERLAY_MARGIN,
+                ..Default::default()
+            },
+            text: Text::from_section("", TextStyle {
+                font_size: OVERLAY_FONT_SIZE,
+                color: Color::WHITE,
+                ..Default::default()
+            }),
+            visibility: Visibility::Hidden,
+            ..Default::default()
+        },
+        DiagnosticsOverlay,
+    ));
+}
+
+fn toggle_diagnostics_overlay(
+    mut overlays: Query<&mut Visibility, With<DiagnosticsOverlay>>,
+    input: Res<Input<KeyCode>>,
+) {
+    if !input.just_pressed(KeyCode::F3) {
+        return;
+    }
+
+    for mut visibility in overlays.iter_mut() {
+        *visibility = match *visibility {
+            Visibility::Hidden => Visibility::Inherited,
+            _ => Visibility::Hidden,
+        };
+    }
+}
+
+fn update_diagnostics_overlay(
+    mut overlays: Query<(&mut Text, &Visibility), With<DiagnosticsOverlay>>,
+    diagnostics: Res<DiagnosticsStore>,
+) {
+    for (mut text, visibility) in overlays.iter_mut() {
+        if *visibility == Visibility::Hidden {
+            continue;
+        }
+
+        // sort by name so lines don't jump around between frames
+        let mut diagnostics = diagnostics.iter()
+            .filter(|diagnostic| diagnostic.is_enabled)
+            .collect::<Vec<_>>();
+        diagnostics.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let mut overlay_text = String::new();
+        for diagnostic in diagnostics {
+            if let Some(value) = diagnostic.smoothed() {
+                let _ = writeln!(overlay_text, "{}: {:.2}{}", diagnostic.name, value, diagnostic.suffix);
+            }
+        }
+
+        text.sections[0].value = overlay_text;
+    }
+}
+
+pub(super) fn add_systems(app: &mut App) {
+    app.add_systems(Startup, init_diagnostics_overlay)
+        .add_systems(Update, (toggle_diagnostics_overlay, update_diagnostics_overlay).chain());
+}
\ No newline at end of file
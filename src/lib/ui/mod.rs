@@ -1,5 +1,7 @@
 use bevy::prelude::*;
 
+mod diagnostics_overlay;
+
 const CROSSHAIR_SIZE: Val = Val::Px(30.0);
 
 fn init_crosshair(
@@ -39,5 +41,7 @@ pub struct UiPlugin;
 impl Plugin for UiPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(Startup, init_crosshair);
+
+        diagnostics_overlay::add_systems(app);
     }
 }
\ No newline at end of file
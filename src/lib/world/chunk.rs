@@ -1,14 +1,18 @@
+use std::ops::RangeInclusive;
 use std::sync::atomic::{AtomicU32, AtomicBool, Ordering};
 
 use bevy::prelude::*;
-use parking_lot::RwLock;
+use bevy::math::Vec3A;
+use bevy::render::primitives::Aabb;
+use parking_lot::{RwLock, Mutex};
 
 use crate::blocks::BlockStorage;
-use crate::meshing::{generate_mesh, ChunkMeshData};
-use crate::render::block_models;
+use crate::diagnostics::GENERATE_MESH_STATS;
+use crate::meshing::{generate_mesh, ChunkMeshData, ChunkMeshCache, FaceDirection};
+use crate::render::{block_models, BlockMaterial, GlobalBlockMaterial};
 use crate::task::{TaskPool, Task};
-use crate::types::ChunkPos;
-use super::{World, ChunkRegion, OwnedChunkArea};
+use crate::types::{ChunkPos, BlockPos, VecAxis, BLOCK_SIZE};
+use super::{World, ChunkRegion, OwnedChunkArea, EcsChunk};
 
 pub const CHUNK_SIZE: usize = 32;
 pub const CHUNK_BLOCK_COUNT: usize = CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE;
@@ -20,13 +24,53 @@ pub struct Chunk {
     pub chunk_pos: ChunkPos,
     pub entity: Entity,
     pub load_count: AtomicU32,
+    /// Set once the chunk's blocks have been generated or received, before this the chunk is empty
+    pub(super) loaded: AtomicBool,
     /// Used to indicate if blocks have been changed but chunk has not yet been remeshed
     pub dirty: AtomicBool,
+    /// The part of the chunk whose mesh might have changed since the last remesh
+    /// 
+    /// This is `None` if nothing has changed
+    dirty_region: Mutex<Option<DirtyRegion>>,
+    /// Meshes of each layer from the last time the chunk was meshed, so unchanged layers don't need to be remeshed
+    mesh_cache: Mutex<ChunkMeshCache>,
 }
 
 impl Chunk {
-    /// Marks the chunk as dirty and queues a remesh job for the chunk
+    /// Creates a chunk which starts with the given data and is loaded by 1 chunk loader
+    pub fn new(chunk_pos: ChunkPos, entity: Entity, data: ChunkData) -> Self {
+        Chunk {
+            data: RwLock::new(data),
+            chunk_pos,
+            entity,
+            load_count: AtomicU32::new(1),
+            loaded: AtomicBool::new(false),
+            dirty: AtomicBool::new(false),
+            dirty_region: Mutex::new(None),
+            mesh_cache: Mutex::new(ChunkMeshCache::default()),
+        }
+    }
+
+    /// Returns true once the chunk's blocks have been generated or received
+    pub fn is_loaded(&self) -> bool {
+        self.loaded.load(Ordering::Acquire)
+    }
+
+    /// Marks the whole chunk as dirty and queues a remesh job for the chunk
     pub fn mark_dirty(&self, world: &World) {
+        self.mark_region_dirty(world, DirtyRegion::FULL);
+    }
+
+    /// Marks part of the chunk as dirty and queues a remesh job for the chunk
+    pub fn mark_region_dirty(&self, world: &World, region: DirtyRegion) {
+        {
+            let mut dirty_region = self.dirty_region.lock();
+            *dirty_region = Some(match *dirty_region {
+                Some(old_region) => old_region.union(region),
+                None => region,
+            });
+        }
+
         // TODO: make sure ordering is correct
         if !self.dirty.swap(true, Ordering::AcqRel) {
             // old dirty bit was false, so push to remesh list
@@ -36,6 +80,64 @@ impl Chunk {
     }
 }
 
+/// A box of blocks in a chunk whose faces might have changed, in chunk local coordinates
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DirtyRegion {
+    /// inclusive
+    pub min: BlockPos,
+    /// inclusive
+    pub max: BlockPos,
+}
+
+impl DirtyRegion {
+    pub const FULL: DirtyRegion = DirtyRegion {
+        min: BlockPos(IVec3::ZERO),
+        max: BlockPos(IVec3::splat(CHUNK_SIZE as i32 - 1)),
+    };
+
+    /// Gets the region of `chunk_pos` whose faces could change when the block at `block_pos` changes
+    /// 
+    /// This is every block touching the changed block, since their faces can be culled or ambient occluded by it
+    pub fn around_block(chunk_pos: ChunkPos, block_pos: BlockPos) -> DirtyRegion {
+        let local_pos = block_pos.0 - BlockPos::from(chunk_pos).0;
+
+        DirtyRegion {
+            min: BlockPos((local_pos - IVec3::ONE).max(Self::FULL.min.0)),
+            max: BlockPos((local_pos + IVec3::ONE).min(Self::FULL.max.0)),
+        }
+    }
+
+    /// Gets the region of a chunk whose faces could change when the adjacent chunk at `offset` from it is loaded
+    pub fn facing_neighbor(offset: ChunkPos) -> DirtyRegion {
+        let max_index = CHUNK_SIZE as i32 - 1;
+
+        let mut region = DirtyRegion::FULL;
+
+        for axis in VecAxis::ALL {
+            if offset[axis] > 0 {
+                region.min[axis] = max_index;
+            } else if offset[axis] < 0 {
+                region.max[axis] = 0;
+            }
+        }
+
+        region
+    }
+
+    pub fn union(&self, other: DirtyRegion) -> DirtyRegion {
+        DirtyRegion {
+            min: BlockPos(self.min.0.min(other.min.0)),
+            max: BlockPos(self.max.0.max(other.max.0)),
+        }
+    }
+
+    /// Returns the range of layers which need to be remeshed for the given face direction
+    pub fn layers(&self, face: FaceDirection) -> RangeInclusive<i32> {
+        let axis = face.axis();
+        self.min[axis]..=self.max[axis]
+    }
+}
+
 #[derive(Debug, Default)]
 pub struct ChunkData {
     pub blocks: BlockStorage,
@@ -52,6 +154,38 @@ impl From<BlockStorage> for ChunkData {
 #[derive(Component)]
 pub struct ChunkMeshTask(Task<Option<Mesh>>);
 
+/// Adds everything in a material mesh bundle except the mesh to chunks, the mesh is added once it is generated
+pub(super) fn add_chunk_render_components(
+    block_material: Res<GlobalBlockMaterial>,
+    chunks: Query<Entity, (With<EcsChunk>, Without<Handle<BlockMaterial>>)>,
+    mut commands: Commands,
+) {
+    let half_chunk_size = CHUNK_SIZE as f32 * BLOCK_SIZE * 0.5;
+    let half_chunk_size = Vec3A::new(half_chunk_size, half_chunk_size, half_chunk_size);
+
+    for chunk_entity in chunks.iter() {
+        commands.entity(chunk_entity).insert((
+            Aabb {
+                center: half_chunk_size,
+                half_extents: half_chunk_size,
+            },
+            Visibility::default(),
+            ComputedVisibility::default(),
+            block_material.0.clone(),
+        ));
+    }
+}
+
+/// Clears the dirty list without meshing anything, used when chunks are not being rendered
+pub(super) fn discard_dirty_chunks(world: Res<World>) {
+    while let Some(dirty_chunk_pos) = world.dirty_chunks.pop() {
+        if let Some(chunk) = world.chunks.get(&dirty_chunk_pos) {
+            chunk.dirty.store(false, Ordering::Release);
+            chunk.dirty_region.lock().take();
+        }
+    }
+}
+
 pub(super) fn remesh_dirty_chunks(world: Res<World>, mut commands: Commands) {
     let task_pool = TaskPool::get();
 
@@ -64,6 +198,7 @@ pub(super) fn remesh_dirty_chunks(world: Res<World>, mut commands: Commands) {
         let Some(owned_chunk_area) = OwnedChunkArea::new(&world, remesh_region) else {
             // adjacent chunks did not exist, so the chunk was skipped, but since it was removed from the dirty list,
             // it needs to be marked dirty so it can be remeshed when its neigbors are loaded in the future
+            // the dirty region is left alone so the changes are still remeshed then
             if let Some(chunk) = world.chunks.get(&dirty_chunk_pos) {
                 chunk.dirty.store(false, Ordering::Release);
             }
@@ -76,17 +211,26 @@ pub(super) fn remesh_dirty_chunks(world: Res<World>, mut commands: Commands) {
             .unwrap()
             .entity;
 
-        let task = task_pool.spawn(move || {
+        GENERATE_MESH_STATS.queue();
+        let task = task_pool.spawn(move || GENERATE_MESH_STATS.run(|| {
             let mesh_data = ChunkMeshData::new(owned_chunk_area.read());
 
-            owned_chunk_area
+            let chunk = owned_chunk_area
                 .get_chunk_relative(ChunkPos::new(1, 1, 1))
-                .unwrap()
-                .dirty
-                .store(false, Ordering::Release);
+                .unwrap();
+
+            chunk.dirty.store(false, Ordering::Release);
+
+            // if part of the chunk is marked dirty after the region is taken, the chunk is queued to be remeshed again,
+            // so nothing is missed
+            let dirty_region = chunk.dirty_region.lock()
+                .take()
+                .unwrap_or(DirtyRegion::FULL);
 
-            generate_mesh(&mesh_data, block_models())
-        });
+            let mut mesh_cache = chunk.mesh_cache.lock();
+
+            generate_mesh(&mesh_data, block_models(), &mut mesh_cache, dirty_region)
+        }));
 
         commands.entity(chunk_entity)
             .insert(ChunkMeshTask(task));
@@ -109,4 +253,182 @@ pub(super) fn poll_chunk_mesh_tasks(
             }
         }
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::render::mesh::VertexAttributeValues;
+    use strum::IntoEnumIterator;
+
+    use crate::blocks::BlockType;
+    use crate::blocks::utils::Rotation;
+    use crate::meshing::{BlockModelUv, BlockFaceUv, BlockFaceType, TextureUvData};
+    use super::super::LockedChunkArea;
+    use super::*;
+
+    const MAX: i32 = CHUNK_SIZE as i32 - 1;
+
+    fn region(min: (i32, i32, i32), max: (i32, i32, i32)) -> DirtyRegion {
+        DirtyRegion {
+            min: BlockPos::new(min.0, min.1, min.2),
+            max: BlockPos::new(max.0, max.1, max.2),
+        }
+    }
+
+    #[test]
+    fn region_around_block_touches_its_neighbors() {
+        let chunk_pos = ChunkPos::new(1, 0, -1);
+        let chunk_min = BlockPos::from(chunk_pos);
+
+        assert_eq!(DirtyRegion::around_block(chunk_pos, chunk_min + BlockPos::new(10, 20, 30)), region((9, 19, 29), (11, 21, 31)));
+        // cut off at the edges and corners of the chunk
+        assert_eq!(DirtyRegion::around_block(chunk_pos, chunk_min + BlockPos::new(0, 5, MAX)), region((0, 4, MAX - 1), (1, 6, MAX)));
+        assert_eq!(DirtyRegion::around_block(chunk_pos, chunk_min + BlockPos::new(MAX, MAX, MAX)), region((MAX - 1, MAX - 1, MAX - 1), (MAX, MAX, MAX)));
+    }
+
+    #[test]
+    fn region_around_block_in_neighbor_chunk_is_the_touching_edge() {
+        let chunk_pos = ChunkPos::new(1, 0, -1);
+        let chunk_min = BlockPos::from(chunk_pos);
+
+        // across a face
+        assert_eq!(DirtyRegion::around_block(chunk_pos, chunk_min + BlockPos::new(-1, 5, 5)), region((0, 4, 4), (0, 6, 6)));
+        // across an edge
+        assert_eq!(DirtyRegion::around_block(chunk_pos, chunk_min + BlockPos::new(5, MAX + 1, -1)), region((4, MAX, 0), (6, MAX, 0)));
+        // across a corner, where only the corner block touches it
+        assert_eq!(DirtyRegion::around_block(chunk_pos, chunk_min + BlockPos::new(-1, -1, -1)), region((0, 0, 0), (0, 0, 0)));
+        assert_eq!(DirtyRegion::around_block(chunk_pos, chunk_min + BlockPos::new(MAX + 1, MAX + 1, MAX + 1)), region((MAX, MAX, MAX), (MAX, MAX, MAX)));
+    }
+
+    #[test]
+    fn region_facing_neighbor_is_the_touching_side() {
+        assert_eq!(DirtyRegion::facing_neighbor(ChunkPos::X), region((MAX, 0, 0), (MAX, MAX, MAX)));
+        assert_eq!(DirtyRegion::facing_neighbor(ChunkPos::NEG_Y), region((0, 0, 0), (MAX, 0, MAX)));
+        assert_eq!(DirtyRegion::facing_neighbor(ChunkPos::new(1, 0, -1)), region((MAX, 0, 0), (MAX, MAX, 0)));
+        assert_eq!(DirtyRegion::facing_neighbor(ChunkPos::new(-1, 1, 1)), region((0, MAX, MAX), (0, MAX, MAX)));
+    }
+
+    #[test]
+    fn region_union_covers_both_regions() {
+        let a = region((0, 10, 4), (2, 12, 6));
+        let b = region((5, 1, 5), (8, 3, 5));
+
+        assert_eq!(a.union(b), region((0, 1, 4), (8, 12, 6)));
+        assert_eq!(b.union(a), a.union(b));
+        assert_eq!(a.union(a), a);
+    }
+
+    #[test]
+    fn region_layers_are_along_the_face_axis() {
+        let region = region((1, 2, 3), (4, 5, 6));
+
+        assert_eq!(region.layers(FaceDirection::Left), 1..=4);
+        assert_eq!(region.layers(FaceDirection::Right), 1..=4);
+        assert_eq!(region.layers(FaceDirection::Top), 2..=5);
+        assert_eq!(region.layers(FaceDirection::Bottom), 2..=5);
+        assert_eq!(region.layers(FaceDirection::Front), 3..=6);
+        assert_eq!(region.layers(FaceDirection::Back), 3..=6);
+    }
+
+    /// Models with a full face on every side of every block except air, each block type has its own texture
+    fn test_models() -> Vec<BlockModelUv> {
+        BlockType::iter()
+            .map(|block_type| {
+                let texture_data = TextureUvData {
+                    uv_base: Vec2::ZERO,
+                    texture_map_index: block_type as usize,
+                };
+
+                BlockModelUv::new(BlockFaceUv {
+                    rotation: Rotation::Deg0,
+                    face_type: match block_type {
+                        BlockType::Air => BlockFaceType::Empty,
+                        _ => BlockFaceType::Full(texture_data),
+                    },
+                })
+            })
+            .collect()
+    }
+
+    /// Scattered stone which has lots of faces and ambient occlusion to get wrong
+    fn is_stone(block_pos: BlockPos) -> bool {
+        (block_pos.x * 7 + block_pos.y * 13 + block_pos.z * 5).rem_euclid(11) < 4
+    }
+
+    /// Builds a world with the chunk at the origin and every chunk around it
+    fn test_world() -> World {
+        let chunks = ChunkRegion {
+            min_chunk: ChunkPos::new(-1, -1, -1),
+            size: UVec3::new(3, 3, 3),
+        };
+
+        World::with_chunks(chunks.iter_chunks().map(|chunk_pos| {
+            let mut blocks = BlockStorage::default();
+
+            for x in 0..CHUNK_SIZE as i32 {
+                for y in 0..CHUNK_SIZE as i32 {
+                    for z in 0..CHUNK_SIZE as i32 {
+                        let local_pos = BlockPos::new(x, y, z);
+                        if is_stone(BlockPos::from(chunk_pos) + local_pos) {
+                            blocks.new_block(local_pos, BlockType::Stone);
+                        }
+                    }
+                }
+            }
+
+            (chunk_pos, blocks)
+        }))
+    }
+
+    /// Contents of every attribute of the mesh, followed by its indices
+    fn mesh_contents(mesh: &Mesh) -> Vec<Vec<u8>> {
+        let mut contents: Vec<_> = mesh.attributes()
+            .map(|(_, values): (_, &VertexAttributeValues)| values.get_bytes().to_vec())
+            .collect();
+
+        contents.push(mesh.indices().unwrap().iter().flat_map(|index| (index as u32).to_le_bytes()).collect());
+
+        contents
+    }
+
+    #[test]
+    fn partial_remesh_matches_full_remesh() {
+        let world = test_world();
+        let models = test_models();
+        let chunk = world.chunks.get(&ChunkPos::ZERO).unwrap().clone();
+
+        let area = ChunkRegion {
+            min_chunk: ChunkPos::new(-1, -1, -1),
+            size: UVec3::new(3, 3, 3),
+        };
+        let mesh = |cache: &mut ChunkMeshCache, dirty_region| {
+            let mesh_data = ChunkMeshData::new(LockedChunkArea::new(&world, area).unwrap());
+            generate_mesh(&mesh_data, &models, cache, dirty_region).unwrap()
+        };
+
+        // fills the cache
+        mesh(&mut chunk.mesh_cache.lock(), DirtyRegion::FULL);
+
+        // each round only changes part of the chunk, around its corners and edges, and in the chunks next to it
+        let rounds = [
+            vec![BlockPos::new(0, 0, 0), BlockPos::new(-1, 5, 5), BlockPos::new(-1, -1, -1), BlockPos::new(3, 0, 2)],
+            vec![BlockPos::new(MAX, MAX, MAX), BlockPos::new(MAX + 1, MAX + 1, MAX + 1), BlockPos::new(MAX, 10, MAX - 1)],
+            vec![BlockPos::new(15, 15, 15), BlockPos::new(16, MAX + 1, 14)],
+        ];
+
+        for edits in rounds {
+            world.new_blocks(edits.iter().map(|block_pos| {
+                let block_type = if is_stone(*block_pos) { BlockType::Air } else { BlockType::Stone };
+                (*block_pos, block_type)
+            }));
+
+            let dirty_region = chunk.dirty_region.lock().take().expect("the edits did not mark the chunk dirty");
+            assert_ne!(dirty_region, DirtyRegion::FULL, "edits {edits:?} marked the whole chunk dirty");
+
+            let partial = mesh(&mut chunk.mesh_cache.lock(), dirty_region);
+            let full = mesh(&mut ChunkMeshCache::default(), DirtyRegion::FULL);
+
+            assert!(mesh_contents(&partial) == mesh_contents(&full), "remeshing {dirty_region:?} after editing {edits:?} gave a different mesh");
+        }
+    }
 }
\ No newline at end of file
@@ -1,15 +1,12 @@
 use std::sync::Arc;
-use std::sync::atomic::{AtomicU32, Ordering, AtomicBool};
+use std::sync::atomic::Ordering;
 
 use bevy::prelude::*;
-use bevy::math::Vec3A;
-use bevy::render::primitives::Aabb;
-use parking_lot::RwLock;
 
+use crate::diagnostics::GENERATE_CHUNK_STATS;
 use crate::task::{Task, TaskPool};
-use crate::{types::*, render::GlobalBlockMaterial, worldgen::Worldgen};
-use super::CHUNK_SIZE;
-use super::{World, EcsChunk, Chunk, chunk::ChunkData, ChunkRegion};
+use crate::{types::*, worldgen::SharedWorldgen};
+use super::{World, EcsChunk, Chunk, chunk::ChunkData, ChunkRegion, DirtyRegion};
 
 /// Something which loads in chunks in a certain distance around it
 #[derive(Debug, Clone, Copy, Component)]
@@ -30,7 +27,7 @@ impl ChunkLoader {
         }
     }
 
-    pub fn move_to(&mut self, position: Vec3) {
+    pub fn move_to(&mut self, position: WorldPos) {
         self.position = position.into();
     }
 
@@ -46,10 +43,18 @@ impl ChunkLoader {
         }
     }
 
-    fn current_loaded_region(&self) -> ChunkRegion {
+    /// The region of chunks this loader wants loaded
+    pub fn loaded_region(&self) -> ChunkRegion {
         Self::chunk_region_inner(self.position, self.load_distance)
     }
 
+    /// Returns true if this loader is not keeping any chunks loaded
+    /// 
+    /// Set the load distance to 0 and wait for this before despawning a chunk loader, or its chunks will never be unloaded
+    pub fn is_unloaded(&self) -> bool {
+        self.load_distance == UVec3::ZERO && self.last_load_distance == UVec3::ZERO
+    }
+
     fn last_loaded_region(&self) -> ChunkRegion {
         Self::chunk_region_inner(self.last_position, self.last_load_distance)
     }
@@ -62,10 +67,11 @@ impl ChunkLoader {
 
 /// moves the chunk loaders to the position of the transform
 pub fn move_chunk_loader(
+    world: Res<World>,
     mut query: Query<(&mut ChunkLoader, &Transform)>,
 ) {
     for (mut loader, transform) in query.iter_mut() {
-        loader.move_to(transform.translation);
+        loader.move_to(world.world_pos(transform.translation));
     }
 }
 
@@ -73,10 +79,21 @@ pub fn move_chunk_loader(
 #[derive(Component)]
 pub struct ChunkLoadTask(Task<ChunkData>);
 
+/// Where the blocks for newly loaded chunks come from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Resource)]
+pub enum ChunkSource {
+    /// Chunks are generated locally by the world generator
+    #[default]
+    Generate,
+    /// Chunks are left empty until their blocks are received with [`finish_loading_chunk`]
+    Remote,
+}
+
 /// Loads and unloads chunks based on whre chunk loaders are
 pub fn queue_generate_chunks(
     mut world: ResMut<World>,
-    block_material: Res<GlobalBlockMaterial>,
+    chunk_source: Res<ChunkSource>,
+    worldgen: Res<SharedWorldgen>,
     mut loaders: Query<&mut ChunkLoader>,
     mut commands: Commands,
 ) {
@@ -84,46 +101,43 @@ pub fn queue_generate_chunks(
 
     for mut loader in loaders.iter_mut() {
         if loader.has_changed() {
-            let current_region = loader.current_loaded_region();
+            let current_region = loader.loaded_region();
             let last_region = loader.last_loaded_region();
 
             // FIXME: don't use this naive implementation
             for chunk_pos in current_region.iter_chunks() {
                 if !last_region.contains_chunk(chunk_pos) {
-                    // load chunks
-                    let load_task = task_pool.spawn(move || {
-                        Worldgen::get().generate_chunk(chunk_pos)
-                    });
-
-                    let half_chunk_size = CHUNK_SIZE as f32 * BLOCK_SIZE * 0.5;
-                    let half_chunk_size = Vec3A::new(half_chunk_size, half_chunk_size, half_chunk_size);
+                    if let Some(chunk) = world.chunks.get(&chunk_pos) {
+                        // another loader already has this chunk loaded, so keep using it instead of loading it again
+                        chunk.load_count.fetch_add(1, Ordering::AcqRel);
+                        continue;
+                    }
 
-                    let chunk_entity = commands.spawn((
+                    // load chunks
+                    // the components needed to render the chunk are added later by the chunk mesh plugin, if it exists
+                    let mut chunk_entity = commands.spawn((
                         EcsChunk(chunk_pos),
-                        ChunkLoadTask(load_task),
-                        // All these are all parts of material mesh bundle except the mesh, which will be generated later
                         TransformBundle {
-                            local: chunk_pos.into(),
+                            local: world.chunk_transform(chunk_pos),
                             ..Default::default()
                         },
-                        Aabb {
-                            center: half_chunk_size,
-                            half_extents: half_chunk_size,
-                        },
-                        Visibility::default(),
-                        ComputedVisibility::default(),
-                        block_material.0.clone(),
-                    )).id();
-
-                    let chunk = Chunk {
-                        data: RwLock::new(ChunkData::default()),
-                        chunk_pos,
-                        entity: chunk_entity,
-                        load_count: AtomicU32::new(1),
-                        // chunk is not dirty because it has no blocks and has not been generated yet,
-                        // so having no mesh is up to date with blocks
-                        dirty: AtomicBool::new(false),
-                    };
+                    ));
+
+                    if *chunk_source == ChunkSource::Generate {
+                        GENERATE_CHUNK_STATS.queue();
+                        let worldgen = worldgen.clone();
+                        let load_task = task_pool.spawn(move || {
+                            GENERATE_CHUNK_STATS.run(|| worldgen.generate_chunk(chunk_pos))
+                        });
+
+                        chunk_entity.insert(ChunkLoadTask(load_task));
+                    }
+
+                    let chunk_entity = chunk_entity.id();
+
+                    // chunk is not dirty because it has no blocks and has not been generated yet,
+                    // so having no mesh is up to date with blocks
+                    let chunk = Chunk::new(chunk_pos, chunk_entity, ChunkData::default());
 
                     world.chunks.insert(chunk_pos, Arc::new(chunk));
                 }
@@ -132,7 +146,9 @@ pub fn queue_generate_chunks(
             for chunk_pos in last_region.iter_chunks() {
                 if !current_region.contains_chunk(chunk_pos) {
                     // unload chunks
-                    let chunk = world.chunks.get(&chunk_pos).unwrap();
+                    let Some(chunk) = world.chunks.get(&chunk_pos) else {
+                        continue;
+                    };
 
                     // TODO: figure out if this is right ordering
                     let load_count = chunk.load_count.fetch_sub(1, Ordering::AcqRel);
@@ -149,31 +165,144 @@ pub fn queue_generate_chunks(
     }
 }
 
+/// Decides on which frame generated chunks finish loading
+/// 
+/// Chunks take a different amount of time to generate every run,
+/// so replays load chunks on the frames they were loaded on in the recording to stay deterministic
+#[derive(Debug, Clone, Default, Resource)]
+pub enum ChunkLoadTiming {
+    /// Chunks finish loading as soon as they are generated
+    #[default]
+    Immediate,
+    /// Chunks finish loading as soon as they are generated, and the chunks which finished this frame are added to the list
+    Recorded(Vec<ChunkPos>),
+    /// Only the chunks in the list finish loading this frame, waiting for them to be generated if they aren't yet
+    Replayed(Vec<ChunkPos>),
+}
+
 pub fn poll_chunk_load_tasks(
     world: Res<World>,
-    mut query: Query<(Entity, &EcsChunk, &ChunkLoadTask)>,
+    mut timing: ResMut<ChunkLoadTiming>,
+    query: Query<(Entity, &EcsChunk, &ChunkLoadTask)>,
     mut commands: Commands,
 ) {
-    for (entity, ecs_chunk, load_task) in query.iter_mut() {
-        if let Some(chunk_data) = load_task.0.poll() {
-            commands.entity(entity).remove::<ChunkLoadTask>();
+    let mut finished_chunks = Vec::new();
 
-            let chunk = world.chunks.get(&ecs_chunk.0).unwrap();
-            *chunk.data.write() = chunk_data;
-            chunk.mark_dirty(&world);
+    if let ChunkLoadTiming::Replayed(chunks) = &mut *timing {
+        for chunk_pos in chunks.drain(..) {
+            let load_task = world.chunks.get(&chunk_pos)
+                .and_then(|chunk| query.get(chunk.entity).ok());
 
-            let adjacent_region = ChunkRegion {
-                min_chunk: ChunkPos::new(-1, -1, -1),
-                size: UVec3::new(3, 3, 3),
+            let Some((entity, _, load_task)) = load_task else {
+                warn!("replayed chunk {chunk_pos:?} is not being loaded");
+                continue;
             };
 
-            for chunk_pos in adjacent_region.iter_chunks() {
-                if chunk_pos != ChunkPos::ZERO {
-                    if let Some(chunk) = world.chunks.get(&(ecs_chunk.0 + chunk_pos)) {
-                        chunk.mark_dirty(&world);
-                    }
-                }
+            finished_chunks.push((entity, chunk_pos, load_task.0.wait()));
+        }
+    } else {
+        for (entity, ecs_chunk, load_task) in query.iter() {
+            if let Some(chunk_data) = load_task.0.poll() {
+                finished_chunks.push((entity, ecs_chunk.0, chunk_data));
             }
         }
+
+        // the query order can change between runs, so chunks are always finished in the same order
+        finished_chunks.sort_by_key(|(_, chunk_pos, _)| chunk_pos.to_array());
+    }
+
+    for (entity, chunk_pos, chunk_data) in finished_chunks {
+        commands.entity(entity).remove::<ChunkLoadTask>();
+
+        finish_loading_chunk(&world, chunk_pos, chunk_data);
+
+        if let ChunkLoadTiming::Recorded(chunks) = &mut *timing {
+            chunks.push(chunk_pos);
+        }
+    }
+}
+
+/// Sets the blocks of a chunk which has been loaded but not filled in yet, and marks it and its neighbors for remeshing
+/// 
+/// Returns false if the chunk is not loaded
+pub fn finish_loading_chunk(world: &World, chunk_pos: ChunkPos, chunk_data: ChunkData) -> bool {
+    let Some(chunk) = world.chunks.get(&chunk_pos) else {
+        return false;
+    };
+
+    *chunk.data.write() = chunk_data;
+    chunk.loaded.store(true, Ordering::Release);
+    chunk.mark_dirty(world);
+
+    let adjacent_region = ChunkRegion {
+        min_chunk: ChunkPos::new(-1, -1, -1),
+        size: UVec3::new(3, 3, 3),
+    };
+
+    for offset in adjacent_region.iter_chunks() {
+        if offset != ChunkPos::ZERO && let Some(chunk) = world.chunks.get(&(chunk_pos + offset)) {
+            // only the blocks in the neighbor touching this chunk can have their faces changed
+            chunk.mark_region_dirty(world, DirtyRegion::facing_neighbor(ChunkPos::ZERO - offset));
+        }
+    }
+
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::worldgen::{Worldgen, WorldgenSettings};
+    use super::*;
+
+    /// Runs the chunk loading system without generating any chunks
+    fn test_app() -> App {
+        let worldgen = Worldgen::new(WorldgenSettings::default());
+
+        let mut app = App::new();
+        app.insert_resource(World::default())
+            .insert_resource(ChunkSource::Remote)
+            .insert_resource(SharedWorldgen(Arc::new(worldgen)))
+            .add_systems(Update, queue_generate_chunks);
+
+        app
+    }
+
+    fn loaded_chunk(app: &App, chunk_pos: ChunkPos) -> Option<Arc<Chunk>> {
+        app.world.resource::<World>().chunks.get(&chunk_pos).cloned()
+    }
+
+    fn chunk_entity_count(app: &mut App) -> usize {
+        app.world.query::<&EcsChunk>().iter(&app.world).count()
+    }
+
+    #[test]
+    fn overlapping_loaders_share_chunks() {
+        let mut app = test_app();
+        let first = app.world.spawn(ChunkLoader::new(ChunkPos::new(0, 0, 0), UVec3::splat(2))).id();
+        let second = app.world.spawn(ChunkLoader::new(ChunkPos::new(2, 0, 0), UVec3::splat(2))).id();
+        app.update();
+
+        // each loader loads 3x3x3 chunks, and they overlap in the 1x3x3 chunks at x = 1
+        let shared_pos = ChunkPos::new(1, 0, 0);
+        let shared_chunk = loaded_chunk(&app, shared_pos).unwrap();
+        assert_eq!(shared_chunk.load_count.load(Ordering::Acquire), 2);
+        assert_eq!(app.world.resource::<World>().chunks.len(), 45);
+        assert_eq!(chunk_entity_count(&mut app), 45);
+
+        app.world.get_mut::<ChunkLoader>(first).unwrap().position = ChunkPos::new(-10, 0, 0);
+        app.update();
+
+        // the shared chunk is still the same chunk, so it keeps its blocks and entity
+        let kept_chunk = loaded_chunk(&app, shared_pos).unwrap();
+        assert!(Arc::ptr_eq(&shared_chunk, &kept_chunk));
+        assert_eq!(kept_chunk.load_count.load(Ordering::Acquire), 1);
+        assert!(loaded_chunk(&app, ChunkPos::new(0, 0, 0)).is_none());
+
+        app.world.get_mut::<ChunkLoader>(second).unwrap().position = ChunkPos::new(10, 0, 0);
+        app.update();
+
+        assert!(loaded_chunk(&app, shared_pos).is_none());
+        assert_eq!(app.world.resource::<World>().chunks.len(), 54);
+        assert_eq!(chunk_entity_count(&mut app), 54);
     }
 }
\ No newline at end of file
@@ -11,6 +11,17 @@ pub struct ChunkRegion {
 }
 
 impl ChunkRegion {
+    /// Creates the smallest region containing both chunks
+    pub fn from_corners(a: ChunkPos, b: ChunkPos) -> Self {
+        let min_chunk = ChunkPos(a.0.min(b.0));
+        let max_chunk = ChunkPos(a.0.max(b.0));
+
+        ChunkRegion {
+            min_chunk,
+            size: (max_chunk.0 - min_chunk.0 + IVec3::ONE).as_uvec3(),
+        }
+    }
+
     /// Position of maximum chunk, exclusive
     pub fn max_chunk(&self) -> ChunkPos {
         ChunkPos(self.min_chunk.0 + self.size.as_ivec3())
@@ -0,0 +1,168 @@
+use bevy::prelude::*;
+use rustc_hash::FxHashSet;
+
+use crate::blocks::{Block, BlockType};
+use crate::types::*;
+use super::{World, ChunkLockCache};
+
+/// Number of rays cast along each edge of the cube of rays sent out from an explosion
+const RAYS_PER_EDGE: i32 = 16;
+/// Distance in blocks each explosion ray moves forward between checking blocks
+const RAY_STEP: f32 = 0.3;
+/// Strength lost by an explosion ray for each step, even when moving through air
+const RAY_STEP_FALLOFF: f32 = 0.225;
+/// Added to blast resistance of solid blocks, so even weak blocks slow down the explosion
+const BASE_BLOCK_RESISTANCE: f32 = 0.3;
+
+/// Iterates the directions of every explosion ray,
+/// these point from the center to each point on the surface of a cube
+fn ray_directions() -> impl Iterator<Item = Vec3> {
+    let max = RAYS_PER_EDGE - 1;
+
+    (0..RAYS_PER_EDGE).flat_map(move |x| {
+        (0..RAYS_PER_EDGE).flat_map(move |y| {
+            (0..RAYS_PER_EDGE).map(move |z| IVec3::new(x, y, z))
+        })
+    })
+    .filter(move |pos| {
+        pos.x == 0 || pos.x == max
+            || pos.y == 0 || pos.y == max
+            || pos.z == 0 || pos.z == max
+    })
+    .map(move |pos| (pos.as_vec3() / max as f32 * 2.0 - Vec3::ONE).normalize())
+}
+
+impl World {
+    /// Creates an explosion at `center` in render space, destroying blocks the explosion is strong enough to get through
+    /// 
+    /// `power` is roughly the radius of the explosion in blocks when there is nothing blocking it
+    /// Returns all the blocks which were destroyed, so drops and effects can be spawned for them
+    pub fn explode(&self, center: Vec3, power: f32) -> Vec<(BlockPos, Block)> {
+        let mut destroyed = FxHashSet::default();
+        let mut destroyed_blocks = Vec::new();
+
+        let mut chunk_lock = ChunkLockCache::new(self);
+
+        for direction in ray_directions() {
+            let step = direction * RAY_STEP * BLOCK_SIZE;
+            let mut position = center;
+            // scaled so power is about the distance the ray travels through air
+            let mut strength = power * RAY_STEP_FALLOFF / RAY_STEP;
+
+            while strength > 0.0 {
+                let block_pos = self.block_pos_at(position);
+
+                if let Some(block) = chunk_lock.get_block(block_pos) && !block.is_air() {
+                    strength -= (block.block_type().properties().blast_resistance + BASE_BLOCK_RESISTANCE) * RAY_STEP;
+
+                    // water slows the explosion down, but it can't be destroyed
+                    if strength > 0.0 && !block.is_replaceable() && destroyed.insert(block_pos) {
+                        destroyed_blocks.push((block_pos, block));
+                    }
+                }
+
+                position += step;
+                strength -= RAY_STEP_FALLOFF;
+            }
+        }
+
+        // release read locks before taking write locks
+        drop(chunk_lock);
+
+        self.new_blocks(destroyed_blocks.iter().map(|(block_pos, _)| (*block_pos, BlockType::Air)));
+
+        destroyed_blocks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::blocks::BlockStorage;
+    use super::*;
+
+    /// Block the explosions are centered on, in the middle of the chunk at the origin
+    fn center() -> BlockPos {
+        BlockPos::new(16, 16, 16)
+    }
+
+    /// Builds a world with only the chunk at the origin loaded, which is air apart from the given blocks
+    fn world_with_blocks(blocks: &[(BlockPos, BlockType)]) -> World {
+        let mut storage = BlockStorage::default();
+        for (block_pos, block_type) in blocks {
+            storage.new_block(*block_pos, *block_type);
+        }
+
+        World::with_chunks([(ChunkPos::new(0, 0, 0), storage)])
+    }
+
+    fn explode_at_center(world: &World, power: f32) -> Vec<(BlockPos, Block)> {
+        world.explode(world.block_render_pos(center()) + Vec3::splat(BLOCK_SIZE / 2.0), power)
+    }
+
+    fn block_type(world: &World, block_pos: BlockPos) -> BlockType {
+        world.get_block(block_pos).unwrap().block_type()
+    }
+
+    #[test]
+    fn stone_shortens_the_blast() {
+        let target = center() + BlockPos::new(4, 0, 0);
+
+        let world = world_with_blocks(&[(target, BlockType::Dirt)]);
+        explode_at_center(&world, 6.0);
+        assert_eq!(block_type(&world, target), BlockType::Air, "the blast did not reach through the air");
+
+        // the same blast has to get through stone to reach the dirt
+        let mut blocks: Vec<_> = (1..4).map(|x| (center() + BlockPos::new(x, 0, 0), BlockType::Stone)).collect();
+        blocks.push((target, BlockType::Dirt));
+
+        let world = world_with_blocks(&blocks);
+        explode_at_center(&world, 6.0);
+        assert_eq!(block_type(&world, target), BlockType::Dirt, "the blast went through stone as if it was air");
+    }
+
+    #[test]
+    fn water_and_stone_survive_a_blast_that_breaks_dirt() {
+        let dirt = center() + BlockPos::new(0, 0, 1);
+        let stone = center() + BlockPos::new(0, 0, -1);
+        let water = center() + BlockPos::new(1, 0, 0);
+        let behind_water = center() + BlockPos::new(2, 0, 0);
+
+        let world = world_with_blocks(&[
+            (dirt, BlockType::Dirt),
+            (stone, BlockType::Stone),
+            (water, BlockType::Water),
+            (behind_water, BlockType::Dirt),
+        ]);
+        let destroyed = explode_at_center(&world, 2.5);
+
+        assert_eq!(block_type(&world, dirt), BlockType::Air);
+        assert_eq!(block_type(&world, stone), BlockType::Stone);
+        assert_eq!(block_type(&world, water), BlockType::Water);
+        assert_eq!(block_type(&world, behind_water), BlockType::Dirt, "the blast went through the water");
+
+        assert!(destroyed.iter().all(|(_, block)| !block.is_replaceable()), "air or water was destroyed");
+    }
+
+    #[test]
+    fn every_touched_chunk_is_remeshed_once() {
+        // 8 chunks of dirt meeting at the origin, so a blast there touches all of them
+        let chunks: Vec<_> = (-1..=0)
+            .flat_map(|x| (-1..=0).flat_map(move |y| (-1..=0).map(move |z| ChunkPos::new(x, y, z))))
+            .collect();
+        let world = World::with_chunks(chunks.iter().map(|chunk_pos| (*chunk_pos, BlockStorage::new_filled(BlockType::Dirt))));
+
+        let destroyed = world.explode(Vec3::ZERO, 4.0);
+        assert!(!destroyed.is_empty());
+
+        let mut remeshed = Vec::new();
+        while let Some(chunk_pos) = world.dirty_chunks.pop() {
+            remeshed.push(chunk_pos);
+        }
+
+        for chunk_pos in chunks.iter() {
+            let count = remeshed.iter().filter(|remeshed_pos| *remeshed_pos == chunk_pos).count();
+            assert_eq!(count, 1, "chunk {chunk_pos:?} was queued to be remeshed {count} times");
+        }
+        assert_eq!(remeshed.len(), chunks.len());
+    }
+}
\ No newline at end of file
@@ -0,0 +1,46 @@
+use bevy::prelude::*;
+use bevy::ecs::query::Has;
+
+use crate::types::*;
+use super::{World, EcsChunk};
+
+/// How many chunks away from the origin the anchor can move along any axis before the origin is moved to follow it
+const ORIGIN_SHIFT_DISTANCE: i32 = 4;
+
+/// Marks the entity which the floating origin follows, this should be the entity the camera is on
+#[derive(Debug, Default, Component)]
+pub struct FloatingOriginAnchor;
+
+/// Entities which are moved when the origin shifts, chunks are moved differently and the anchor is looked up by its marker
+type ShiftedEntity = (&'static mut Transform, Option<&'static EcsChunk>, Has<FloatingOriginAnchor>);
+
+/// Moves the render space origin to the chunk the anchor is in once it gets too far away,
+/// and moves all top level entities so they stay in the same place in the world
+pub(super) fn shift_floating_origin(
+    mut world: ResMut<World>,
+    mut transforms: Query<ShiftedEntity, (Without<Parent>, Without<Node>)>,
+) {
+    let Some(anchor_position) = transforms.iter()
+        .find(|(_, _, is_anchor)| *is_anchor)
+        .map(|(transform, _, _)| transform.translation) else {
+        return;
+    };
+
+    // position of the anchor's chunk relative to the current origin
+    let offset = ChunkPos::from(anchor_position);
+    if offset.abs().max_element() < ORIGIN_SHIFT_DISTANCE {
+        return;
+    }
+
+    world.origin += offset;
+    let shift = Vec3::from(offset);
+
+    for (mut transform, ecs_chunk, _) in transforms.iter_mut() {
+        if let Some(ecs_chunk) = ecs_chunk {
+            // recalculate chunk positions instead of subtracting so they are always exact
+            *transform = world.chunk_transform(ecs_chunk.0);
+        } else {
+            transform.translation -= shift;
+        }
+    }
+}
\ No newline at end of file
@@ -4,7 +4,8 @@ use bevy::prelude::*;
 use parking_lot::RwLockReadGuard;
 
 use super::{ChunkData, ChunkRegion, World, Chunk};
-use crate::types::ChunkPos;
+use crate::blocks::Block;
+use crate::types::{ChunkPos, BlockPos};
 
 pub struct OwnedChunkArea {
     chunks: Vec<Arc<Chunk>>,
@@ -77,4 +78,11 @@ impl<'chunks> LockedChunkArea<'chunks> {
     pub fn get_chunk_data_relative(&self, chunk_pos: ChunkPos) -> Option<&ChunkData> {
         Some(&self.chunks[self.region.get_array_index_relative(chunk_pos)?])
     }
+
+    /// Gets the block at the given position, or `None` if the block is outside of this area
+    pub fn get_block(&self, block_pos: BlockPos) -> Option<Block> {
+        let chunk_data = self.get_chunk_data(ChunkPos::from(block_pos))?;
+
+        Some(chunk_data.blocks.get(block_pos.as_chunk_local()))
+    }
 }
\ No newline at end of file
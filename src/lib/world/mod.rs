@@ -1,17 +1,21 @@
 use bevy::prelude::*;
+use bevy::transform::TransformSystem;
 
-use crate::{types::ChunkPos, GameSet};
+use crate::{types::ChunkPos, render::TextureLoadState, GameSet};
 
 mod chunk;
-pub use chunk::{Chunk, ChunkData, CHUNK_SIZE, CHUNK_BLOCK_COUNT};
+pub use chunk::{Chunk, ChunkData, DirtyRegion, CHUNK_SIZE, CHUNK_BLOCK_COUNT};
 mod chunk_loader;
-pub use chunk_loader::ChunkLoader;
+pub use chunk_loader::{ChunkLoader, ChunkSource, ChunkLoadTiming, finish_loading_chunk};
 mod chunk_region;
 pub use chunk_region::*;
+mod explosion;
+mod floating_origin;
+pub use floating_origin::FloatingOriginAnchor;
 mod locked_chunk_area;
 pub use locked_chunk_area::*;
 mod world;
-pub use world::World;
+pub use world::{World, ChunkLockCache};
 
 /// A chunk component stored for the chunk entity in the EcsChunk
 /// 
@@ -24,6 +28,8 @@ pub struct WorldPlugin;
 impl Plugin for WorldPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<World>()
+            .init_resource::<ChunkSource>()
+            .init_resource::<ChunkLoadTiming>()
             .add_systems(
                 Update,
                 (
@@ -31,10 +37,41 @@ impl Plugin for WorldPlugin {
                     // run this before queue generate chunks so it will run next frame, which will give command buffer time to flush
                     chunk_loader::poll_chunk_load_tasks.before(chunk_loader::queue_generate_chunks),
                     chunk_loader::queue_generate_chunks,
-                    chunk::poll_chunk_mesh_tasks,
                 ).in_set(GameSet::Main)
             )
+            // move the origin before transforms are propagated so the shift is never visible
+            .add_systems(
+                PostUpdate,
+                floating_origin::shift_floating_origin.before(TransformSystem::TransformPropagate),
+            )
+            // nothing will remesh dirty chunks if chunks are not rendered, so they have to be cleared
+            .add_systems(
+                PostUpdate,
+                chunk::discard_dirty_chunks.run_if(not(resource_exists::<ChunkMeshingEnabled>())),
+            );
+    }
+}
+
+/// Inserted by the [`ChunkMeshPlugin`] to indicate that dirty chunks will be remeshed
+#[derive(Debug, Default, Resource)]
+struct ChunkMeshingEnabled;
+
+/// Generates meshes for chunks and renders them
+/// 
+/// This needs the block models and block material, so it won't do anything until textures are loaded
+pub struct ChunkMeshPlugin;
+
+impl Plugin for ChunkMeshPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ChunkMeshingEnabled>()
+            .add_systems(
+                Update,
+                (
+                    chunk::add_chunk_render_components,
+                    chunk::poll_chunk_mesh_tasks,
+                ).run_if(in_state(TextureLoadState::Done))
+            )
             // do this after everything else has run
-            .add_systems(PostUpdate, chunk::remesh_dirty_chunks);
+            .add_systems(PostUpdate, chunk::remesh_dirty_chunks.run_if(in_state(TextureLoadState::Done)));
     }
 }
\ No newline at end of file
@@ -1,26 +1,110 @@
+use std::hash::{Hash, Hasher};
 use std::sync::Arc;
 
 use bevy::{prelude::*, utils::HashMap};
 use parking_lot::{RwLockReadGuard, RwLockWriteGuard};
 use crossbeam::queue::SegQueue;
+use rustc_hash::{FxHashMap, FxHasher};
 
-use crate::{types::*, vec3_map_many, blocks::{Block, BlockType}};
-use super::{chunk::Chunk, ChunkData};
+use crate::{types::*, vec3_map_many, blocks::{Block, BlockType}, meshing::FaceDirection};
+use super::{chunk::Chunk, ChunkData, DirtyRegion, CHUNK_SIZE};
 
 #[derive(Debug, Default, Resource)]
 pub struct World {
     pub chunks: HashMap<ChunkPos, Arc<Chunk>>,
     /// A list of chunks which have changed and need to be remeshed
     pub(super) dirty_chunks: SegQueue<ChunkPos>,
+    /// The chunk which is at (0, 0, 0) in render space
+    /// 
+    /// This follows the player so render space positions, which are f32, stay close to 0 and don't lose precision
+    pub(super) origin: ChunkPos,
+    /// Every block change made since the last call to `take_block_changes`, or `None` if changes are not being recorded
+    change_log: Option<SegQueue<(BlockPos, BlockType)>>,
+    /// Positions of blocks which were replaced by air or water since the last call to `take_removed_blocks`
+    removed_blocks: SegQueue<BlockPos>,
 }
 
 #[derive(Debug)]
 pub struct RayHitInfo {
+    /// Point in render space where the ray entered the hit block
     pub position: Vec3,
     pub block_pos: BlockPos,
+    /// The face of the hit block that the ray entered through
+    pub face: FaceDirection,
+    /// Surface normal of the hit face
+    pub normal: Vec3,
+    /// Position of the block adjacent to the hit face, which is where a new block would be placed
+    pub place_pos: BlockPos,
+    /// Distance travelled along the ray before hitting the block
+    pub distance: f32,
 }
 
 impl World {
+    /// Number of chunks waiting to be remeshed
+    pub fn dirty_chunk_count(&self) -> usize {
+        self.dirty_chunks.len()
+    }
+
+    /// Starts recording every block that is changed, so the changes can be sent somewhere else
+    pub fn enable_change_log(&mut self) {
+        self.change_log.get_or_insert_with(SegQueue::new);
+    }
+
+    /// Returns every block change recorded since the last call, in the order they were made
+    pub fn take_block_changes(&self) -> Vec<(BlockPos, BlockType)> {
+        let Some(ref change_log) = self.change_log else {
+            return Vec::new();
+        };
+
+        let mut changes = Vec::with_capacity(change_log.len());
+        while let Some(change) = change_log.pop() {
+            changes.push(change);
+        }
+
+        changes
+    }
+
+    /// Returns the position of every block removed since the last call, so blocks that were resting on them can fall
+    /// 
+    /// Changes made with `new_blocks_untracked` are not included, since they came from somewhere that already handles this
+    pub fn take_removed_blocks(&self) -> Vec<BlockPos> {
+        let mut removed_blocks = Vec::with_capacity(self.removed_blocks.len());
+        while let Some(block_pos) = self.removed_blocks.pop() {
+            removed_blocks.push(block_pos);
+        }
+
+        removed_blocks
+    }
+
+    pub fn origin(&self) -> ChunkPos {
+        self.origin
+    }
+
+    /// Converts a position in render space to a position in the world
+    pub fn world_pos(&self, render_pos: Vec3) -> WorldPos {
+        WorldPos::from(BlockPos::from(self.origin)) + WorldPos(render_pos.as_dvec3())
+    }
+
+    /// Converts a position in the world to a position in render space
+    pub fn render_pos(&self, world_pos: WorldPos) -> Vec3 {
+        (world_pos - WorldPos::from(BlockPos::from(self.origin))).as_vec3()
+    }
+
+    /// Gets the block containing the given position in render space
+    pub fn block_pos_at(&self, render_pos: Vec3) -> BlockPos {
+        BlockPos::from(render_pos) + BlockPos::from(self.origin)
+    }
+
+    /// Gets the position in render space of the minimum corner of the block
+    pub fn block_render_pos(&self, block_pos: BlockPos) -> Vec3 {
+        Vec3::from(block_pos - BlockPos::from(self.origin))
+    }
+
+    /// Gets the transform a chunk's entity should have to be rendered in the right place
+    pub fn chunk_transform(&self, chunk_pos: ChunkPos) -> Transform {
+        Transform::from(chunk_pos - self.origin)
+    }
+
     /// Sets the block at the given position to the given block type
     /// 
     /// Returns a copy of the block on sucess, or `None` on failure
@@ -32,8 +116,104 @@ impl World {
             .new_block(block_pos, block_type)
     }
 
+    /// Sets many blocks at once, locking each chunk only once and marking each affected chunk dirty only once
+    /// 
+    /// Returns the positions of blocks which were set, blocks in chunks that are not loaded are skipped
+    pub fn new_blocks<I: IntoIterator<Item = (BlockPos, BlockType)>>(&self, blocks: I) -> Vec<BlockPos> {
+        self.new_blocks_inner(blocks, true)
+    }
+
+    /// Same as `new_blocks`, but the changes are not recorded in the change log
+    /// 
+    /// This is used to apply changes which came from somewhere else, so they are not sent back
+    pub fn new_blocks_untracked<I: IntoIterator<Item = (BlockPos, BlockType)>>(&self, blocks: I) -> Vec<BlockPos> {
+        self.new_blocks_inner(blocks, false)
+    }
+
+    fn new_blocks_inner<I: IntoIterator<Item = (BlockPos, BlockType)>>(&self, blocks: I, track_changes: bool) -> Vec<BlockPos> {
+        let mut blocks = blocks.into_iter().collect::<Vec<_>>();
+        // group edits by chunk so the lock cache does not keep relocking chunks
+        blocks.sort_by_key(|(block_pos, _)| ChunkPos::from(*block_pos).to_array());
+
+        let mut set_blocks = Vec::with_capacity(blocks.len());
+        let mut dirty_regions: FxHashMap<ChunkPos, DirtyRegion> = FxHashMap::default();
+
+        let mut chunk_lock = ChunkLockCacheMut::new(self);
+        chunk_lock.track_changes = track_changes;
+
+        for (block_pos, block_type) in blocks {
+            if chunk_lock.new_block_unmarked(block_pos, block_type).is_some() {
+                for chunk_pos in block_pos.adjacent_chunks().iter_chunks() {
+                    let region = DirtyRegion::around_block(chunk_pos, block_pos);
+
+                    dirty_regions.entry(chunk_pos)
+                        .and_modify(|old_region| *old_region = old_region.union(region))
+                        .or_insert(region);
+                }
+                set_blocks.push(block_pos);
+            }
+        }
+        drop(chunk_lock);
+
+        for (chunk_pos, region) in dirty_regions {
+            if let Some(chunk) = self.chunks.get(&chunk_pos) {
+                chunk.mark_region_dirty(self, region);
+            }
+        }
+
+        set_blocks
+    }
+
+    /// Hashes the position and blocks of every loaded chunk, so 2 worlds can be checked to be the same
+    /// 
+    /// Chunks which are still being generated are skipped
+    pub fn block_hash(&self) -> u64 {
+        let mut chunk_positions = self.chunks.iter()
+            .filter(|(_, chunk)| chunk.is_loaded())
+            .map(|(chunk_pos, _)| *chunk_pos)
+            .collect::<Vec<_>>();
+        chunk_positions.sort_by_key(|chunk_pos| chunk_pos.to_array());
+
+        let mut hasher = FxHasher::default();
+
+        for chunk_pos in chunk_positions {
+            chunk_pos.to_array().hash(&mut hasher);
+
+            let chunk_data = self.chunks[&chunk_pos].data.read();
+            if chunk_data.blocks.is_empty() {
+                continue;
+            }
+
+            let size = CHUNK_SIZE as i32;
+            for x in 0..size {
+                for y in 0..size {
+                    for z in 0..size {
+                        chunk_data.blocks.get(BlockPos::new(x, y, z)).block_id().hash(&mut hasher);
+                    }
+                }
+            }
+        }
+
+        hasher.finish()
+    }
+
+    /// Gets a copy of the block at the given position, or `None` if the chunk containing it is not loaded
+    pub fn get_block(&self, block_pos: BlockPos) -> Option<Block> {
+        ChunkLockCache::new(self)
+            .get_block(block_pos)
+    }
+
+    /// Casts a ray in render space and returns information about the first block hit which is not replaceable, such as air or water
     pub fn raycast(&self, ray: Ray, max_length: f32) -> Option<RayHitInfo> {
-        let mut block_pos = BlockPos::from(ray.origin);
+        self.raycast_filtered(ray, max_length, |block| !block.is_replaceable())
+    }
+
+    /// Casts a ray and returns information about the first block for which `is_hit` returns true
+    /// 
+    /// Blocks in chunks which are not loaded are never considered a hit
+    pub fn raycast_filtered<F: FnMut(Block) -> bool>(&self, ray: Ray, max_length: f32, mut is_hit: F) -> Option<RayHitInfo> {
+        // the origin is always on a block boundary, so converting to a block position here is exact
+        let mut block_pos = self.block_pos_at(ray.origin);
 
         let direction = ray.direction.signum().as_ivec3();
 
@@ -76,6 +256,7 @@ impl World {
                 VecAxis::Z
             };
 
+            let last_block_pos = block_pos;
             block_pos[next_intercept_axis] += direction[next_intercept_axis];
 
             let current_time = next_intercept_time[next_intercept_axis];
@@ -87,10 +268,17 @@ impl World {
             }
 
             // ray has hit
-            if let Some(block) = chunk_lock.get_block(block_pos) && !block.is_air() {
+            if let Some(block) = chunk_lock.get_block(block_pos) && is_hit(block) {
+                // the ray enters through the face pointing back against the direction of travel
+                let face = FaceDirection::from_axis(next_intercept_axis, direction[next_intercept_axis] < 0);
+
                 return Some(RayHitInfo {
                     position: ray.get_point(current_time),
                     block_pos,
+                    face,
+                    normal: face.normal().as_vec3(),
+                    place_pos: last_block_pos,
+                    distance: current_time,
                 });
             }
         }
@@ -98,7 +286,7 @@ impl World {
 }
 
 /// Caches the last lock chunk so block accessess around the same area do not need to repeatedly re lock the chunk
-struct ChunkLockCache<'world> {
+pub struct ChunkLockCache<'world> {
     world: &'world World,
     inner: Option<ChunkLockCacheInner<'world>>,
 }
@@ -109,7 +297,7 @@ struct ChunkLockCacheInner<'world> {
 }
 
 impl<'a> ChunkLockCache<'a> {
-    fn new(world: &'a World) -> Self {
+    pub fn new(world: &'a World) -> Self {
         ChunkLockCache {
             world,
             inner: None,
@@ -138,18 +326,31 @@ impl<'a> ChunkLockCache<'a> {
         }
     }
 
-    fn get_block(&mut self, block_pos: BlockPos) -> Option<Block> {
+    pub fn get_block(&mut self, block_pos: BlockPos) -> Option<Block> {
         self.lock_chunk(ChunkPos::from(block_pos));
         let chunk_data = self.get_chunk_data()?;
 
         Some(chunk_data.blocks.get(block_pos.as_chunk_local()))
     }
+
+    /// Same as `get_block`, but returns None if the chunk's blocks have not been generated or received yet
+    pub fn get_loaded_block(&mut self, block_pos: BlockPos) -> Option<Block> {
+        self.lock_chunk(ChunkPos::from(block_pos));
+        let inner = self.inner.as_ref()?;
+        if !inner.chunk.is_loaded() {
+            return None;
+        }
+
+        Some(inner.lock.blocks.get(block_pos.as_chunk_local()))
+    }
 }
 
 /// Caches the last lock chunk so block accessess around the same area do not need to repeatedly re lock the chunk
 struct ChunkLockCacheMut<'world> {
     world: &'world World,
     inner: Option<ChunkLockCacheInnerMut<'world>>,
+    /// If set blocks are recorded in the world's change log
+    track_changes: bool,
 }
 
 struct ChunkLockCacheInnerMut<'world> {
@@ -162,6 +363,7 @@ impl<'a> ChunkLockCacheMut<'a> {
         ChunkLockCacheMut {
             world,
             inner: None,
+            track_changes: true,
         }
     }
 
@@ -210,17 +412,50 @@ impl<'a> ChunkLockCacheMut<'a> {
     }*/
 
     fn new_block(&mut self, block_pos: BlockPos, block_type: BlockType) -> Option<Block> {
-        self.lock_chunk(ChunkPos::from(block_pos));
-
-        // do this before marking chunks as dirty to ensure lock chunk succeeded
-        let inner = self.inner.as_mut()?;
+        let block = self.new_block_unmarked(block_pos, block_type)?;
 
         for chunk_pos in block_pos.adjacent_chunks().iter_chunks() {
             if let Some(chunk) = self.world.chunks.get(&chunk_pos) {
-                chunk.mark_dirty(self.world);
+                chunk.mark_region_dirty(self.world, DirtyRegion::around_block(chunk_pos, block_pos));
             }
         }
 
-        Some(inner.lock.blocks.new_block(block_pos.as_chunk_local(), block_type))
+        Some(block)
+    }
+
+    /// Same as `new_block`, but the caller is responsible for marking the affected chunks as dirty
+    fn new_block_unmarked(&mut self, block_pos: BlockPos, block_type: BlockType) -> Option<Block> {
+        self.lock_chunk(ChunkPos::from(block_pos));
+        let inner = self.inner.as_mut()?;
+
+        let block = inner.lock.blocks.new_block(block_pos.as_chunk_local(), block_type);
+
+        if self.track_changes {
+            if let Some(ref change_log) = self.world.change_log {
+                change_log.push((block_pos, block_type));
+            }
+
+            if block_type.is_replaceable() {
+                self.world.removed_blocks.push(block_pos);
+            }
+        }
+
+        Some(block)
+    }
+}
+
+#[cfg(test)]
+impl World {
+    /// Creates a world where only the given chunks are loaded, so tests can use hand built blocks instead of worldgen
+    pub(crate) fn with_chunks<I: IntoIterator<Item = (ChunkPos, crate::blocks::BlockStorage)>>(chunks: I) -> Self {
+        let mut world = World::default();
+
+        for (chunk_pos, blocks) in chunks {
+            let chunk = Chunk::new(chunk_pos, Entity::PLACEHOLDER, blocks.into());
+            chunk.loaded.store(true, std::sync::atomic::Ordering::Release);
+            world.chunks.insert(chunk_pos, Arc::new(chunk));
+        }
+
+        world
     }
 }
\ No newline at end of file
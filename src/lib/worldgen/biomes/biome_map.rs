@@ -0,0 +1,237 @@
+use super::*;
+
+/// Number of cells along each axis of the lookup grid
+const GRID_SIZE: usize = 8;
+/// Size of each cell along each axis, since conditions are in the range -1 to 1
+const CELL_SIZE: f64 = 2.0 / GRID_SIZE as f64;
+/// Every point in a cell is within this distance of the cell's center, which is half the diagonal of a 4d cell
+const CELL_RADIUS: f64 = CELL_SIZE;
+
+#[derive(Debug)]
+struct MapBiome {
+    biome_type: BiomeType,
+    conditions: BiomeConditions,
+    size: f64,
+}
+
+impl MapBiome {
+    /// Distance from the biome to the conditions, scaled so bigger biomes seem closer
+    fn distance(&self, conditions: &BiomeConditions) -> f64 {
+        self.conditions.distance(conditions) / self.size
+    }
+}
+
+/// Finds which biomes are at any point in condition space
+/// 
+/// Condition space is split into a grid, and each cell stores the only biomes which can be blended somewhere in that cell,
+/// so finding the biomes for a point only has to check a few of them
+#[derive(Debug)]
+pub struct BiomeMap {
+    biomes: Vec<MapBiome>,
+    /// Indices of the biomes which can be blended in each cell
+    cells: Vec<Vec<usize>>,
+    /// Used for conditions outside of the grid
+    all_biomes: Vec<usize>,
+}
+
+impl BiomeMap {
+    pub fn new(biomes: &[Biome]) -> Self {
+        let biomes = biomes.iter()
+            .map(|biome| MapBiome {
+                biome_type: biome.biome_type(),
+                conditions: biome.biome_conditions(),
+                size: biome.biome_size(),
+            })
+            .collect::<Vec<_>>();
+
+        Self::from_map_biomes(biomes)
+    }
+
+    fn from_map_biomes(biomes: Vec<MapBiome>) -> Self {
+        let cells = (0..GRID_SIZE.pow(4))
+            .map(|cell| cell_candidates(&biomes, cell_center(cell)))
+            .collect();
+
+        BiomeMap {
+            all_biomes: (0..biomes.len()).collect(),
+            biomes,
+            cells,
+        }
+    }
+
+    /// Gets every biome close enough to the given conditions to be blended
+    /// 
+    /// Weights fall off smoothly from the closest biome, so they change continuously as the conditions change
+    pub fn get_blend(&self, conditions: BiomeConditions) -> BiomeBlend {
+        let candidates = match cell_index(conditions) {
+            Some(cell) => &self.cells[cell],
+            None => &self.all_biomes,
+        };
+
+        let min_distance = candidates.iter()
+            .map(|i| self.biomes[*i].distance(&conditions))
+            .reduce(f64::min)
+            .unwrap();
+
+        let mut blend: Vec<_> = candidates.iter()
+            .filter_map(|i| {
+                let biome = &self.biomes[*i];
+                let t = (biome.distance(&conditions) - min_distance) / BLEND_DISTANCE;
+
+                if t < 1.0 {
+                    // 1 - smoothstep, so the weight has no sharp corner when a biome enters the blend
+                    Some((biome.biome_type, 1.0 - t * t * (3.0 - 2.0 * t)))
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        let total_weight: f64 = blend.iter().map(|(_, weight)| weight).sum();
+        for (_, weight) in blend.iter_mut() {
+            *weight /= total_weight;
+        }
+
+        BiomeBlend(blend)
+    }
+}
+
+/// Gets the index of the cell containing the conditions, or `None` if they are outside of the grid
+fn cell_index(conditions: BiomeConditions) -> Option<usize> {
+    let mut index = 0;
+
+    for value in conditions.to_array() {
+        if !(-1.0..=1.0).contains(&value) {
+            return None;
+        }
+
+        // a value of exactly 1 is put in the last cell
+        let cell = (((value + 1.0) / CELL_SIZE) as usize).min(GRID_SIZE - 1);
+        index = index * GRID_SIZE + cell;
+    }
+
+    Some(index)
+}
+
+fn cell_center(mut index: usize) -> BiomeConditions {
+    let mut center = [0.0; 4];
+
+    for value in center.iter_mut().rev() {
+        *value = -1.0 + ((index % GRID_SIZE) as f64 + 0.5) * CELL_SIZE;
+        index /= GRID_SIZE;
+    }
+
+    BiomeConditions::from_array(center)
+}
+
+/// Gets the indices of every biome which could be blended at some point in the cell with the given center
+/// 
+/// This never leaves out a biome that would be blended, so looking up biomes with the grid is exact
+fn cell_candidates(biomes: &[MapBiome], center: BiomeConditions) -> Vec<usize> {
+    // the closest biome to any point in the cell is at most this far away
+    let max_min_distance = biomes.iter()
+        .map(|biome| (biome.conditions.distance(&center) + CELL_RADIUS) / biome.size)
+        .reduce(f64::min)
+        .unwrap();
+
+    (0..biomes.len())
+        .filter(|i| {
+            let biome = &biomes[*i];
+            let closest_distance = (biome.conditions.distance(&center) - CELL_RADIUS).max(0.0) / biome.size;
+
+            closest_distance < max_min_distance + BLEND_DISTANCE
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+    use strum::IntoEnumIterator;
+
+    use super::*;
+
+    /// Gets the blend by checking every biome, which is what the grid must always match
+    fn brute_force_blend(map: &BiomeMap, conditions: BiomeConditions) -> Vec<(BiomeType, f64)> {
+        let min_distance = map.biomes.iter()
+            .map(|biome| biome.distance(&conditions))
+            .reduce(f64::min)
+            .unwrap();
+
+        let mut blend: Vec<_> = map.biomes.iter()
+            .filter_map(|biome| {
+                let t = (biome.distance(&conditions) - min_distance) / BLEND_DISTANCE;
+                (t < 1.0).then_some((biome.biome_type, 1.0 - t * t * (3.0 - 2.0 * t)))
+            })
+            .collect();
+
+        let total_weight: f64 = blend.iter().map(|(_, weight)| weight).sum();
+        for (_, weight) in blend.iter_mut() {
+            *weight /= total_weight;
+        }
+
+        blend
+    }
+
+    /// Random conditions which go a bit outside the range of the grid
+    fn random_conditions(rng: &mut StdRng) -> BiomeConditions {
+        BiomeConditions::from_array([(); 4].map(|_| rng.gen_range(-1.3..1.3)))
+    }
+
+    fn assert_matches_brute_force(map: &BiomeMap, rng: &mut StdRng) {
+        for _ in 0..20_000 {
+            let conditions = random_conditions(rng);
+
+            let blend = map.get_blend(conditions).iter().collect::<Vec<_>>();
+            let expected = brute_force_blend(map, conditions);
+
+            assert_eq!(blend.len(), expected.len(), "wrong biomes blended at {conditions:?}");
+            for ((biome_type, weight), (expected_type, expected_weight)) in blend.into_iter().zip(expected) {
+                assert_eq!(biome_type, expected_type, "wrong biomes blended at {conditions:?}");
+                assert!((weight - expected_weight).abs() < 1e-9, "wrong weight for {biome_type:?} at {conditions:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn grid_matches_brute_force_for_real_biomes() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let map = BiomeMap::new(&construct_biomes(&mut rng));
+
+        assert_matches_brute_force(&map, &mut rng);
+    }
+
+    #[test]
+    fn grid_matches_brute_force_for_many_biomes() {
+        let mut rng = StdRng::seed_from_u64(2);
+        let biome_types = BiomeType::iter().collect::<Vec<_>>();
+
+        // lots of biomes of different sizes, some of which are outside of the grid
+        let biomes = (0..40)
+            .map(|i| MapBiome {
+                biome_type: biome_types[i % biome_types.len()],
+                conditions: random_conditions(&mut rng),
+                size: rng.gen_range(0.3..2.0),
+            })
+            .collect();
+        let map = BiomeMap::from_map_biomes(biomes);
+
+        assert_matches_brute_force(&map, &mut rng);
+    }
+
+    #[test]
+    fn conditions_on_the_edge_of_the_grid_match_brute_force() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let map = BiomeMap::new(&construct_biomes(&mut rng));
+
+        for value in [-1.0, -1.0 + CELL_SIZE, 0.0, 1.0 - CELL_SIZE, 1.0, 1.0 + 1e-9, -1.0 - 1e-9] {
+            let conditions = BiomeConditions::from_array([value, -value, value * 0.5, value]);
+
+            let blend = map.get_blend(conditions).iter().map(|(biome_type, _)| biome_type).collect::<Vec<_>>();
+            let expected = brute_force_blend(&map, conditions).into_iter().map(|(biome_type, _)| biome_type).collect::<Vec<_>>();
+
+            assert_eq!(blend, expected, "wrong biomes blended at {conditions:?}");
+        }
+    }
+}
\ No newline at end of file
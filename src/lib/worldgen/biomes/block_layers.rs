@@ -15,6 +15,11 @@ pub struct BiomeLayers {
 }
 
 impl BiomeLayers {
+    /// Gets the total thickness of all the top layers, any block deeper than this is the bottom block
+    pub fn thickness(&self) -> u32 {
+        self.layers.iter().map(|layer| layer.thickness).sum()
+    }
+
     /// Gets the block at the given depth, with depth == 0 beingh the surface, depth < 0 being underground, adn depth > 0 being air
     // NOTE: this is O(n) with number of layers, so if there is ever a biome with lots of layers use binary search instead
     pub fn get_block_at_depth(&self, depth: i32) -> BlockType {
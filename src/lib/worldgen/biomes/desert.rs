@@ -0,0 +1,51 @@
+use crate::blocks::BlockType;
+
+use super::*;
+
+#[derive(Debug)]
+pub struct Desert {
+    dune_noise: Fbm<OpenSimplex>,
+}
+
+const LAYERS: BiomeLayers = BiomeLayers {
+    layers: &[
+        BlockLayer {
+            block: BlockType::Sand,
+            thickness: 4,
+        },
+        BlockLayer {
+            block: BlockType::Sandstone,
+            thickness: 8,
+        },
+    ],
+    bottom: BlockType::Stone,
+};
+
+impl BiomeGen for Desert {
+    fn from_seed(seed_rng: &mut StdRng) -> Self {
+        Desert {
+            dune_noise: Fbm::new(seed_rng.next_u32())
+                .set_octaves(2)
+                .set_frequency(0.02),
+        }
+    }
+
+    fn biome_conditions() -> BiomeConditions {
+        BiomeConditions {
+            temperature: 0.6,
+            humidity: -0.6,
+            special_factor: 0.0,
+            biome_height: 0.0,
+        }
+    }
+
+    fn layers() -> BiomeLayers {
+        LAYERS
+    }
+
+    fn get_height(&self, block: BlockPos) -> f64 {
+        // squaring the noise gives dunes with wide flat areas between them
+        let dune = self.dune_noise.get(block.as_noise_point_2d());
+        2.0 + 8.0 * dune * dune
+    }
+}
\ No newline at end of file
@@ -1,5 +1,3 @@
-use noise::{Fbm, MultiFractal};
-
 use crate::blocks::BlockType;
 
 use super::*;
@@ -45,7 +43,22 @@ impl BiomeGen for Grasslands {
         LAYERS
     }
 
-    fn get_height(&self, block: BlockPos, cache: &mut NoiseCache2d) -> i32 {
-        (5.0 * self.height_noise.get(block.as_noise_point_2d())) as i32
+    fn features() -> &'static [FeatureConfig] {
+        &[
+            FeatureConfig {
+                feature: Feature::Tree,
+                chance: 0.004,
+                grows_on: &[BlockType::Grass],
+            },
+            FeatureConfig {
+                feature: Feature::Boulder,
+                chance: 0.0005,
+                grows_on: &[BlockType::Grass],
+            },
+        ]
+    }
+
+    fn get_height(&self, block: BlockPos) -> f64 {
+        5.0 * self.height_noise.get(block.as_noise_point_2d())
     }
 }
\ No newline at end of file
@@ -1,5 +1,4 @@
-use bevy::prelude::Vec4;
-use noise::{OpenSimplex, NoiseFn};
+use noise::{Fbm, MultiFractal, OpenSimplex, NoiseFn};
 use strum::EnumIter;
 use rand::{RngCore, rngs::StdRng};
 use derive_more::Sub;
@@ -8,11 +7,24 @@ use std::fmt::Debug;
 use crate::types::BlockPos;
 
 use super::NoiseCache2d;
+use super::features::{Feature, FeatureConfig};
 
+mod biome_map;
+pub use biome_map::BiomeMap;
 mod block_layers;
-use block_layers::{BiomeLayers, BlockLayer};
+pub use block_layers::{BiomeLayers, BlockLayer};
+mod desert;
+use desert::*;
 mod grasslands;
 use grasslands::*;
+mod mountains;
+use mountains::*;
+mod ocean;
+use ocean::*;
+mod swamp;
+use swamp::*;
+mod tundra;
+use tundra::*;
 
 /// Biome conditions specify spawn conditions of a biome
 /// 
@@ -20,6 +32,9 @@ use grasslands::*;
 /// and the biome which is closest to the generated conditions is selected as the biome for that block
 /// 
 /// All fields are on a range of -1 to 1
+/// 
+/// `biome_height` decides between lowland and highland biomes,
+/// with oceans below -0.3, flat land around 0, and mountainous biomes above 0.2
 #[derive(Debug, Clone, Copy, Sub)]
 pub struct BiomeConditions {
     pub temperature: f64,
@@ -28,36 +43,59 @@ pub struct BiomeConditions {
     pub biome_height: f64,
 }
 
+impl BiomeConditions {
+    pub fn distance(&self, other: &BiomeConditions) -> f64 {
+        let diff = *self - *other;
+
+        (diff.temperature * diff.temperature
+            + diff.humidity * diff.humidity
+            + diff.special_factor * diff.special_factor
+            + diff.biome_height * diff.biome_height).sqrt()
+    }
+
+    pub fn to_array(&self) -> [f64; 4] {
+        [self.temperature, self.humidity, self.special_factor, self.biome_height]
+    }
+
+    pub fn from_array(array: [f64; 4]) -> Self {
+        BiomeConditions {
+            temperature: array[0],
+            humidity: array[1],
+            special_factor: array[2],
+            biome_height: array[3],
+        }
+    }
+}
+
+/// Biomes which are at most this much further away in condition space than the closest biome are blended with it
+const BLEND_DISTANCE: f64 = 0.1;
+
+/// The biomes that make up a column, and how much each one contributes to it
+/// 
+/// Weights are always positive and add up to 1
 #[derive(Debug, Default)]
-pub struct BiomeMap(Vec<(BiomeType, BiomeConditions)>);
-
-impl BiomeMap {
-    // TODO: maybe look into kd-trees if vec performance is not good enough
-    // for low number of biomes, a vec is probably faster though
-    pub fn get_biome(&self, conditions: BiomeConditions) -> BiomeType {
-        let (biome_type, _) = self.0.iter()
-            .map(|(biome_type, this_conditions)| {
-                let diff = *this_conditions - conditions;
-                let dist_squared = diff.temperature * diff.temperature
-                    + diff.humidity * diff.humidity
-                    + diff.special_factor * diff.special_factor
-                    + diff.biome_height * diff.biome_height;
-
-                (biome_type, dist_squared)
-            })
-            .reduce(|a, b| {
-                if a.1 < b.1 {
-                    a
-                } else {
-                    b
-                }
-            }).unwrap();
-        
-        *biome_type
+pub struct BiomeBlend(Vec<(BiomeType, f64)>);
+
+impl BiomeBlend {
+    pub fn iter(&self) -> impl Iterator<Item = (BiomeType, f64)> + '_ {
+        self.0.iter().copied()
     }
 
-    pub fn insert_biome(&mut self, biome: &Biome) {
-        self.0.push((biome.biome_type(), biome.biome_conditions()))
+    /// Picks one of the biomes, where each biome is picked for a fraction of the `selector` range equal to its weight
+    /// 
+    /// `selector` should be in the range 0 to 1
+    pub fn select(&self, selector: f64) -> BiomeType {
+        let mut remaining = selector;
+        for (biome_type, weight) in self.iter() {
+            if remaining < weight {
+                return biome_type;
+            }
+
+            remaining -= weight;
+        }
+
+        // selector may land past the end from floating point error
+        self.0.last().unwrap().0
     }
 }
 
@@ -70,22 +108,31 @@ pub struct BiomeNoiseCache {
     biome_height: NoiseCache2d,
 }
 
+/// Frequency of the biome condition noise, this needs to be low so biomes are big and conditions change slowly enough to blend
+const BIOME_NOISE_FREQUENCY: f64 = 0.002;
+
+fn biome_noise(seed_rng: &mut StdRng) -> Fbm<OpenSimplex> {
+    Fbm::new(seed_rng.next_u32())
+        .set_octaves(3)
+        .set_frequency(BIOME_NOISE_FREQUENCY)
+}
+
 /// Generates random biome conditions
 #[derive(Debug)]
 pub struct BiomeNoiseMap {
-    temperature: OpenSimplex,
-    humidity: OpenSimplex,
-    special_factor: OpenSimplex,
-    biome_height: OpenSimplex,
+    temperature: Fbm<OpenSimplex>,
+    humidity: Fbm<OpenSimplex>,
+    special_factor: Fbm<OpenSimplex>,
+    biome_height: Fbm<OpenSimplex>,
 }
 
 impl BiomeNoiseMap {
     pub fn new(seed_rng: &mut StdRng) -> Self {
         BiomeNoiseMap {
-            temperature: OpenSimplex::new(seed_rng.next_u32()),
-            humidity: OpenSimplex::new(seed_rng.next_u32()),
-            special_factor: OpenSimplex::new(seed_rng.next_u32()),
-            biome_height: OpenSimplex::new(seed_rng.next_u32()),
+            temperature: biome_noise(seed_rng),
+            humidity: biome_noise(seed_rng),
+            special_factor: biome_noise(seed_rng),
+            biome_height: biome_noise(seed_rng),
         }
     }
 
@@ -97,16 +144,71 @@ impl BiomeNoiseMap {
             biome_height: cache.biome_height.get(&self.biome_height, block),
         }
     }
+
+    /// Gets the conditions for a column outside of the chunk the cache is for
+    pub fn get_uncached(&self, block: BlockPos) -> BiomeConditions {
+        let point = block.as_noise_point_2d();
+
+        BiomeConditions {
+            temperature: self.temperature.get(point),
+            humidity: self.humidity.get(point),
+            special_factor: self.special_factor.get(point),
+            biome_height: self.biome_height.get(point),
+        }
+    }
+}
+
+/// How the shape of a biome's terrain is generated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TerrainMode {
+    /// Every column is solid up to the height of the surface
+    Heightmap,
+    /// Each block is solid if its density is positive, which allows overhangs and arches
+    Density,
 }
 
 pub trait BiomeGen: Debug {
     fn from_seed(seed_rng: &mut StdRng) -> Self;
 
     fn biome_conditions() -> BiomeConditions;
+
+    /// How big the biome is compared to other biomes, rare biomes should be smaller than 1
+    /// 
+    /// Distances to the biome in condition space are divided by this
+    fn biome_size() -> f64 {
+        1.0
+    }
+
     fn layers() -> BiomeLayers;
 
+    fn terrain_mode() -> TerrainMode {
+        TerrainMode::Heightmap
+    }
+
+    /// Features which are placed on the surface of this biome
+    fn features() -> &'static [FeatureConfig] {
+        &[]
+    }
+
+    /// Gets the density at the given block position, the block is solid if the density is at least 0
+    /// 
+    /// `height` is the biome's surface height for the column, and the default just uses that height
+    /// Only called if the biome or a biome it is blended with uses [`TerrainMode::Density`]
+    fn get_density(&self, block: BlockPos, height: f64) -> f64 {
+        height - block.y as f64
+    }
+
+    /// The furthest that the surface from [`BiomeGen::get_density`] can be above or below `height`
+    /// 
+    /// This is used to skip generating chunks which are entirely above or below the surface, so it must never be too small
+    fn max_density_offset() -> f64 {
+        0.0
+    }
+
     /// Gets the height of the surface at the given block position
-    fn get_height(&self, block: BlockPos, cache: &mut NoiseCache2d) -> i32;
+    /// 
+    /// This is not rounded so heights can be blended smoothly with neighboring biomes
+    fn get_height(&self, block: BlockPos) -> f64;
 }
 
 macro_rules! register_biome {
@@ -143,6 +245,14 @@ macro_rules! register_biome {
                 }
             }
 
+            pub fn biome_size(&self) -> f64 {
+                match self {
+                    $(
+                        Biome::$biomes(_) => $biomes::biome_size(),
+                    )*
+                }
+            }
+
             pub fn layers(&self) -> BiomeLayers {
                 match self {
                     $(
@@ -151,10 +261,42 @@ macro_rules! register_biome {
                 }
             }
 
-            pub fn get_height(&self, block: BlockPos, cache: &mut NoiseCache2d) -> i32 {
+            pub fn terrain_mode(&self) -> TerrainMode {
+                match self {
+                    $(
+                        Biome::$biomes(_) => $biomes::terrain_mode(),
+                    )*
+                }
+            }
+
+            pub fn features(&self) -> &'static [FeatureConfig] {
+                match self {
+                    $(
+                        Biome::$biomes(_) => $biomes::features(),
+                    )*
+                }
+            }
+
+            pub fn max_density_offset(&self) -> f64 {
+                match self {
+                    $(
+                        Biome::$biomes(_) => $biomes::max_density_offset(),
+                    )*
+                }
+            }
+
+            pub fn get_density(&self, block: BlockPos, height: f64) -> f64 {
+                match self {
+                    $(
+                        Biome::$biomes(biome) => biome.get_density(block, height),
+                    )*
+                }
+            }
+
+            pub fn get_height(&self, block: BlockPos) -> f64 {
                 match self {
                     $(
-                        Biome::$biomes(biome) => biome.get_height(block, cache),
+                        Biome::$biomes(biome) => biome.get_height(block),
                     )*
                 }
             }
@@ -177,4 +319,9 @@ macro_rules! register_biome {
 
 register_biome! {
     Grasslands,
+    Desert,
+    Mountains,
+    Tundra,
+    Swamp,
+    Ocean,
 }
\ No newline at end of file
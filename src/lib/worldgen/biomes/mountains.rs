@@ -0,0 +1,76 @@
+use noise::RidgedMulti;
+
+use crate::blocks::BlockType;
+
+use super::*;
+
+#[derive(Debug)]
+pub struct Mountains {
+    ridge_noise: RidgedMulti<OpenSimplex>,
+    overhang_noise: Fbm<OpenSimplex>,
+}
+
+/// How many blocks the overhang noise can move the surface up or down
+const OVERHANG_STRENGTH: f64 = 12.0;
+/// How much faster the overhang noise changes going up than going sideways,
+/// it has to change faster than the surface rises for the terrain to fold over into overhangs
+const OVERHANG_SQUASH: f64 = 4.0;
+
+const LAYERS: BiomeLayers = BiomeLayers {
+    layers: &[],
+    bottom: BlockType::Stone,
+};
+
+impl BiomeGen for Mountains {
+    fn from_seed(seed_rng: &mut StdRng) -> Self {
+        Mountains {
+            ridge_noise: RidgedMulti::new(seed_rng.next_u32())
+                .set_octaves(5)
+                .set_frequency(0.004),
+            overhang_noise: Fbm::new(seed_rng.next_u32())
+                .set_octaves(3)
+                .set_frequency(0.02),
+        }
+    }
+
+    fn biome_conditions() -> BiomeConditions {
+        BiomeConditions {
+            temperature: 0.0,
+            humidity: 0.0,
+            special_factor: 0.0,
+            biome_height: 0.5,
+        }
+    }
+
+    fn layers() -> BiomeLayers {
+        LAYERS
+    }
+
+    fn features() -> &'static [FeatureConfig] {
+        &[
+            FeatureConfig {
+                feature: Feature::Boulder,
+                chance: 0.003,
+                grows_on: &[BlockType::Stone],
+            },
+        ]
+    }
+
+    fn terrain_mode() -> TerrainMode {
+        TerrainMode::Density
+    }
+
+    fn get_density(&self, block: BlockPos, height: f64) -> f64 {
+        height - block.y as f64 + OVERHANG_STRENGTH * self.overhang_noise.get([block.x as f64, block.y as f64 * OVERHANG_SQUASH, block.z as f64])
+    }
+
+    fn max_density_offset() -> f64 {
+        // the noise is in the range -1 to 1
+        OVERHANG_STRENGTH
+    }
+
+    fn get_height(&self, block: BlockPos) -> f64 {
+        // ridged noise is highest along sharp lines, which become the mountain ridges
+        30.0 + 60.0 * self.ridge_noise.get(block.as_noise_point_2d())
+    }
+}
\ No newline at end of file
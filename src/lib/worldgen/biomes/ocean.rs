@@ -0,0 +1,45 @@
+use crate::blocks::BlockType;
+
+use super::*;
+
+#[derive(Debug)]
+pub struct Ocean {
+    floor_noise: Fbm<OpenSimplex>,
+}
+
+const LAYERS: BiomeLayers = BiomeLayers {
+    layers: &[
+        BlockLayer {
+            block: BlockType::Sand,
+            thickness: 3,
+        },
+    ],
+    bottom: BlockType::Stone,
+};
+
+impl BiomeGen for Ocean {
+    fn from_seed(seed_rng: &mut StdRng) -> Self {
+        Ocean {
+            floor_noise: Fbm::new(seed_rng.next_u32())
+                .set_octaves(4)
+                .set_frequency(0.005),
+        }
+    }
+
+    fn biome_conditions() -> BiomeConditions {
+        BiomeConditions {
+            temperature: 0.0,
+            humidity: 0.0,
+            special_factor: 0.0,
+            biome_height: -0.5,
+        }
+    }
+
+    fn layers() -> BiomeLayers {
+        LAYERS
+    }
+
+    fn get_height(&self, block: BlockPos) -> f64 {
+        -30.0 + 10.0 * self.floor_noise.get(block.as_noise_point_2d())
+    }
+}
\ No newline at end of file
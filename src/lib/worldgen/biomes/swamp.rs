@@ -0,0 +1,64 @@
+use crate::blocks::BlockType;
+
+use super::*;
+
+#[derive(Debug)]
+pub struct Swamp {
+    height_noise: Fbm<OpenSimplex>,
+}
+
+const LAYERS: BiomeLayers = BiomeLayers {
+    layers: &[
+        BlockLayer {
+            block: BlockType::Grass,
+            thickness: 1,
+        },
+        BlockLayer {
+            block: BlockType::Dirt,
+            thickness: 6,
+        },
+    ],
+    bottom: BlockType::Stone,
+};
+
+impl BiomeGen for Swamp {
+    fn from_seed(seed_rng: &mut StdRng) -> Self {
+        Swamp {
+            height_noise: Fbm::new(seed_rng.next_u32())
+                .set_octaves(3)
+                .set_frequency(0.03),
+        }
+    }
+
+    fn biome_conditions() -> BiomeConditions {
+        BiomeConditions {
+            temperature: 0.3,
+            humidity: 0.6,
+            special_factor: 0.0,
+            biome_height: -0.15,
+        }
+    }
+
+    fn biome_size() -> f64 {
+        0.7
+    }
+
+    fn layers() -> BiomeLayers {
+        LAYERS
+    }
+
+    fn features() -> &'static [FeatureConfig] {
+        &[
+            FeatureConfig {
+                feature: Feature::Tree,
+                chance: 0.01,
+                grows_on: &[BlockType::Grass],
+            },
+        ]
+    }
+
+    fn get_height(&self, block: BlockPos) -> f64 {
+        // swamps are flat and sit slightly below the surrounding land
+        -3.0 + 2.0 * self.height_noise.get(block.as_noise_point_2d())
+    }
+}
\ No newline at end of file
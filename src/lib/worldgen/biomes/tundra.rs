@@ -0,0 +1,59 @@
+use crate::blocks::BlockType;
+
+use super::*;
+
+#[derive(Debug)]
+pub struct Tundra {
+    height_noise: Fbm<OpenSimplex>,
+}
+
+const LAYERS: BiomeLayers = BiomeLayers {
+    layers: &[
+        BlockLayer {
+            block: BlockType::Snow,
+            thickness: 1,
+        },
+        BlockLayer {
+            block: BlockType::Dirt,
+            thickness: 3,
+        },
+    ],
+    bottom: BlockType::Stone,
+};
+
+impl BiomeGen for Tundra {
+    fn from_seed(seed_rng: &mut StdRng) -> Self {
+        Tundra {
+            height_noise: Fbm::new(seed_rng.next_u32())
+                .set_octaves(4)
+                .set_frequency(0.008),
+        }
+    }
+
+    fn biome_conditions() -> BiomeConditions {
+        BiomeConditions {
+            temperature: -0.6,
+            humidity: 0.0,
+            special_factor: 0.0,
+            biome_height: 0.25,
+        }
+    }
+
+    fn layers() -> BiomeLayers {
+        LAYERS
+    }
+
+    fn features() -> &'static [FeatureConfig] {
+        &[
+            FeatureConfig {
+                feature: Feature::Boulder,
+                chance: 0.002,
+                grows_on: &[BlockType::Snow],
+            },
+        ]
+    }
+
+    fn get_height(&self, block: BlockPos) -> f64 {
+        12.0 + 8.0 * self.height_noise.get(block.as_noise_point_2d())
+    }
+}
\ No newline at end of file
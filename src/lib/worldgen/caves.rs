@@ -0,0 +1,161 @@
+//! Carves caves out of the terrain after it has been shaped
+//! 
+//! There are 3 kinds of caves:
+//! - cheese caves are big caverns where 3d noise is above a threshold
+//! - spaghetti caves are long thin tunnels where 2 3d noises are both close to 0
+//! - worm caves are tunnels made by a random walk, and can cross into other chunks
+//! 
+//! Every cave only depends on the seed and block position, so chunks can be generated in any order.
+//! Worms are simulated from their starting chunk every time a chunk they may reach is generated,
+//! and each chunk only carves the part of the worm inside of it.
+
+use std::f64::consts::TAU;
+
+use bevy::math::{DVec3, IVec3};
+use noise::{Fbm, MultiFractal, NoiseFn, OpenSimplex};
+use rand::{Rng, RngCore, SeedableRng};
+use rand::rngs::StdRng;
+
+use crate::blocks::BlockStorage;
+use crate::world::CHUNK_SIZE;
+use crate::types::*;
+
+use super::Column;
+use super::random::{position_hash, position_random};
+
+/// Cheese caves are only carved this far below the surface, so the surface doesn't get full of holes
+pub const CHEESE_MIN_DEPTH: i32 = 8;
+const CHEESE_THRESHOLD: f64 = 0.5;
+
+/// How close to 0 both spaghetti noises must be for a block to be carved, this controls the width of the tunnels
+const SPAGHETTI_WIDTH: f64 = 0.04;
+
+const WORM_SALT: u64 = 1;
+/// Chance that a chunk is the start of a worm
+const WORM_CHANCE: f64 = 0.15;
+const WORM_MIN_LENGTH: u32 = 20;
+const WORM_MAX_LENGTH: u32 = 60;
+const WORM_MIN_RADIUS: f64 = 1.5;
+const WORM_MAX_RADIUS: f64 = 3.5;
+/// How many chunks away from its starting chunk a worm can reach
+/// 
+/// Worms move 1 block per step, so this must be big enough to fit `WORM_MAX_LENGTH + WORM_MAX_RADIUS` blocks
+const WORM_CHUNK_RANGE: i32 = 2;
+/// Worms only start in chunks below this chunk y, so they don't start in the sky
+const WORM_MAX_START_CHUNK_Y: i32 = 0;
+
+#[derive(Debug)]
+pub struct CaveGen {
+    seed: u64,
+    cheese_noise: Fbm<OpenSimplex>,
+    spaghetti_noise: [Fbm<OpenSimplex>; 2],
+}
+
+impl CaveGen {
+    pub fn new(seed: u64, seed_rng: &mut StdRng) -> Self {
+        let mut spaghetti_noise = || Fbm::new(seed_rng.next_u32())
+            .set_octaves(2)
+            .set_frequency(0.01);
+
+        let spaghetti_noise = [spaghetti_noise(), spaghetti_noise()];
+
+        CaveGen {
+            seed,
+            cheese_noise: Fbm::new(seed_rng.next_u32())
+                .set_octaves(3)
+                .set_frequency(0.015),
+            spaghetti_noise,
+        }
+    }
+
+    /// Returns true if a noise cave carves out the block at the given position
+    /// 
+    /// `depth` is the depth of the block as given to [`BiomeLayers::get_block_at_depth`](super::biomes::BiomeLayers::get_block_at_depth)
+    pub fn is_carved(&self, block: BlockPos, depth: i32) -> bool {
+        if depth > 0 {
+            // already air
+            return false;
+        }
+
+        let point = block.as_noise_point_3d();
+
+        if depth <= -CHEESE_MIN_DEPTH && self.cheese_noise.get(point) > CHEESE_THRESHOLD {
+            return true;
+        }
+
+        self.spaghetti_noise.iter()
+            .all(|noise| noise.get(point).abs() < SPAGHETTI_WIDTH)
+    }
+
+    /// Carves every worm that reaches into the given chunk, `columns` are the chunk's columns indexed by `x * CHUNK_SIZE + z`
+    pub(super) fn carve_worms(&self, chunk_pos: ChunkPos, blocks: &mut BlockStorage, columns: &[Column]) {
+        let chunk_min = BlockPos::from(chunk_pos).as_dvec3();
+        let chunk_max = chunk_min + DVec3::splat(CHUNK_SIZE as f64);
+
+        for x in -WORM_CHUNK_RANGE..=WORM_CHUNK_RANGE {
+            for y in -WORM_CHUNK_RANGE..=WORM_CHUNK_RANGE {
+                for z in -WORM_CHUNK_RANGE..=WORM_CHUNK_RANGE {
+                    let start_chunk = chunk_pos + ChunkPos::new(x, y, z);
+                    if start_chunk.y >= WORM_MAX_START_CHUNK_Y
+                        || position_random(self.seed, *start_chunk, WORM_SALT) >= WORM_CHANCE {
+                        continue;
+                    }
+
+                    self.carve_worm(start_chunk, blocks, columns, chunk_pos, chunk_min, chunk_max);
+                }
+            }
+        }
+    }
+
+    /// Simulates the worm starting in `start_chunk`, and carves the parts of it inside `chunk_pos`
+    /// 
+    /// The worm must always be simulated the same way no matter which chunk is being carved
+    fn carve_worm(&self, start_chunk: ChunkPos, blocks: &mut BlockStorage, columns: &[Column], chunk_pos: ChunkPos, chunk_min: DVec3, chunk_max: DVec3) {
+        // the salt is different from the chance check so the worm's path doesn't depend on the chance
+        let mut rng = StdRng::seed_from_u64(position_hash(self.seed, *start_chunk, WORM_SALT + 1));
+
+        let start_offset = DVec3::new(rng.gen(), rng.gen(), rng.gen()) * CHUNK_SIZE as f64;
+        let mut position = BlockPos::from(start_chunk).as_dvec3() + start_offset;
+        let mut yaw = rng.gen_range(0.0..TAU);
+        let mut pitch: f64 = rng.gen_range(-0.5..0.5);
+        let length = rng.gen_range(WORM_MIN_LENGTH..=WORM_MAX_LENGTH);
+        let radius = rng.gen_range(WORM_MIN_RADIUS..WORM_MAX_RADIUS);
+
+        for _ in 0..length {
+            position += DVec3::new(yaw.cos() * pitch.cos(), pitch.sin(), yaw.sin() * pitch.cos());
+            yaw += rng.gen_range(-0.3..0.3);
+            // pull the pitch back towards flat so worms don't dive straight down
+            pitch = (0.9 * (pitch + rng.gen_range(-0.2..0.2))).clamp(-0.8, 0.8);
+
+            let in_chunk = (position + radius).cmpge(chunk_min).all() && (position - radius).cmplt(chunk_max).all();
+            if in_chunk {
+                carve_sphere(blocks, columns, chunk_pos, position, radius);
+            }
+        }
+    }
+}
+
+/// Carves every block in the chunk within `radius` of `center`, water is never carved
+/// 
+/// Carved blocks are filled with water if they are in a column under water, the same as noise caves
+fn carve_sphere(blocks: &mut BlockStorage, columns: &[Column], chunk_pos: ChunkPos, center: DVec3, radius: f64) {
+    let chunk_min = BlockPos::from(chunk_pos);
+    let min = ((center - radius).floor().as_ivec3() - *chunk_min).max(IVec3::ZERO);
+    let max = ((center + radius).ceil().as_ivec3() - *chunk_min).min(IVec3::splat(CHUNK_SIZE as i32 - 1));
+
+    for x in min.x..=max.x {
+        for y in min.y..=max.y {
+            for z in min.z..=max.z {
+                let local_block_pos = BlockPos::new(x, y, z);
+                // distance to the center of the block
+                let block_center = (*chunk_min + *local_block_pos).as_dvec3() + 0.5;
+
+                if block_center.distance_squared(center) <= radius * radius && !blocks.get(local_block_pos).is_replaceable() {
+                    let column = &columns[x as usize * CHUNK_SIZE + z as usize];
+                    let carved_block = column.carved_block(chunk_min.y + y);
+                    blocks.new_block(local_block_pos, carved_block);
+                }
+            }
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,81 @@
+//! Caches the columns of each chunk footprint
+//! 
+//! Every chunk stacked on the same footprint has the same columns, and decorating a chunk needs the columns around it too,
+//! so without this each column would be generated again for every chunk above, below and next to it.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+use rustc_hash::FxHashMap;
+
+use crate::types::*;
+use super::Column;
+
+/// Most footprints kept in the cache, the oldest footprint is removed once there are more than this
+/// 
+/// This fits every footprint within a render distance of 10 chunks, along with the ring of footprints around them used for decorating
+const MAX_CACHED_FOOTPRINTS: usize = 512;
+
+/// Every column of a chunk's footprint, along with bounds of their surfaces
+pub(super) struct FootprintColumns {
+    /// Indexed by `x * CHUNK_SIZE + z`
+    pub columns: Vec<Column>,
+    /// Lowest `min_surface` of any of the columns
+    pub min_surface: i32,
+    /// Highest `max_surface` of any of the columns
+    pub max_surface: i32,
+}
+
+#[derive(Default)]
+pub(super) struct ColumnCache {
+    inner: Mutex<ColumnCacheInner>,
+}
+
+#[derive(Default)]
+struct ColumnCacheInner {
+    footprints: FxHashMap<(i32, i32), Arc<FootprintColumns>>,
+    /// Order the footprints were added in, so the oldest one can be removed first
+    insertion_order: VecDeque<(i32, i32)>,
+}
+
+impl fmt::Debug for ColumnCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ColumnCache")
+            .field("cached_footprints", &self.inner.lock().footprints.len())
+            .finish()
+    }
+}
+
+impl ColumnCache {
+    /// Gets the columns of the footprint `chunk_pos` is in, using `generate` to generate them if they aren't cached
+    pub fn get_or_generate(&self, chunk_pos: ChunkPos, generate: impl FnOnce() -> Vec<Column>) -> Arc<FootprintColumns> {
+        let key = (chunk_pos.x, chunk_pos.z);
+
+        if let Some(footprint) = self.inner.lock().footprints.get(&key) {
+            return footprint.clone();
+        }
+
+        // the lock isn't held while generating so other threads can still use the cache,
+        // 2 threads might generate the same footprint at once but they will get the same columns
+        let columns = generate();
+        let footprint = Arc::new(FootprintColumns {
+            min_surface: columns.iter().map(|column| column.min_surface).min().unwrap(),
+            max_surface: columns.iter().map(|column| column.max_surface).max().unwrap(),
+            columns,
+        });
+
+        let mut inner = self.inner.lock();
+        if inner.footprints.insert(key, footprint.clone()).is_none() {
+            inner.insertion_order.push_back(key);
+
+            if inner.insertion_order.len() > MAX_CACHED_FOOTPRINTS {
+                let oldest = inner.insertion_order.pop_front().unwrap();
+                inner.footprints.remove(&oldest);
+            }
+        }
+
+        footprint
+    }
+}
\ No newline at end of file
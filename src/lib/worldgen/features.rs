@@ -0,0 +1,98 @@
+//! Multi block features, such as trees and boulders, which are placed after a chunk's terrain is shaped
+//! 
+//! Features may extend into neighboring chunks, so when a chunk is decorated the features rooted in the columns
+//! around it are placed as well, and only their blocks inside the chunk are kept.
+//! Features only ever replace air.
+//! 
+//! The other way to do this would be to keep the blocks a feature places in chunks which aren't generated yet
+//! as pending edits, and apply them when those chunks are generated. That would need chunks which are already generated
+//! to be edited again, and the result would depend on the order chunks are generated in.
+//! Working out the features around every chunk again costs more, but each chunk only depends on the seed,
+//! and the columns around it are cached so only the features themselves are worked out again.
+
+use std::f64::consts::TAU;
+
+use bevy::math::{DVec3, IVec3};
+use rand::Rng;
+use rand::rngs::StdRng;
+
+use crate::blocks::BlockType;
+use crate::types::*;
+
+/// Furthest any block of a feature can be from its root along the x and z axis
+pub const MAX_FEATURE_RADIUS: i32 = 3;
+/// Furthest any block of a feature can be above its root
+pub const MAX_FEATURE_HEIGHT: i32 = 9;
+/// Furthest any block of a feature can be below its root
+pub const MAX_FEATURE_DEPTH: i32 = 3;
+
+/// A kind of feature
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Feature {
+    Tree,
+    Boulder,
+}
+
+impl Feature {
+    /// Adds the blocks of this feature rooted on top of the surface block at `root` to `edits`
+    pub fn place(&self, root: BlockPos, rng: &mut StdRng, edits: &mut Vec<(BlockPos, BlockType)>) {
+        match self {
+            Feature::Tree => place_tree(root, rng, edits),
+            Feature::Boulder => place_boulder(root, rng, edits),
+        }
+    }
+}
+
+/// Describes how often a feature spawns in a biome
+pub struct FeatureConfig {
+    pub feature: Feature,
+    /// Chance that any given surface block has this feature on it
+    pub chance: f64,
+    /// Surface blocks the feature can be placed on
+    pub grows_on: &'static [BlockType],
+}
+
+fn place_tree(root: BlockPos, rng: &mut StdRng, edits: &mut Vec<(BlockPos, BlockType)>) {
+    let trunk_height = rng.gen_range(4..=6);
+
+    // the trunk goes first so the leaves don't replace it
+    for y in 1..=trunk_height {
+        edits.push((BlockPos(*root + IVec3::new(0, y, 0)), BlockType::Log));
+    }
+
+    let leaves_center = *root + IVec3::new(0, trunk_height, 0);
+    let radius = rng.gen_range(2.0..2.8f64);
+    let size = radius.ceil() as i32;
+
+    for x in -size..=size {
+        for y in -size..=size {
+            for z in -size..=size {
+                let offset = IVec3::new(x, y, z);
+                if offset.as_dvec3().length() <= radius {
+                    edits.push((BlockPos(leaves_center + offset), BlockType::Leaves));
+                }
+            }
+        }
+    }
+}
+
+fn place_boulder(root: BlockPos, rng: &mut StdRng, edits: &mut Vec<(BlockPos, BlockType)>) {
+    let radius = rng.gen_range(1.5..2.5f64);
+    let size = radius.ceil() as i32;
+    // squash the boulder a random amount in a random direction so they aren't all perfect spheres
+    let angle = rng.gen_range(0.0..TAU);
+    let squash = DVec3::new(angle.cos(), 0.0, angle.sin()) * rng.gen_range(0.0..0.4);
+
+    for x in -size..=size {
+        for y in -size..=size {
+            for z in -size..=size {
+                let offset = IVec3::new(x, y, z).as_dvec3();
+                let stretched = offset + squash * offset.dot(squash);
+
+                if stretched.length() <= radius {
+                    edits.push((BlockPos(*root + IVec3::new(x, y, z)), BlockType::Stone));
+                }
+            }
+        }
+    }
+}
\ No newline at end of file
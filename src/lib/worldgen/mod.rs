@@ -1,5 +1,6 @@
-use std::sync::OnceLock;
+use std::sync::Arc;
 
+use bevy::prelude::{IVec3, Resource, Deref};
 use rand::SeedableRng;
 use rand::rngs::StdRng;
 
@@ -9,77 +10,950 @@ use crate::types::*;
 
 mod biomes;
 use biomes::Biome;
+mod caves;
+use caves::{CaveGen, CHEESE_MIN_DEPTH};
+mod column_cache;
+use column_cache::{ColumnCache, FootprintColumns};
+mod features;
+use features::{MAX_FEATURE_RADIUS, MAX_FEATURE_HEIGHT, MAX_FEATURE_DEPTH};
 mod noise_cache_2d;
 use noise_cache_2d::NoiseCache2d;
+mod ores;
+use ores::OreGen;
+mod random;
+use random::{position_hash, position_random};
+mod settings;
+pub use settings::{WorldgenSettings, WorldgenConfigError, DEFAULT_SEED};
+mod water;
+use water::{WaterGen, Lake};
+pub use water::SEA_LEVEL;
 
-use self::biomes::{BiomeMap, BiomeNoiseMap, BiomeNoiseCache, BiomeConditions};
+use self::biomes::{BiomeMap, BiomeNoiseMap, BiomeNoiseCache, BiomeLayers, BlockLayer, TerrainMode};
+pub use self::biomes::{BiomeConditions, BiomeType};
 
-static WORLDGEN: OnceLock<Worldgen> = OnceLock::new();
+/// The terrain of a single column of blocks
+struct Column {
+    height: i32,
+    /// Every block at or below this height is solid, ignoring caves
+    min_surface: i32,
+    /// Every block above this height is air or water
+    max_surface: i32,
+    /// Biome whose layers and features are used for this column
+    biome: BiomeType,
+    layers: BiomeLayers,
+    /// Air at or below this height is filled with water
+    water_level: i32,
+    /// Every biome in the column with its weight and height,
+    /// only present if one of the biomes uses [`TerrainMode::Density`]
+    density_biomes: Option<Vec<(BiomeType, f64, f64)>>,
+}
+
+impl Column {
+    /// Gets the block at the given depth in the column, after caves are carved and water is filled in
+    fn get_block(&self, block: BlockPos, depth: i32, caves: Option<&CaveGen>) -> BlockType {
+        if caves.is_some_and(|caves| caves.is_carved(block, depth)) {
+            self.carved_block(block.y)
+        } else if depth > 0 && block.y <= self.water_level {
+            BlockType::Water
+        } else {
+            self.layers.get_block_at_depth(depth)
+        }
+    }
+
+    /// Gets the block a cave leaves behind at the given height in the column
+    /// 
+    /// Caves under the sea, rivers and lakes are flooded up to the water level, so they don't leave air under the water.
+    /// Caves under dry land stay dry even below the water level.
+    fn carved_block(&self, y: i32) -> BlockType {
+        if self.water_level > self.max_surface && y <= self.water_level {
+            BlockType::Water
+        } else {
+            BlockType::Air
+        }
+    }
+}
+
+/// The terrain of a single column without caves or features, used to preview worldgen
+#[derive(Debug, Clone, Copy)]
+pub struct ColumnSample {
+    /// Height of the highest solid block
+    pub height: i32,
+    /// Air at or below this height is filled with water
+    pub water_level: i32,
+    pub biome: BiomeType,
+    /// Highest block of the column, which is water if the column is under water
+    pub surface_block: BlockType,
+    pub conditions: BiomeConditions,
+}
+
+/// If every block of a chunk with the given top is deep underground in all columns, returns the block they are all made of
+fn uniform_block(columns: &[Column], chunk_top: i32) -> Option<BlockType> {
+    let block_type = columns[0].layers.bottom;
+
+    let is_uniform = columns.iter().all(|column| {
+        // cheese caves only carve blocks a certain depth underground, so the whole chunk must be below that depth too
+        let deep_depth = (column.layers.thickness() as i32).max(CHEESE_MIN_DEPTH);
+
+        column.layers.bottom == block_type && chunk_top <= column.min_surface - deep_depth
+    });
+
+    is_uniform.then_some(block_type)
+}
+
+/// Layers used for columns near the sea level, instead of the biome's layers
+const BEACH_LAYERS: BiomeLayers = BiomeLayers {
+    layers: &[
+        BlockLayer {
+            block: BlockType::Sand,
+            thickness: 3,
+        },
+        BlockLayer {
+            block: BlockType::Sandstone,
+            thickness: 2,
+        },
+    ],
+    bottom: BlockType::Stone,
+};
+
+/// Salt for picking which biome's layers a column uses
+const LAYERS_SALT: u64 = 0;
+/// Salt of the first feature of a biome, each feature uses 2 salts starting at its index times 2 added to this
+const FEATURE_SALT: u64 = 32;
+
+/// The worldgen used by the world, which is shared with chunk generation tasks
+#[derive(Debug, Clone, Resource, Deref)]
+pub struct SharedWorldgen(pub Arc<Worldgen>);
 
 #[derive(Debug)]
 pub struct Worldgen {
     seed: u64,
+    settings: WorldgenSettings,
     biomes: Vec<Biome>,
     biome_map: BiomeMap,
     biome_noise: BiomeNoiseMap,
+    caves: CaveGen,
+    ores: OreGen,
+    water: WaterGen,
+    columns: ColumnCache,
 }
 
 impl Worldgen {
-    pub fn init(seed: u64) {
-        WORLDGEN.set(Self::new(seed)).expect("worldgen already initialized");
-    }
-
-    pub fn get() -> &'static Worldgen {
-        WORLDGEN.get().expect("worldgen not initialized")
-    }
+    pub fn new(settings: WorldgenSettings) -> Self {
+        let seed = settings.seed;
 
-    fn new(seed: u64) -> Self {
         // used to generate the seeds for all noise maps
         let mut seed_rng = StdRng::seed_from_u64(seed);
 
         let biomes = biomes::construct_biomes(&mut seed_rng);
 
-        let mut biome_map = BiomeMap::default();
-        for biome in biomes.iter() {
-            biome_map.insert_biome(biome);
-        }
+        let biome_map = BiomeMap::new(&biomes);
 
         Worldgen {
             seed,
+            settings,
             biomes,
             biome_map,
             biome_noise: BiomeNoiseMap::new(&mut seed_rng),
+            caves: CaveGen::new(seed, &mut seed_rng),
+            ores: OreGen::new(seed),
+            water: WaterGen::new(seed, &mut seed_rng),
+            columns: ColumnCache::default(),
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn settings(&self) -> &WorldgenSettings {
+        &self.settings
+    }
+
+    /// Gets the terrain of the column at the given x and z, without generating any chunks
+    pub fn sample_column(&self, x: i32, z: i32) -> ColumnSample {
+        self.sample_column_cached(x, z, &mut Box::new(BiomeNoiseCache::default()))
+    }
+
+    /// Gets the terrain of every column in the area starting at the given x and z, in rows along the x axis
+    /// 
+    /// This is faster than sampling each column on its own, since noise is reused for columns in the same chunk
+    pub fn sample_area(&self, x: i32, z: i32, width: u32, depth: u32) -> Vec<ColumnSample> {
+        let size = CHUNK_SIZE as i32;
+        let (max_x, max_z) = (x + width as i32, z + depth as i32);
+
+        let mut samples = vec![None; (width * depth) as usize];
+
+        for chunk_x in x.div_euclid(size)..=(max_x - 1).div_euclid(size) {
+            for chunk_z in z.div_euclid(size)..=(max_z - 1).div_euclid(size) {
+                // the noise cache only works for columns within a single chunk
+                let mut biome_noise_cache = Box::new(BiomeNoiseCache::default());
+
+                for column_z in (chunk_z * size).max(z)..((chunk_z + 1) * size).min(max_z) {
+                    for column_x in (chunk_x * size).max(x)..((chunk_x + 1) * size).min(max_x) {
+                        let index = (column_z - z) as usize * width as usize + (column_x - x) as usize;
+                        samples[index] = Some(self.sample_column_cached(column_x, column_z, &mut biome_noise_cache));
+                    }
+                }
+            }
+        }
+
+        samples.into_iter()
+            .map(|sample| sample.unwrap())
+            .collect()
+    }
+
+    fn sample_column_cached(&self, x: i32, z: i32, biome_noise_cache: &mut BiomeNoiseCache) -> ColumnSample {
+        let block = BlockPos::new(x, 0, z);
+        let column = self.generate_column(block, biome_noise_cache);
+        let height = self.surface_height(block, &column);
+
+        let surface_block = if column.water_level > height {
+            BlockType::Water
+        } else {
+            column.layers.get_block_at_depth(0)
+        };
+
+        ColumnSample {
+            height,
+            water_level: column.water_level,
+            biome: column.biome,
+            surface_block,
+            conditions: self.biome_noise.get_uncached(block),
+        }
+    }
+
+    /// Gets the height of the highest solid block of the column, ignoring caves
+    fn surface_height(&self, column_pos: BlockPos, column: &Column) -> i32 {
+        match &column.density_biomes {
+            // every block at or below the min surface is solid, so the highest solid block is found by searching down to it
+            Some(density_biomes) => (column.min_surface + 1..=column.max_surface).rev()
+                .find(|y| self.get_density(BlockPos::new(column_pos.x, *y, column_pos.z), density_biomes) >= 0.0)
+                .unwrap_or(column.min_surface),
+            None => column.height,
+        }
+    }
+
+    /// Returns the cave generator if caves are enabled
+    fn enabled_caves(&self) -> Option<&CaveGen> {
+        self.settings.caves.then_some(&self.caves)
+    }
+
+    /// Gets the height of the terrain before water shapes it, by blending the heights of all the biomes near the column
+    fn get_blended_height(&self, block: BlockPos, biome_conditions: BiomeConditions) -> f64 {
+        self.biome_map.get_blend(biome_conditions).iter()
+            .map(|(biome_type, weight)| weight * self.biomes[biome_type as usize].get_height(block))
+            .sum()
+    }
+
+    /// Gets the water level of the lake, which only depends on the lake so it is the same for every column in it
+    /// 
+    /// Also returns how much the lake is in lowlands, which scales its depth.
+    /// Returns `None` if the lake is in highlands or would not be above the sea level.
+    fn lake_water_level(&self, lake: &Lake) -> Option<(i32, f64)> {
+        let center_conditions = self.biome_noise.get_uncached(lake.center);
+
+        let lowland = WaterGen::lowland_factor(center_conditions.biome_height);
+        if lowland == 0.0 {
+            return None;
+        }
+
+        let center_height = self.get_blended_height(lake.center, center_conditions);
+
+        // the water must stay below the lowest point of the rim, or it would spill out of the lake
+        let rim_height = lake.rim_points()
+            .map(|rim_point| self.get_blended_height(rim_point, self.biome_noise.get_uncached(rim_point)))
+            .fold(f64::INFINITY, f64::min);
+
+        let water_level = center_height.min(rim_height).floor() as i32 - 1;
+
+        (water_level > SEA_LEVEL).then_some((water_level, lowland))
+    }
+
+    /// Blends the height and layers of all biomes near the given column, and then carves rivers and lakes into it
+    fn generate_column(&self, block: BlockPos, biome_noise_cache: &mut BiomeNoiseCache) -> Column {
+        let biome_conditions = self.biome_noise.get(block, biome_noise_cache);
+        let blend = self.biome_map.get_blend(biome_conditions);
+
+        let biome_heights: Vec<_> = blend.iter()
+            .map(|(biome_type, weight)| (biome_type, weight, self.biomes[biome_type as usize].get_height(block)))
+            .collect();
+
+        let blended_height: f64 = biome_heights.iter()
+            .map(|(_, weight, height)| weight * height)
+            .sum();
+
+        let mut height = self.water.carve_river(block, blended_height, biome_conditions.biome_height);
+
+        let mut water_level = SEA_LEVEL;
+
+        if let Some(lake) = self.water.lake(block) {
+            let lake_depth = self.water.lake_depth(&lake, block);
+
+            if (lake_depth > 0.0 || lake.rim_contains(block)) && let Some((lake_level, lowland)) = self.lake_water_level(&lake) {
+                if lake_depth > 0.0 {
+                    water_level = lake_level;
+                    // at the edge of the lake the depth is 0, so the terrain there is flattened to the water level
+                    height = height.min(water_level as f64) - lake_depth * lowland;
+                } else {
+                    // the shore is raised up to the water level where the terrain dips below it,
+                    // so the water is never next to a column lower than it
+                    height = height.max(lake_level as f64);
+                }
+            }
+        }
+
+        let uses_density = biome_heights.iter()
+            .any(|(biome_type, _, _)| self.biomes[*biome_type as usize].terrain_mode() == TerrainMode::Density);
+
+        // layers can't be averaged, so each column picks a biome's layers at random based on the weights,
+        // which dithers the surface blocks along the border
+        let layers_biome = blend.select(position_random(self.seed, IVec3::new(block.x, 0, block.z), LAYERS_SALT));
+
+        let height = height.floor() as i32;
+
+        // lakes have their own shore, so only the sea gets beaches
+        let layers = if water_level == SEA_LEVEL && water::is_beach(height) {
+            BEACH_LAYERS
+        } else {
+            self.biomes[layers_biome as usize].layers()
+        };
+
+        let (min_surface, max_surface) = if uses_density {
+            // density columns ignore rivers and lakes, and the density can only move the surface so far from the blended height
+            let max_offset: f64 = biome_heights.iter()
+                .map(|(biome_type, weight, _)| weight * self.biomes[*biome_type as usize].max_density_offset())
+                .sum();
+
+            ((blended_height - max_offset).floor() as i32, (blended_height + max_offset).floor() as i32)
+        } else {
+            (height, height)
+        };
+
+        Column {
+            height,
+            min_surface,
+            max_surface,
+            biome: layers_biome,
+            layers,
+            water_level,
+            density_biomes: uses_density.then_some(biome_heights),
         }
     }
 
-    /// Gets the biome for the given conditions
-    fn get_biome(&self, biome_conditions: BiomeConditions) -> &Biome {
-        &self.biomes[self.biome_map.get_biome(biome_conditions) as usize]
+    /// Gets the blended density of all the biomes in a column
+    fn get_density(&self, block: BlockPos, density_biomes: &[(BiomeType, f64, f64)]) -> f64 {
+        density_biomes.iter()
+            .map(|(biome_type, weight, height)| weight * self.biomes[*biome_type as usize].get_density(block, *height))
+            .sum()
     }
 
+    /// Generates a chunk in 2 stages, first the terrain is shaped, and then features are placed on it
     pub fn generate_chunk(&self, chunk_pos: ChunkPos) -> ChunkData {
-        let mut blocks = BlockStorage::default();
+        self.generate_chunk_inner(chunk_pos, true)
+    }
 
-        let mut biome_noise_cache = Box::new(BiomeNoiseCache::default());
-        let mut height_noise = Box::new(NoiseCache2d::default());
+    /// Generates a chunk without the fast paths for chunks which are all sky or all underground,
+    /// so every column is shaped block by block and every chunk is decorated
+    /// 
+    /// This gives the same blocks as [`Worldgen::generate_chunk`], it is only used to measure how much the fast paths help
+    #[doc(hidden)]
+    pub fn generate_chunk_per_block(&self, chunk_pos: ChunkPos) -> ChunkData {
+        self.generate_chunk_inner(chunk_pos, false)
+    }
 
-        for x in 0..CHUNK_SIZE {
-            for y in 0..CHUNK_SIZE {
+    fn generate_chunk_inner(&self, chunk_pos: ChunkPos, fast_paths: bool) -> ChunkData {
+        let mut blocks = self.shape_chunk(chunk_pos, fast_paths);
+
+        // decorating has to look at all the columns around the chunk, so it is skipped when no feature can reach the chunk
+        if self.settings.features && (!fast_paths || self.features_can_reach(chunk_pos)) {
+            self.decorate_chunk(chunk_pos, &mut blocks);
+        }
+
+        blocks.into()
+    }
+
+    /// Stage 1: generates the terrain of the chunk, and carves caves and places ores in it
+    fn shape_chunk(&self, chunk_pos: ChunkPos, fast_paths: bool) -> BlockStorage {
+        let footprint = self.footprint_columns(chunk_pos);
+        let columns = &footprint.columns;
+
+        let chunk_bottom = BlockPos::from(chunk_pos).y;
+        let chunk_top = chunk_bottom + CHUNK_SIZE as i32 - 1;
+
+        // the chunk is above every column's terrain and water, so it is all air and caves and ores have nothing to change
+        if fast_paths && columns.iter().all(|column| chunk_bottom > column.max_surface.max(column.water_level)) {
+            return BlockStorage::default();
+        }
+
+        let uniform_block = if fast_paths {
+            uniform_block(columns, chunk_top)
+        } else {
+            None
+        };
+
+        let mut blocks = match uniform_block {
+            Some(block_type) => self.shape_uniform_chunk(chunk_pos, block_type, columns),
+            None => {
+                let mut blocks = BlockStorage::default();
+                for (i, column) in columns.iter().enumerate() {
+                    let local_column_pos = BlockPos::new((i / CHUNK_SIZE) as i32, 0, (i % CHUNK_SIZE) as i32);
+                    self.shape_column(chunk_pos, local_column_pos, column, &mut blocks);
+                }
+                blocks
+            },
+        };
+
+        if self.settings.caves {
+            self.caves.carve_worms(chunk_pos, &mut blocks, columns);
+        }
+
+        if self.settings.ores {
+            self.ores.place_ores(chunk_pos, &mut blocks);
+        }
+
+        blocks
+    }
+
+    /// Gets every column in the footprint of the chunk, which are only generated the first time they are needed
+    fn footprint_columns(&self, chunk_pos: ChunkPos) -> Arc<FootprintColumns> {
+        self.columns.get_or_generate(chunk_pos, || {
+            let mut biome_noise_cache = Box::new(BiomeNoiseCache::default());
+
+            let mut columns = Vec::with_capacity(CHUNK_SIZE * CHUNK_SIZE);
+            for x in 0..CHUNK_SIZE {
                 for z in 0..CHUNK_SIZE {
-                    let local_block_pos = BlockPos::new(x as i32, y as i32, z as i32);
-                    let block_pos = BlockPos::from(chunk_pos) + local_block_pos;
+                    let column_pos = BlockPos::from(chunk_pos) + BlockPos::new(x as i32, 0, z as i32);
+                    columns.push(self.generate_column(column_pos, &mut biome_noise_cache));
+                }
+            }
 
-                    let biome_conditions = self.biome_noise.get(block_pos, &mut biome_noise_cache);
-                    let biome = self.get_biome(biome_conditions);
+            columns
+        })
+    }
 
-                    let height = biome.get_height(block_pos, &mut height_noise);
-                    let depth = block_pos.y - height;
+    /// Shapes a chunk which is deep enough under every column to only be made of `block_type`, apart from caves
+    /// 
+    /// This gives the same blocks as [`Worldgen::shape_column`], but skips working out the depth and layers of each block
+    fn shape_uniform_chunk(&self, chunk_pos: ChunkPos, block_type: BlockType, columns: &[Column]) -> BlockStorage {
+        let mut blocks = BlockStorage::new_filled(block_type);
+
+        for (i, column) in columns.iter().enumerate() {
+            for y in 0..CHUNK_SIZE {
+                let local_block_pos = BlockPos::new((i / CHUNK_SIZE) as i32, y as i32, (i % CHUNK_SIZE) as i32);
+                let block_pos = BlockPos::from(chunk_pos) + local_block_pos;
 
-                    blocks.new_block(local_block_pos, biome.layers().get_block_at_depth(depth));
+                // this isn't the exact depth for density columns, but every block is deep enough that caves treat it the same
+                if self.enabled_caves().is_some_and(|caves| caves.is_carved(block_pos, block_pos.y - column.min_surface)) {
+                    blocks.new_block(local_block_pos, column.carved_block(block_pos.y));
                 }
             }
         }
 
-        blocks.into()
+        blocks
+    }
+
+    /// Fills in the blocks of one column of a chunk
+    fn shape_column(&self, chunk_pos: ChunkPos, local_column_pos: BlockPos, column: &Column, blocks: &mut BlockStorage) {
+        if let Some(density_biomes) = &column.density_biomes {
+            // start above the chunk so the distance to air is known for the blocks at the top of the chunk,
+            // any blocks further than this from air are deep enough to be the bottom block and to have cheese caves
+            let lookahead = (column.layers.thickness() as i32).max(CHEESE_MIN_DEPTH);
+            let mut air_distance = lookahead;
+
+            for y in (0..CHUNK_SIZE as i32 + lookahead).rev() {
+                let local_block_pos = local_column_pos + BlockPos::new(0, y, 0);
+                let block_pos = BlockPos::from(chunk_pos) + local_block_pos;
+
+                let depth = if self.get_density(block_pos, density_biomes) >= 0.0 {
+                    air_distance += 1;
+                    1 - air_distance
+                } else {
+                    air_distance = 0;
+                    1
+                };
+
+                if y < CHUNK_SIZE as i32 {
+                    let block_type = column.get_block(block_pos, depth, self.enabled_caves());
+                    if block_type != BlockType::Air {
+                        blocks.new_block(local_block_pos, block_type);
+                    }
+                }
+            }
+        } else {
+            for y in 0..CHUNK_SIZE as i32 {
+                let local_block_pos = local_column_pos + BlockPos::new(0, y, 0);
+                let block_pos = BlockPos::from(chunk_pos) + local_block_pos;
+
+                let depth = block_pos.y - column.height;
+
+                let block_type = column.get_block(block_pos, depth, self.enabled_caves());
+                if block_type != BlockType::Air {
+                    blocks.new_block(local_block_pos, block_type);
+                }
+            }
+        }
+    }
+
+    /// Returns false if the chunk is too far above or below the surface of every column around it for any feature to reach into it
+    fn features_can_reach(&self, chunk_pos: ChunkPos) -> bool {
+        let chunk_bottom = BlockPos::from(chunk_pos).y;
+        let chunk_top = chunk_bottom + CHUNK_SIZE as i32 - 1;
+
+        (-1..=1).any(|x| (-1..=1).any(|z| {
+            let footprint = self.footprint_columns(chunk_pos + ChunkPos::new(x, 0, z));
+            footprint.max_surface + MAX_FEATURE_HEIGHT >= chunk_bottom && footprint.min_surface - MAX_FEATURE_DEPTH <= chunk_top
+        }))
+    }
+
+    /// Stage 2: places features on the surface of the chunk
+    /// 
+    /// Features rooted in the columns around the chunk can reach into it, so their features are placed too.
+    /// Only the parts of features inside the chunk are kept,
+    /// so every chunk gets the same features no matter what order chunks are generated in.
+    fn decorate_chunk(&self, chunk_pos: ChunkPos, blocks: &mut BlockStorage) {
+        let chunk_min = BlockPos::from(chunk_pos);
+        let chunk_top = chunk_min.y + CHUNK_SIZE as i32 - 1;
+
+        // columns further than this outside the chunk can't have features that reach into it
+        let in_reach = |offset: i32| (-MAX_FEATURE_RADIUS..CHUNK_SIZE as i32 + MAX_FEATURE_RADIUS).contains(&offset);
+
+        let mut features = Vec::new();
+
+        for column_chunk_x in -1..=1 {
+            for column_chunk_z in -1..=1 {
+                let column_chunk = chunk_pos + ChunkPos::new(column_chunk_x, 0, column_chunk_z);
+                let footprint = self.footprint_columns(column_chunk);
+
+                for x in 0..CHUNK_SIZE {
+                    for z in 0..CHUNK_SIZE {
+                        let column_pos = BlockPos::from(column_chunk) + BlockPos::new(x as i32, 0, z as i32);
+                        let offset = column_pos - chunk_min;
+
+                        if !in_reach(offset.x) || !in_reach(offset.z) {
+                            continue;
+                        }
+
+                        let column = &footprint.columns[x * CHUNK_SIZE + z];
+
+                        if let Some(feature) = self.column_feature(column_pos, column, chunk_min.y, chunk_top) {
+                            features.push(feature);
+                        }
+                    }
+                }
+            }
+        }
+
+        // where features overlap the first one placed wins, so they are always placed in the same order
+        features.sort_by_key(|(root, _)| (root.x, root.z));
+
+        for (_, edits) in features {
+            for (block_pos, block_type) in edits {
+                if ChunkPos::from(block_pos) == chunk_pos {
+                    place_feature_block(blocks, block_pos.as_chunk_local(), block_type);
+                }
+            }
+        }
+    }
+
+    /// Gets the blocks of the feature on top of the column, if it has one that could reach between `min_y` and `max_y`
+    /// 
+    /// Returns the root of the feature along with its blocks
+    fn column_feature(&self, column_pos: BlockPos, column: &Column, min_y: i32, max_y: i32) -> Option<(BlockPos, Vec<(BlockPos, BlockType)>)> {
+        let features = self.biomes[column.biome as usize].features();
+        if features.is_empty() {
+            return None;
+        }
+
+        let height = self.surface_height(column_pos, column);
+        if height + MAX_FEATURE_HEIGHT < min_y || height - MAX_FEATURE_DEPTH > max_y {
+            return None;
+        }
+
+        let root = BlockPos::new(column_pos.x, height, column_pos.z);
+
+        // features can't grow under water or on a surface which a cave has carved away
+        if column.water_level > height || self.enabled_caves().is_some_and(|caves| caves.is_carved(root, 0)) {
+            return None;
+        }
+
+        let surface_block = column.layers.get_block_at_depth(0);
+
+        // only 1 feature can be placed on each column
+        let (i, config) = features.iter()
+            .enumerate()
+            .find(|(i, config)| config.grows_on.contains(&surface_block)
+                && position_random(self.seed, *root, FEATURE_SALT + 2 * *i as u64) < config.chance)?;
+
+        let mut rng = StdRng::seed_from_u64(position_hash(self.seed, *root, FEATURE_SALT + 2 * i as u64 + 1));
+        let mut edits = Vec::new();
+        config.feature.place(root, &mut rng, &mut edits);
+
+        Some((root, edits))
+    }
+}
+
+/// Places a block from a feature, features only replace air
+fn place_feature_block(blocks: &mut BlockStorage, local_block_pos: BlockPos, block_type: BlockType) {
+    if blocks.get(local_block_pos).is_air() {
+        blocks.new_block(local_block_pos, block_type);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::seq::SliceRandom;
+    use rustc_hash::FxHashMap;
+
+    use super::*;
+
+    /// Chunks around the origin, from deep underground to above the surface
+    fn test_area() -> Vec<ChunkPos> {
+        let mut chunks = Vec::new();
+        for x in -2..=1 {
+            for y in -3..=1 {
+                for z in -1..=1 {
+                    chunks.push(ChunkPos::new(x, y, z));
+                }
+            }
+        }
+
+        chunks
+    }
+
+    fn block_types(chunk: &ChunkData) -> Vec<BlockType> {
+        let size = CHUNK_SIZE as i32;
+
+        (0..size).flat_map(|x| (0..size).flat_map(move |y| (0..size).map(move |z| BlockPos::new(x, y, z))))
+            .map(|block_pos| chunk.blocks.get(block_pos).block_type())
+            .collect()
+    }
+
+    /// Finds a column using density where the terrain has air under solid ground, and returns the position of the air
+    fn find_overhang(worldgen: &Worldgen) -> Option<BlockPos> {
+        for x in (-2048..2048).step_by(32) {
+            for z in (-2048..2048).step_by(32) {
+                let column = worldgen.generate_column(BlockPos::new(x, 0, z), &mut Box::new(BiomeNoiseCache::default()));
+                let Some(density_biomes) = &column.density_biomes else {
+                    continue;
+                };
+
+                let is_solid = |y| worldgen.get_density(BlockPos::new(x, y, z), density_biomes) >= 0.0;
+
+                if let Some(y) = (column.min_surface..column.max_surface).find(|y| !is_solid(*y) && is_solid(*y + 1)) {
+                    return Some(BlockPos::new(x, y, z));
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Generates every chunk in `order` with a new worldgen, and returns the blocks of each chunk
+    fn generate_in_order(settings: &WorldgenSettings, order: &[ChunkPos]) -> FxHashMap<ChunkPos, Vec<BlockType>> {
+        let worldgen = Worldgen::new(settings.clone());
+
+        order.iter()
+            .map(|chunk_pos| (*chunk_pos, block_types(&worldgen.generate_chunk(*chunk_pos))))
+            .collect()
+    }
+
+    /// Generates the test area in ascending, reversed and shuffled order, and checks every chunk comes out the same
+    fn assert_order_independent(settings: WorldgenSettings) {
+        let ascending = test_area();
+
+        let mut reversed = ascending.clone();
+        reversed.reverse();
+
+        let mut shuffled = ascending.clone();
+        shuffled.shuffle(&mut StdRng::seed_from_u64(7));
+
+        let expected = generate_in_order(&settings, &ascending);
+
+        for order in [reversed, shuffled] {
+            let chunks = generate_in_order(&settings, &order);
+
+            for chunk_pos in ascending.iter() {
+                assert!(chunks[chunk_pos] == expected[chunk_pos], "chunk {chunk_pos:?} depends on the order chunks are generated in");
+            }
+        }
+    }
+
+    #[test]
+    fn caves_do_not_depend_on_generation_order() {
+        let settings = WorldgenSettings {
+            caves: true,
+            ores: false,
+            features: false,
+            ..Default::default()
+        };
+
+        // make sure there is something carved, or the test would pass without checking anything
+        let worldgen = Worldgen::new(settings.clone());
+        let carved = test_area().into_iter()
+            .filter(|chunk_pos| chunk_pos.y < -1)
+            .any(|chunk_pos| block_types(&worldgen.generate_chunk(chunk_pos)).contains(&BlockType::Air));
+        assert!(carved, "no caves were carved in the test area");
+
+        assert_order_independent(settings);
+    }
+
+    #[test]
+    fn features_do_not_depend_on_generation_order() {
+        let settings = WorldgenSettings::default();
+
+        let worldgen = Worldgen::new(settings.clone());
+        let has_trees = test_area().into_iter()
+            .any(|chunk_pos| block_types(&worldgen.generate_chunk(chunk_pos)).contains(&BlockType::Log));
+        assert!(has_trees, "no trees were placed in the test area");
+
+        assert_order_independent(settings);
+    }
+
+    #[test]
+    fn every_column_of_a_lake_has_the_same_water_level() {
+        let worldgen = Worldgen::new(WorldgenSettings::default());
+
+        // find a few lakes which are above the sea level
+        let mut lakes = Vec::<(Lake, i32)>::new();
+        for x in -16..16 {
+            for z in -16..16 {
+                let Some(lake) = worldgen.water.lake(BlockPos::new(x * 64, 0, z * 64)) else {
+                    continue;
+                };
+
+                if lakes.iter().any(|(other, _)| other.center == lake.center) {
+                    continue;
+                }
+
+                if let Some((water_level, _)) = worldgen.lake_water_level(&lake) {
+                    lakes.push((lake, water_level));
+                }
+            }
+        }
+        assert!(lakes.len() >= 3, "only found {} lakes", lakes.len());
+
+        for (lake, water_level) in lakes.into_iter().take(3) {
+            for rim_point in lake.rim_points() {
+                let rim_height = worldgen.get_blended_height(rim_point, worldgen.biome_noise.get_uncached(rim_point));
+                assert!((water_level as f64) < rim_height, "water level {water_level} is above the rim of the lake at {:?}", lake.center);
+            }
+
+            // every column the water can be next to is inside the rim
+            let extent = lake.radius.ceil() as i32 * 2;
+            let size = extent * 2 + 1;
+            let columns = worldgen.sample_area(lake.center.x - extent, lake.center.z - extent, size as u32, size as u32);
+            let column_at = |x: i32, z: i32| &columns[((z + extent) * size + x + extent) as usize];
+            let mut flooded_columns = 0;
+
+            for x in -extent + 1..extent {
+                for z in -extent + 1..extent {
+                    let column_pos = lake.center + BlockPos::new(x, 0, z);
+                    let column = column_at(x, z);
+
+                    if worldgen.water.lake_depth(&lake, column_pos) > 0.0 {
+                        assert_eq!(column.water_level, water_level, "column {column_pos:?} has a different water level from the rest of its lake");
+                    }
+                    if column.water_level != water_level || column.height >= water_level {
+                        continue;
+                    }
+                    flooded_columns += 1;
+
+                    // a neighbour outside the lake that is lower than the water would leave a wall of water in the air
+                    for (dx, dz) in [(1, 0), (-1, 0), (0, 1), (0, -1)] {
+                        let neighbour = column_at(x + dx, z + dz);
+                        if neighbour.water_level != water_level {
+                            assert!(
+                                neighbour.height >= water_level,
+                                "column next to flooded column {column_pos:?} is {} but the water level is {water_level}", neighbour.height,
+                            );
+                        }
+                    }
+                }
+            }
+
+            assert!(flooded_columns > 0, "lake at {:?} has no water in it", lake.center);
+        }
+    }
+
+    #[test]
+    fn fast_paths_give_the_same_blocks() {
+        let worldgen = Worldgen::new(WorldgenSettings::default());
+
+        // sky, surface and deep underground chunks
+        let mut chunks: Vec<_> = [4, 0, -1, -8].into_iter()
+            .flat_map(|chunk_y| (-1..=1).map(move |x| ChunkPos::new(x, chunk_y, 0)))
+            .collect();
+
+        // and the chunks around an overhang, where the columns use density
+        let overhang_chunk = ChunkPos::from(find_overhang(&worldgen).expect("no column using density has an overhang"));
+        chunks.extend((-1..=1).map(|y| overhang_chunk + ChunkPos::new(0, y, 0)));
+
+        for chunk_pos in chunks {
+            assert!(
+                block_types(&worldgen.generate_chunk(chunk_pos)) == block_types(&worldgen.generate_chunk_per_block(chunk_pos)),
+                "fast path changed the blocks of chunk {chunk_pos:?}",
+            );
+        }
+    }
+
+    #[test]
+    fn worldgens_with_different_seeds_run_side_by_side() {
+        let chunks = [ChunkPos::new(0, 0, 0), ChunkPos::new(1, -1, 0), ChunkPos::new(-1, 0, 2)];
+        let settings = [1, 2].map(|seed| WorldgenSettings {
+            seed,
+            ..Default::default()
+        });
+
+        // generated one after another, each with a worldgen of its own
+        let expected = settings.clone().map(|settings| generate_in_order(&settings, &chunks));
+
+        // both worldgens are shared between threads which generate at the same time
+        let worldgens = settings.map(|settings| Arc::new(Worldgen::new(settings)));
+        let threads = (0..4)
+            .map(|i| {
+                let worldgen = worldgens[i % 2].clone();
+                std::thread::spawn(move || {
+                    chunks.iter()
+                        .map(|chunk_pos| (*chunk_pos, block_types(&worldgen.generate_chunk(*chunk_pos))))
+                        .collect::<FxHashMap<_, _>>()
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for (i, thread) in threads.into_iter().enumerate() {
+            let chunks = thread.join().unwrap();
+            assert!(chunks == expected[i % 2], "worldgen with seed {} generated different chunks when run in parallel", worldgens[i % 2].seed());
+        }
+
+        assert!(expected[0] != expected[1], "different seeds generated the same chunks");
+    }
+
+    #[test]
+    fn sampling_an_area_matches_sampling_each_column() {
+        let worldgen = Worldgen::new(WorldgenSettings::default());
+
+        // the area does not line up with chunks, so it has partial chunks on every side
+        let (x, z, width, depth) = (-40, 5, 75, 41);
+        let samples = worldgen.sample_area(x, z, width, depth);
+        assert_eq!(samples.len(), (width * depth) as usize);
+
+        for column_z in 0..depth as i32 {
+            for column_x in 0..width as i32 {
+                let sample = samples[(column_z * width as i32 + column_x) as usize];
+                let expected = worldgen.sample_column(x + column_x, z + column_z);
+
+                assert_eq!(sample.height, expected.height);
+                assert_eq!(sample.water_level, expected.water_level);
+                assert_eq!(sample.biome, expected.biome);
+                assert_eq!(sample.surface_block, expected.surface_block);
+            }
+        }
+    }
+
+    #[test]
+    fn caves_under_water_are_flooded() {
+        let worldgen = Worldgen::new(WorldgenSettings::default());
+        let size = CHUNK_SIZE as i32;
+
+        let mut flooded_cave_blocks = 0;
+
+        for chunk_x in -4..4 {
+            for chunk_z in -4..4 {
+                let samples = worldgen.sample_area(chunk_x * size, chunk_z * size, size as u32, size as u32);
+                let under_water = |sample: &ColumnSample| sample.water_level > sample.height;
+
+                let Some(lowest_floor) = samples.iter().filter(|sample| under_water(sample)).map(|sample| sample.height).min() else {
+                    continue;
+                };
+                let highest_water = samples.iter().map(|sample| sample.water_level).max().unwrap();
+
+                // the chunks under the water down to a chunk below the lowest floor, so caves under the floor are included
+                for chunk_y in lowest_floor.div_euclid(size) - 1..=highest_water.div_euclid(size) {
+                    let chunk_pos = ChunkPos::new(chunk_x, chunk_y, chunk_z);
+                    let chunk = worldgen.generate_chunk(chunk_pos);
+
+                    for (i, sample) in samples.iter().enumerate() {
+                        if !under_water(sample) {
+                            continue;
+                        }
+
+                        for y in 0..size {
+                            let local_block_pos = BlockPos::new(i as i32 % size, y, i as i32 / size);
+                            let block_pos = BlockPos::from(chunk_pos) + local_block_pos;
+                            if block_pos.y > sample.water_level {
+                                continue;
+                            }
+
+                            let block_type = chunk.blocks.get(local_block_pos).block_type();
+                            assert_ne!(block_type, BlockType::Air, "{block_pos:?} is air under the water");
+
+                            if block_type == BlockType::Water && block_pos.y <= sample.height {
+                                flooded_cave_blocks += 1;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        assert!(flooded_cave_blocks > 0, "no caves were carved under water");
+    }
+
+    #[test]
+    fn heights_are_continuous_across_biome_borders() {
+        let worldgen = Worldgen::new(WorldgenSettings::default());
+
+        let main_biome = |block: BlockPos| {
+            worldgen.biome_map.get_blend(worldgen.biome_noise.get_uncached(block)).iter()
+                .max_by(|(_, a), (_, b)| a.total_cmp(b))
+                .unwrap().0
+        };
+
+        let mut borders = 0;
+        let mut largest_biome_difference = 0.0f64;
+
+        // walk along a few rows, comparing each column to the one before it
+        for z in (-1024..1024).step_by(256) {
+            let mut previous: Option<(BiomeType, f64)> = None;
+
+            for x in -1024..1024 {
+                let block = BlockPos::new(x, 0, z);
+                let biome = main_biome(block);
+                let height = worldgen.get_blended_height(block, worldgen.biome_noise.get_uncached(block));
+
+                if let Some((previous_biome, previous_height)) = previous && previous_biome != biome {
+                    borders += 1;
+                    assert!(
+                        (height - previous_height).abs() < 2.0,
+                        "height jumps from {previous_height} to {height} at the border between {previous_biome:?} and {biome:?} at {block:?}",
+                    );
+
+                    let biome_difference = worldgen.biomes[biome as usize].get_height(block) - worldgen.biomes[previous_biome as usize].get_height(block);
+                    largest_biome_difference = largest_biome_difference.max(biome_difference.abs());
+                }
+
+                previous = Some((biome, height));
+            }
+        }
+
+        assert!(borders > 0, "no biome borders were found");
+        // without blending some borders would be cliffs
+        assert!(largest_biome_difference > 10.0, "the biomes at every border were already the same height");
+    }
+
+    #[test]
+    fn density_terrain_has_overhangs() {
+        let worldgen = Worldgen::new(WorldgenSettings {
+            caves: false,
+            ..WorldgenSettings::default()
+        });
+
+        let air_pos = find_overhang(&worldgen).expect("no column using density has an overhang");
+        let block_type = |block_pos: BlockPos| {
+            worldgen.generate_chunk(ChunkPos::from(block_pos)).blocks.get(block_pos.as_chunk_local()).block_type()
+        };
+
+        assert!(block_type(air_pos).is_replaceable(), "{air_pos:?} is under an overhang but is not air");
+        assert!(!block_type(air_pos + BlockPos::new(0, 1, 0)).is_replaceable(), "there is no overhang above {air_pos:?}");
     }
 }
\ No newline at end of file
@@ -0,0 +1,142 @@
+//! Places veins of ore after caves are carved
+//! 
+//! Like worm caves, veins are simulated from the chunk they start in,
+//! so a vein crossing a chunk border is placed the same no matter which chunk is generated first.
+
+use bevy::math::IVec3;
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+
+use crate::blocks::{BlockStorage, BlockType};
+use crate::world::CHUNK_SIZE;
+use crate::types::*;
+
+use super::random::position_hash;
+
+/// Salt of the first ore, each ore uses its index added to this
+const ORE_SALT: u64 = 16;
+
+/// Describes where and how often an ore spawns
+pub struct OreConfig {
+    pub block: BlockType,
+    /// Veins only start between these heights (inclusive)
+    pub min_height: i32,
+    pub max_height: i32,
+    /// Number of blocks in each vein
+    /// 
+    /// This must be at most the size of a chunk, since veins can only reach 1 chunk away from where they start
+    pub vein_size: u32,
+    /// Average number of veins that start in each chunk
+    pub veins_per_chunk: f64,
+    /// Blocks the ore is allowed to replace
+    pub replaces: &'static [BlockType],
+}
+
+const ORES: &[OreConfig] = &[
+    OreConfig {
+        block: BlockType::CoalOre,
+        min_height: -128,
+        max_height: 64,
+        vein_size: 14,
+        veins_per_chunk: 4.0,
+        replaces: &[BlockType::Stone],
+    },
+    OreConfig {
+        block: BlockType::IronOre,
+        min_height: -256,
+        max_height: 0,
+        vein_size: 8,
+        veins_per_chunk: 2.5,
+        replaces: &[BlockType::Stone],
+    },
+    OreConfig {
+        block: BlockType::GoldOre,
+        min_height: -512,
+        max_height: -64,
+        vein_size: 6,
+        veins_per_chunk: 0.8,
+        replaces: &[BlockType::Stone, BlockType::Sandstone],
+    },
+];
+
+const VEIN_DIRECTIONS: [IVec3; 6] = [
+    IVec3::X,
+    IVec3::NEG_X,
+    IVec3::Y,
+    IVec3::NEG_Y,
+    IVec3::Z,
+    IVec3::NEG_Z,
+];
+
+#[derive(Debug)]
+pub struct OreGen {
+    seed: u64,
+}
+
+impl OreGen {
+    pub fn new(seed: u64) -> Self {
+        OreGen {
+            seed,
+        }
+    }
+
+    /// Places every vein that reaches into the given chunk
+    pub fn place_ores(&self, chunk_pos: ChunkPos, blocks: &mut BlockStorage) {
+        if blocks.is_empty() {
+            // there is nothing to replace
+            return;
+        }
+
+        for (i, ore) in ORES.iter().enumerate() {
+            for x in -1..=1 {
+                for y in -1..=1 {
+                    for z in -1..=1 {
+                        let start_chunk = chunk_pos + ChunkPos::new(x, y, z);
+                        self.place_veins(ore, ORE_SALT + i as u64, start_chunk, blocks, chunk_pos);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Simulates all the veins of one ore starting in `start_chunk`, and places the blocks inside `chunk_pos`
+    fn place_veins(&self, ore: &OreConfig, salt: u64, start_chunk: ChunkPos, blocks: &mut BlockStorage, chunk_pos: ChunkPos) {
+        let start_min = BlockPos::from(start_chunk);
+        let start_top = start_min.y + CHUNK_SIZE as i32 - 1;
+        if start_top < ore.min_height || start_min.y > ore.max_height {
+            return;
+        }
+
+        let mut rng = StdRng::seed_from_u64(position_hash(self.seed, *start_chunk, salt));
+
+        let mut vein_count = ore.veins_per_chunk.floor() as u32;
+        if rng.gen_bool(ore.veins_per_chunk.fract()) {
+            vein_count += 1;
+        }
+
+        let chunk_min = BlockPos::from(chunk_pos);
+
+        for _ in 0..vein_count {
+            let mut position = *start_min + IVec3::new(
+                rng.gen_range(0..CHUNK_SIZE as i32),
+                rng.gen_range(0..CHUNK_SIZE as i32),
+                rng.gen_range(0..CHUNK_SIZE as i32),
+            );
+
+            // the whole vein is always simulated, even if it isn't placed, so the rng stays the same for the next vein
+            let in_height_range = (ore.min_height..=ore.max_height).contains(&position.y);
+
+            for _ in 0..ore.vein_size {
+                let local_block_pos = BlockPos(position - *chunk_min);
+                let in_chunk = local_block_pos.cmpge(IVec3::ZERO).all()
+                    && local_block_pos.cmplt(IVec3::splat(CHUNK_SIZE as i32)).all();
+
+                if in_height_range && in_chunk && ore.replaces.contains(&blocks.get(local_block_pos).block_type()) {
+                    blocks.new_block(local_block_pos, ore.block);
+                }
+
+                position += VEIN_DIRECTIONS[rng.gen_range(0..VEIN_DIRECTIONS.len())];
+            }
+        }
+    }
+}
\ No newline at end of file
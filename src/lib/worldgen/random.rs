@@ -0,0 +1,23 @@
+use bevy::prelude::IVec3;
+
+/// Mixes the bits of `n` so that similar inputs give very different outputs (splitmix64 finalizer)
+fn mix(mut n: u64) -> u64 {
+    n = (n ^ (n >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    n = (n ^ (n >> 27)).wrapping_mul(0x94d049bb133111eb);
+    n ^ (n >> 31)
+}
+
+/// Gets a random number that is always the same for a given seed, position and salt
+/// 
+/// The salt is used so different features at the same position get different numbers
+pub fn position_hash(seed: u64, position: IVec3, salt: u64) -> u64 {
+    let mut n = mix(seed ^ salt);
+    n = mix(n ^ position.x as u32 as u64);
+    n = mix(n ^ position.y as u32 as u64);
+    mix(n ^ position.z as u32 as u64)
+}
+
+/// Same as [`position_hash`], but returns a number in the range 0 to 1
+pub fn position_random(seed: u64, position: IVec3, salt: u64) -> f64 {
+    (position_hash(seed, position, salt) >> 11) as f64 / (1u64 << 53) as f64
+}
\ No newline at end of file
@@ -0,0 +1,156 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Seed used when none is given
+pub const DEFAULT_SEED: u64 = 128947;
+
+/// Everything that changes what the worldgen generates
+/// 
+/// These can be loaded from a config file with one `key = value` pair on each line, for example:
+/// 
+/// ```text
+/// # lines starting with # are comments
+/// seed = 1234
+/// caves = false
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorldgenSettings {
+    pub seed: u64,
+    pub caves: bool,
+    pub ores: bool,
+    /// Trees, boulders and other features placed on the surface
+    pub features: bool,
+}
+
+impl Default for WorldgenSettings {
+    fn default() -> Self {
+        WorldgenSettings {
+            seed: DEFAULT_SEED,
+            caves: true,
+            ores: true,
+            features: true,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum WorldgenConfigError {
+    Io(io::Error),
+    /// A line was not a `key = value` pair, the line number starts at 1
+    InvalidLine(usize),
+    UnknownKey(String),
+    InvalidValue {
+        key: String,
+        value: String,
+    },
+}
+
+impl fmt::Display for WorldgenConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "failed to read worldgen config: {error}"),
+            Self::InvalidLine(line) => write!(f, "line {line} is not a key = value pair"),
+            Self::UnknownKey(key) => write!(f, "unknown worldgen setting: {key}"),
+            Self::InvalidValue { key, value } => write!(f, "invalid value for {key}: {value}"),
+        }
+    }
+}
+
+impl std::error::Error for WorldgenConfigError {}
+
+impl WorldgenSettings {
+    pub fn load(path: &Path) -> Result<Self, WorldgenConfigError> {
+        let config = fs::read_to_string(path).map_err(WorldgenConfigError::Io)?;
+        Self::parse(&config)
+    }
+
+    /// Parses a config, any setting not in the config is left as the default
+    pub fn parse(config: &str) -> Result<Self, WorldgenConfigError> {
+        let mut settings = WorldgenSettings::default();
+
+        for (i, line) in config.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (key, value) = line.split_once('=').ok_or(WorldgenConfigError::InvalidLine(i + 1))?;
+            let (key, value) = (key.trim(), value.trim());
+
+            let invalid_value = || WorldgenConfigError::InvalidValue {
+                key: key.to_owned(),
+                value: value.to_owned(),
+            };
+
+            match key {
+                "seed" => settings.seed = value.parse().map_err(|_| invalid_value())?,
+                "caves" => settings.caves = value.parse().map_err(|_| invalid_value())?,
+                "ores" => settings.ores = value.parse().map_err(|_| invalid_value())?,
+                "features" => settings.features = value.parse().map_err(|_| invalid_value())?,
+                _ => return Err(WorldgenConfigError::UnknownKey(key.to_owned())),
+            }
+        }
+
+        Ok(settings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_config_is_default() {
+        assert_eq!(WorldgenSettings::parse("").unwrap(), WorldgenSettings::default());
+        assert_eq!(WorldgenSettings::parse("\n   \n# only a comment\n").unwrap(), WorldgenSettings::default());
+    }
+
+    #[test]
+    fn parses_every_setting() {
+        let config = "
+            # a comment
+            seed = 1234
+            caves=false
+              ores   =   false
+
+            features = false
+        ";
+
+        assert_eq!(WorldgenSettings::parse(config).unwrap(), WorldgenSettings {
+            seed: 1234,
+            caves: false,
+            ores: false,
+            features: false,
+        });
+    }
+
+    #[test]
+    fn missing_settings_are_default() {
+        assert_eq!(WorldgenSettings::parse("ores = false").unwrap(), WorldgenSettings {
+            ores: false,
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    fn later_lines_override_earlier_ones() {
+        assert_eq!(WorldgenSettings::parse("seed = 1\nseed = 2").unwrap().seed, 2);
+    }
+
+    #[test]
+    fn invalid_configs_are_errors() {
+        assert!(matches!(WorldgenSettings::parse("seed = 1\ncaves"), Err(WorldgenConfigError::InvalidLine(2))));
+        assert!(matches!(WorldgenSettings::parse("rivers = true"), Err(WorldgenConfigError::UnknownKey(key)) if key == "rivers"));
+        assert!(matches!(WorldgenSettings::parse("seed = -1"), Err(WorldgenConfigError::InvalidValue { key, value }) if key == "seed" && value == "-1"));
+        assert!(matches!(WorldgenSettings::parse("caves = yes"), Err(WorldgenConfigError::InvalidValue { key, .. }) if key == "caves"));
+        assert!(matches!(WorldgenSettings::parse("seed ="), Err(WorldgenConfigError::InvalidValue { .. })));
+    }
+
+    #[test]
+    fn missing_file_is_an_error() {
+        let path = std::env::temp_dir().join("minecone-worldgen-config-that-does-not-exist");
+        assert!(matches!(WorldgenSettings::load(&path), Err(WorldgenConfigError::Io(_))));
+    }
+}
\ No newline at end of file
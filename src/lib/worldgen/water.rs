@@ -0,0 +1,176 @@
+//! Shapes the terrain around water, and decides where water goes
+//! 
+//! All air at or below the sea level is filled with water, so oceans and rivers only need the terrain to be low enough.
+//! Rivers carve valleys down below the sea level, and lakes carve basins above the sea level with their own water level.
+//! Each lake is placed in a cell of a grid, so every column of a lake can find the middle and rim of the lake it is in.
+
+use std::f64::consts::TAU;
+
+use bevy::math::{DVec2, IVec3};
+use noise::{Fbm, MultiFractal, NoiseFn, OpenSimplex};
+use rand::{Rng, RngCore, SeedableRng};
+use rand::rngs::StdRng;
+
+use crate::types::*;
+use super::random::{position_hash, position_random};
+
+/// Every block of air at or below this height is water
+pub const SEA_LEVEL: i32 = -4;
+
+/// Rivers and lakes are only in columns with a `biome_height` below this, so they don't cut through mountains
+const LOWLAND_BIOME_HEIGHT: f64 = 0.2;
+/// Rivers and lakes fade out over this range of `biome_height` below [`LOWLAND_BIOME_HEIGHT`]
+const LOWLAND_FADE: f64 = 0.1;
+
+/// Rivers are where the river noise is within this distance of 0
+const RIVER_WIDTH: f64 = 0.02;
+/// The valley around a river is where the river noise is within this distance of 0
+const RIVER_VALLEY_WIDTH: f64 = 0.08;
+/// Depth of the middle of a river below the sea level
+const RIVER_DEPTH: f64 = 3.0;
+
+/// Width and depth of the cells of the lake grid, each cell has at most 1 lake
+const LAKE_CELL_SIZE: i32 = 256;
+/// Chance that a cell has a lake
+const LAKE_CHANCE: f64 = 0.4;
+const LAKE_MIN_RADIUS: f64 = 12.0;
+const LAKE_MAX_RADIUS: f64 = 32.0;
+/// How far the lake noise can move the shore in or out, as a fraction of the radius
+const LAKE_SHORE_NOISE: f64 = 0.25;
+/// Distance past the furthest the shore can be that the rim of a lake is sampled at
+const LAKE_RIM_MARGIN: f64 = 2.0;
+/// Number of points around a lake its rim is sampled at
+const LAKE_RIM_SAMPLES: usize = 8;
+/// Depth of the deepest part of a lake
+const LAKE_DEPTH: f64 = 8.0;
+/// Salt for the chance of a cell having a lake, the salt after this is used for the lake's size and position
+const LAKE_SALT: u64 = 8;
+
+/// Columns with a surface this close to the sea level are beaches
+const BEACH_MIN_HEIGHT: i32 = SEA_LEVEL - 2;
+const BEACH_MAX_HEIGHT: i32 = SEA_LEVEL + 1;
+
+fn smoothstep(t: f64) -> f64 {
+    let t = t.clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// Returns true if a column with a surface at `height` is part of a beach
+pub fn is_beach(height: i32) -> bool {
+    (BEACH_MIN_HEIGHT..=BEACH_MAX_HEIGHT).contains(&height)
+}
+
+/// A lake in one of the cells of the lake grid
+#[derive(Debug, Clone, Copy)]
+pub struct Lake {
+    /// Column in the middle of the lake, which has a y of 0
+    pub center: BlockPos,
+    pub radius: f64,
+}
+
+impl Lake {
+    /// Distance from the center to the rim, which is just past where the shore can be
+    fn rim_distance(&self) -> f64 {
+        self.radius * (1.0 + LAKE_SHORE_NOISE) + LAKE_RIM_MARGIN
+    }
+
+    /// Columns around the lake on its rim, which the water level must stay below
+    pub fn rim_points(&self) -> impl Iterator<Item = BlockPos> {
+        let center = self.center;
+        let distance = self.rim_distance();
+
+        (0..LAKE_RIM_SAMPLES).map(move |i| {
+            let angle = i as f64 / LAKE_RIM_SAMPLES as f64 * TAU;
+            center + BlockPos::new((angle.cos() * distance).round() as i32, 0, (angle.sin() * distance).round() as i32)
+        })
+    }
+
+    /// Returns true if the column is inside the rim of the lake, which includes every column next to the water
+    pub fn rim_contains(&self, block: BlockPos) -> bool {
+        let offset = DVec2::new((block.x - self.center.x) as f64, (block.z - self.center.z) as f64);
+        offset.length() <= self.rim_distance()
+    }
+}
+
+#[derive(Debug)]
+pub struct WaterGen {
+    seed: u64,
+    river_noise: Fbm<OpenSimplex>,
+    lake_noise: Fbm<OpenSimplex>,
+}
+
+impl WaterGen {
+    pub fn new(seed: u64, seed_rng: &mut StdRng) -> Self {
+        WaterGen {
+            seed,
+            river_noise: Fbm::new(seed_rng.next_u32())
+                .set_octaves(3)
+                .set_frequency(0.003),
+            lake_noise: Fbm::new(seed_rng.next_u32())
+                .set_octaves(2)
+                .set_frequency(0.01),
+        }
+    }
+
+    /// Returns 1 in lowlands, fading to 0 in highlands
+    pub fn lowland_factor(biome_height: f64) -> f64 {
+        smoothstep((LOWLAND_BIOME_HEIGHT - biome_height) / LOWLAND_FADE)
+    }
+
+    /// Lowers the height of the terrain to carve a river valley, and returns the new height
+    pub fn carve_river(&self, block: BlockPos, height: f64, biome_height: f64) -> f64 {
+        let river_distance = self.river_noise.get(block.as_noise_point_2d()).abs();
+        if river_distance >= RIVER_VALLEY_WIDTH {
+            return height;
+        }
+
+        let lowland = Self::lowland_factor(biome_height);
+        if lowland == 0.0 {
+            return height;
+        }
+
+        // the river bed is deepest in the middle, and rises to the sea level at the edges of the river
+        let river_bed = SEA_LEVEL as f64 - RIVER_DEPTH * smoothstep(1.0 - river_distance / RIVER_WIDTH);
+        let valley = smoothstep(1.0 - river_distance / RIVER_VALLEY_WIDTH) * lowland;
+
+        if height > river_bed {
+            height + (river_bed - height) * valley
+        } else {
+            height
+        }
+    }
+
+    /// Gets the lake of the cell the column is in, if the cell has one
+    pub fn lake(&self, block: BlockPos) -> Option<Lake> {
+        let cell = IVec3::new(block.x.div_euclid(LAKE_CELL_SIZE), 0, block.z.div_euclid(LAKE_CELL_SIZE));
+        if position_random(self.seed, cell, LAKE_SALT) >= LAKE_CHANCE {
+            return None;
+        }
+
+        let mut rng = StdRng::seed_from_u64(position_hash(self.seed, cell, LAKE_SALT + 1));
+        let radius = rng.gen_range(LAKE_MIN_RADIUS..LAKE_MAX_RADIUS);
+
+        // the lake and its rim always fit in the cell, so columns never need to look at the lakes of other cells
+        let margin = (LAKE_MAX_RADIUS * (1.0 + LAKE_SHORE_NOISE) + LAKE_RIM_MARGIN).ceil() as i32;
+        let offset = IVec3::new(
+            rng.gen_range(margin..LAKE_CELL_SIZE - margin),
+            0,
+            rng.gen_range(margin..LAKE_CELL_SIZE - margin),
+        );
+
+        Some(Lake {
+            center: BlockPos(cell * LAKE_CELL_SIZE + offset),
+            radius,
+        })
+    }
+
+    /// Gets how deep the lake basin is at the column, or 0 if the column is outside the lake
+    pub fn lake_depth(&self, lake: &Lake, block: BlockPos) -> f64 {
+        let offset = DVec2::new((block.x - lake.center.x) as f64, (block.z - lake.center.z) as f64);
+
+        // the noise makes the shore wobble instead of being a perfect circle
+        let distance = offset.length() / lake.radius + self.lake_noise.get(block.as_noise_point_2d()) * LAKE_SHORE_NOISE;
+
+        LAKE_DEPTH * smoothstep(1.0 - distance)
+    }
+}
\ No newline at end of file
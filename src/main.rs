@@ -1,10 +1,122 @@
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::path::PathBuf;
+use std::process;
+
 use bevy::prelude::*;
 
 use minecone::MineconePlugin;
+use minecone::net::{NetClientPlugin, DEFAULT_PORT};
+use minecone::replay::{InputRecording, InputRecorderPlugin, InputReplayPlugin};
+use minecone::worldgen::WorldgenSettings;
+
+const USAGE: &str = "usage: minecone [--connect <address>] [--record <file> | --replay <file>] [--worldgen-config <file>] [--seed <seed>]";
+
+struct Args {
+    server_address: Option<SocketAddr>,
+    record_path: Option<PathBuf>,
+    replay_path: Option<PathBuf>,
+    worldgen: WorldgenSettings,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut server_address = None;
+    let mut record_path = None;
+    let mut replay_path = None;
+    let mut worldgen_config_path = None;
+    let mut seed = None;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--connect" => {
+                let address = args.next().ok_or("--connect needs an address")?;
+
+                // use the default port if none is given
+                let address = if address.contains(':') {
+                    address
+                } else {
+                    format!("{address}:{DEFAULT_PORT}")
+                };
+
+                server_address = address.to_socket_addrs()
+                    .ok()
+                    .and_then(|mut addresses| addresses.next());
+
+                if server_address.is_none() {
+                    return Err(format!("invalid server address: {address}"));
+                }
+            },
+            "--record" => {
+                record_path = Some(PathBuf::from(args.next().ok_or("--record needs a file")?));
+            },
+            "--replay" => {
+                replay_path = Some(PathBuf::from(args.next().ok_or("--replay needs a file")?));
+            },
+            "--worldgen-config" => {
+                worldgen_config_path = Some(PathBuf::from(args.next().ok_or("--worldgen-config needs a file")?));
+            },
+            "--seed" => {
+                seed = Some(args.next()
+                    .and_then(|n| n.parse::<u64>().ok())
+                    .ok_or("--seed needs a number")?);
+            },
+            arg => return Err(format!("unknown argument: {arg}")),
+        }
+    }
+
+    if record_path.is_some() && replay_path.is_some() {
+        return Err("--record and --replay can not be used together".to_owned());
+    }
+
+    if server_address.is_some() && (record_path.is_some() || replay_path.is_some()) {
+        return Err("recording and replaying only work in singleplayer".to_owned());
+    }
+
+    // the seed given on the command line overrides the one in the config
+    let mut worldgen = match worldgen_config_path {
+        Some(path) => WorldgenSettings::load(&path).map_err(|error| format!("{}: {error}", path.display()))?,
+        None => WorldgenSettings::default(),
+    };
+
+    if let Some(seed) = seed {
+        worldgen.seed = seed;
+    }
+
+    Ok(Args {
+        server_address,
+        record_path,
+        replay_path,
+        worldgen,
+    })
+}
 
 fn main() {
-    App::new()
-        .add_plugins(DefaultPlugins
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(error) => {
+            eprintln!("{error}\n{USAGE}");
+            process::exit(1);
+        },
+    };
+
+    let mut worldgen = args.worldgen;
+
+    let recording = args.replay_path.map(|path| match InputRecording::load(&path) {
+        Ok(recording) => recording,
+        Err(error) => {
+            eprintln!("{}: {error}", path.display());
+            process::exit(1);
+        },
+    });
+
+    // replays have to generate the same world they were recorded in
+    if let Some(recording) = &recording {
+        worldgen = recording.worldgen.clone();
+    }
+
+    let mut app = App::new();
+
+    app.add_plugins(DefaultPlugins
             .set(WindowPlugin {
                 primary_window: Some(Window {
                     title: "Minecone".into(),
@@ -13,6 +125,19 @@ fn main() {
                 ..Default::default()
             })
         )
-        .add_plugins(MineconePlugin)
-        .run();
+        .add_plugins(MineconePlugin { worldgen });
+
+    if let Some(address) = args.server_address {
+        app.add_plugins(NetClientPlugin { address });
+    }
+
+    if let Some(path) = args.record_path {
+        app.add_plugins(InputRecorderPlugin { path });
+    }
+
+    if let Some(recording) = recording {
+        app.add_plugins(InputReplayPlugin { recording });
+    }
+
+    app.run();
 }